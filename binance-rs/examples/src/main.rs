@@ -1,4 +1,5 @@
 extern crate binance;
+extern crate rust_decimal;
 
 use binance::api::*;
 use binance::general::*;
@@ -8,6 +9,7 @@ use binance::userstream::*;
 use binance::websockets::*;
 use binance::model::{AccountUpdateEvent, KlineEvent, OrderTradeEvent,
                      TradesEvent, DayTickerEvent, OrderBook, DepthOrderBookEvent};
+use rust_decimal::Decimal;
 
 fn main() {
     general();
@@ -52,22 +54,22 @@ fn account() {
         Err(e) => println!("Error: {}", e),
     }
 
-    match account.limit_buy("WTCETH", 10, 0.014000) {
+    match account.limit_buy("WTCETH", Decimal::new(10, 0), Decimal::new(14, 3)) {
         Ok(answer) => println!("{:?}", answer),
         Err(e) => println!("Error: {}", e),
     }
 
-    match account.market_buy("WTCETH", 5) {
+    match account.market_buy("WTCETH", Decimal::new(5, 0)) {
         Ok(answer) => println!("{:?}", answer),
         Err(e) => println!("Error: {}", e),
     }
 
-    match account.limit_sell("WTCETH", 10, 0.035000) {
+    match account.limit_sell("WTCETH", Decimal::new(10, 0), Decimal::new(35, 3)) {
         Ok(answer) => println!("{:?}", answer),
         Err(e) => println!("Error: {}", e),
     }
 
-    match account.market_sell("WTCETH", 5) {
+    match account.market_sell("WTCETH", Decimal::new(5, 0)) {
         Ok(answer) => println!("{:?}", answer),
         Err(e) => println!("Error: {}", e),
     }
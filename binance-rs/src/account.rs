@@ -3,6 +3,7 @@ use model::*;
 use client::*;
 use errors::*;
 use std::collections::BTreeMap;
+use rust_decimal::Decimal;
 use serde_json::from_str;
 
 static ORDER_TYPE_LIMIT: &'static str = "LIMIT";
@@ -21,8 +22,8 @@ pub struct Account {
 
 struct OrderRequest {
     pub symbol: String,
-    pub qty: f64,
-    pub price: f64,
+    pub qty: Decimal,
+    pub price: Decimal,
     pub order_side: String,
     pub order_type: String,
     pub time_in_force: String
@@ -126,12 +127,12 @@ impl Account {
     }
 
     // Place a LIMIT order - BUY
-    pub fn limit_buy<S, F>(&self, symbol: S, qty: F, price: f64) -> Result<(Transaction)>
-        where S: Into<String>, F: Into<f64>
+    pub fn limit_buy<S>(&self, symbol: S, qty: Decimal, price: Decimal) -> Result<(Transaction)>
+        where S: Into<String>
     {
         let buy: OrderRequest = OrderRequest {
             symbol: symbol.into(),
-            qty: qty.into(),
+            qty: qty,
             price: price,
             order_side: ORDER_SIDE_BUY.to_string(),
             order_type: ORDER_TYPE_LIMIT.to_string(),
@@ -146,12 +147,12 @@ impl Account {
     }
 
     // Place a LIMIT order - SELL
-    pub fn limit_sell<S, F>(&self, symbol: S, qty: F, price: f64) -> Result<(Transaction)>
-        where S: Into<String>, F: Into<f64>
+    pub fn limit_sell<S>(&self, symbol: S, qty: Decimal, price: Decimal) -> Result<(Transaction)>
+        where S: Into<String>
     {
         let sell: OrderRequest = OrderRequest {
             symbol: symbol.into(),
-            qty: qty.into(),
+            qty: qty,
             price: price,
             order_side: ORDER_SIDE_SELL.to_string(),
             order_type: ORDER_TYPE_LIMIT.to_string(),
@@ -166,13 +167,13 @@ impl Account {
     }
 
     // Place a MARKET order - BUY
-    pub fn market_buy<S, F>(&self, symbol: S, qty: F) -> Result<(Transaction)>
-        where S: Into<String>, F: Into<f64>
+    pub fn market_buy<S>(&self, symbol: S, qty: Decimal) -> Result<(Transaction)>
+        where S: Into<String>
     {
         let buy: OrderRequest = OrderRequest {
             symbol: symbol.into(),
-            qty: qty.into(),
-            price: 0.0,
+            qty: qty,
+            price: Decimal::ZERO,
             order_side: ORDER_SIDE_BUY.to_string(),
             order_type: ORDER_TYPE_MARKET.to_string(),
             time_in_force: TIME_IN_FORCE_GTC.to_string()
@@ -186,13 +187,13 @@ impl Account {
     }
 
     // Place a MARKET order - SELL
-    pub fn market_sell<S, F>(&self, symbol: S, qty: F) -> Result<(Transaction)>
-        where S: Into<String>, F: Into<f64>
+    pub fn market_sell<S>(&self, symbol: S, qty: Decimal) -> Result<(Transaction)>
+        where S: Into<String>
     {
         let sell: OrderRequest = OrderRequest {
             symbol: symbol.into(),
-            qty: qty.into(),
-            price: 0.0,
+            qty: qty,
+            price: Decimal::ZERO,
             order_side: ORDER_SIDE_SELL.to_string(),
             order_type: ORDER_TYPE_MARKET.to_string(),
             time_in_force: TIME_IN_FORCE_GTC.to_string()
@@ -242,7 +243,7 @@ impl Account {
         order_parameters.insert("type".into(), order.order_type);
         order_parameters.insert("quantity".into(), order.qty.to_string());
 
-        if order.price != 0.0 {
+        if order.price != Decimal::ZERO {
             order_parameters.insert("price".into(), order.price.to_string());
             order_parameters.insert("timeInForce".into(), order.time_in_force);
         }
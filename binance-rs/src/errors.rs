@@ -1,6 +1,8 @@
 use std;
 use reqwest;
 use url;
+use rust_decimal;
+use serde_json;
 
 error_chain! {
     types {
@@ -14,6 +16,8 @@ error_chain! {
         IoError(std::io::Error);
         ParseFloatError(std::num::ParseFloatError);
         UrlParserError(url::ParseError);
+        DecimalError(rust_decimal::Error);
+        JsonError(serde_json::Error);
     }
 
 }
@@ -25,4 +25,13 @@ impl General {
 
         Ok(server_time)
     }
+
+    // Current exchange trading rules and symbol information
+    pub fn exchange_info(&self) -> Result<(ExchangeInformation)> {
+        let data: String = self.client.get("/api/v1/exchangeInfo", "")?;
+
+        let info: ExchangeInformation = from_str(data.as_str())?;
+
+        Ok(info)
+    }
 }
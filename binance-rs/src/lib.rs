@@ -6,6 +6,7 @@ extern crate error_chain;
 extern crate hex;
 extern crate reqwest;
 extern crate ring;
+extern crate rust_decimal;
 extern crate serde;
 extern crate serde_json;
 
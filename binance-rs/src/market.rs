@@ -4,6 +4,7 @@ use model::*;
 use serde_json::from_str;
 use std::collections::BTreeMap;
 use util::*;
+use rust_decimal::Decimal;
 
 #[derive(Clone)]
 pub struct Market {
@@ -38,21 +39,24 @@ impl Market {
             String,
         )> = from_str(data.as_str()).unwrap();
 
-        Ok(order_book_result
+        order_book_result
             .iter()
-            .map(|r| CandleStick {
-                open_time: r.0,
-                open_price: r.1.parse::<f64>().unwrap(),
-                high_price: r.2.parse::<f64>().unwrap(),
-                low_price: r.3.parse::<f64>().unwrap(),
-                close_price: r.4.parse::<f64>().unwrap(),
-                volume: r.5.parse::<f64>().unwrap(),
-                quote_asset_volume: r.7.parse::<f64>().unwrap(),
-                trades: r.8,
-                taker_buy_base_asset_volume: r.9.parse::<f64>().unwrap(),
-                taker_buy_quote_asset_volume: r.10.parse::<f64>().unwrap(),
+            .map(|r| -> Result<CandleStick> {
+                Ok(CandleStick {
+                    open_time: r.0,
+                    open_price: r.1.parse()?,
+                    high_price: r.2.parse()?,
+                    low_price: r.3.parse()?,
+                    close_price: r.4.parse()?,
+                    volume: r.5.parse()?,
+                    close_time: r.6,
+                    quote_asset_volume: r.7.parse()?,
+                    trades: r.8,
+                    taker_buy_base_asset_volume: r.9.parse()?,
+                    taker_buy_quote_asset_volume: r.10.parse()?,
+                })
             })
-            .collect())
+            .collect()
     }
 
     // Order book (Default 100; max 100)
@@ -80,7 +84,7 @@ impl Market {
     }
 
     // Latest price for ONE symbol.
-    pub fn get_price<S>(&self, symbol: S) -> Result<(f64)>
+    pub fn get_price<S>(&self, symbol: S) -> Result<(Decimal)>
     where
         S: Into<String>,
     {
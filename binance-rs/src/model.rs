@@ -1,9 +1,57 @@
+use rust_decimal::Decimal;
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ServerTime {
     pub server_time: u64,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExchangeInformation {
+    pub timezone: String,
+    pub server_time: u64,
+    pub symbols: Vec<Symbol>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Symbol {
+    pub symbol: String,
+    pub status: String,
+    pub base_asset: String,
+    pub base_asset_precision: u64,
+    pub quote_asset: String,
+    pub quote_precision: u64,
+    pub order_types: Vec<String>,
+    pub iceberg_allowed: bool,
+    pub filters: Vec<Filters>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "filterType")]
+pub enum Filters {
+    #[serde(rename = "PRICE_FILTER")]
+    #[serde(rename_all = "camelCase")]
+    PriceFilter {
+        min_price: Decimal,
+        max_price: Decimal,
+        tick_size: Decimal,
+    },
+    #[serde(rename = "LOT_SIZE")]
+    #[serde(rename_all = "camelCase")]
+    LotSize {
+        min_qty: Decimal,
+        max_qty: Decimal,
+        step_size: Decimal,
+    },
+    #[serde(rename = "MIN_NOTIONAL")]
+    #[serde(rename_all = "camelCase")]
+    MinNotional { min_notional: Decimal },
+    #[serde(other)]
+    Other,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct AccountInformation {
@@ -21,8 +69,8 @@ pub struct AccountInformation {
 #[serde(rename_all = "camelCase")]
 pub struct Balance {
     pub asset: String,
-    pub free: String,
-    pub locked: String,
+    pub free: Decimal,
+    pub locked: Decimal,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -31,18 +79,16 @@ pub struct Order {
     pub symbol: String,
     pub order_id: u64,
     pub client_order_id: String,
-    #[serde(with = "string_or_float")]
-    pub price: f64,
-    pub orig_qty: String,
-    pub executed_qty: String,
+    pub price: Decimal,
+    pub orig_qty: Decimal,
+    pub executed_qty: Decimal,
     pub status: String,
     pub time_in_force: String,
     #[serde(rename = "type")]
     pub type_name: String,
     pub side: String,
-    #[serde(with = "string_or_float")]
-    pub stop_price: f64,
-    pub iceberg_qty: String,
+    pub stop_price: Decimal,
+    pub iceberg_qty: Decimal,
     pub time: u64,
 }
 
@@ -74,10 +120,8 @@ pub struct OrderBook {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Bids {
-    #[serde(with = "string_or_float")]
-    pub price: f64,
-    #[serde(with = "string_or_float")]
-    pub qty: f64,
+    pub price: Decimal,
+    pub qty: Decimal,
     // Never serialized.
     // #[serde(skip_serializing)]
     // ignore: Vec<String>,
@@ -85,10 +129,8 @@ pub struct Bids {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Asks {
-    #[serde(with = "string_or_float")]
-    pub price: f64,
-    #[serde(with = "string_or_float")]
-    pub qty: f64,
+    pub price: Decimal,
+    pub qty: Decimal,
     // Never serialized.
     // #[serde(skip_serializing)]
     // ignore: Vec<String>,
@@ -113,8 +155,7 @@ pub enum Prices {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SymbolPrice {
     pub symbol: String,
-    #[serde(with = "string_or_float")]
-    pub price: f64,
+    pub price: Decimal,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -128,25 +169,19 @@ pub enum BookTickers {
 #[serde(rename_all = "camelCase")]
 pub struct Tickers {
     pub symbol: String,
-    #[serde(with = "string_or_float")]
-    pub bid_price: f64,
-    #[serde(with = "string_or_float")]
-    pub bid_qty: f64,
-    #[serde(with = "string_or_float")]
-    pub ask_price: f64,
-    #[serde(with = "string_or_float")]
-    pub ask_qty: f64,
+    pub bid_price: Decimal,
+    pub bid_qty: Decimal,
+    pub ask_price: Decimal,
+    pub ask_qty: Decimal,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct TradeHistory {
     pub id: u64,
-    #[serde(with = "string_or_float")]
-    pub price: f64,
-    #[serde(with = "string_or_float")]
-    pub qty: f64,
-    pub commission: String,
+    pub price: Decimal,
+    pub qty: Decimal,
+    pub commission: Decimal,
     pub commission_asset: String,
     pub time: u64,
     pub is_buyer: bool,
@@ -156,15 +191,16 @@ pub struct TradeHistory {
 
 pub struct CandleStick {
     pub open_time: u64,
-    pub open_price: f64,
-    pub high_price: f64,
-    pub low_price: f64,
-    pub close_price: f64,
-    pub volume: f64,
-    pub quote_asset_volume: f64,
+    pub open_price: Decimal,
+    pub high_price: Decimal,
+    pub low_price: Decimal,
+    pub close_price: Decimal,
+    pub volume: Decimal,
+    pub close_time: u64,
+    pub quote_asset_volume: Decimal,
     pub trades: u64,
-    pub taker_buy_base_asset_volume: f64,
-    pub taker_buy_quote_asset_volume: f64,
+    pub taker_buy_base_asset_volume: Decimal,
+    pub taker_buy_quote_asset_volume: Decimal,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -173,22 +209,14 @@ pub struct PriceStats {
     pub price_change: String,
     pub price_change_percent: String,
     pub weighted_avg_price: String,
-    #[serde(with = "string_or_float")]
-    pub prev_close_price: f64,
-    #[serde(with = "string_or_float")]
-    pub last_price: f64,
-    #[serde(with = "string_or_float")]
-    pub bid_price: f64,
-    #[serde(with = "string_or_float")]
-    pub ask_price: f64,
-    #[serde(with = "string_or_float")]
-    pub open_price: f64,
-    #[serde(with = "string_or_float")]
-    pub high_price: f64,
-    #[serde(with = "string_or_float")]
-    pub low_price: f64,
-    #[serde(with = "string_or_float")]
-    pub volume: f64,
+    pub prev_close_price: Decimal,
+    pub last_price: Decimal,
+    pub bid_price: Decimal,
+    pub ask_price: Decimal,
+    pub open_price: Decimal,
+    pub high_price: Decimal,
+    pub low_price: Decimal,
+    pub volume: Decimal,
     pub open_time: u64,
     pub close_time: u64,
     pub first_id: u64,
@@ -525,34 +553,3 @@ pub struct DepthOrderBookEvent {
     #[serde(rename = "a")]
     pub asks: Vec<Asks>,
 }
-
-mod string_or_float {
-    use std::fmt;
-
-    use serde::{de, Deserialize, Deserializer, Serializer};
-
-    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        T: fmt::Display,
-        S: Serializer,
-    {
-        serializer.collect_str(value)
-    }
-
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<f64, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        #[derive(Deserialize)]
-        #[serde(untagged)]
-        enum StringOrFloat {
-            String(String),
-            Float(f64),
-        }
-
-        match StringOrFloat::deserialize(deserializer)? {
-            StringOrFloat::String(s) => s.parse().map_err(de::Error::custom),
-            StringOrFloat::Float(i) => Ok(i),
-        }
-    }
-}
@@ -142,6 +142,19 @@ impl Client {
         Ok(response.data)
     }
 
+    /// Candles for a symbol, newest first. `period` is one of 1min, 5min, 15min, 30min, 60min,
+    /// 4hour, 1day, 1week, 1mon and `size` at most 2000.
+    pub fn klines(&self, symbol: &str, period: &str, size: u32) -> APIResult<Vec<Kline>> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        params.insert("period".to_string(), period.to_string());
+        params.insert("size".to_string(), size.to_string());
+        let data = self.get("/market/history/kline", &build_query_string(params))?;
+        let response: APIResponse<Vec<Kline>> = from_str(data.as_str())?;
+
+        Ok(response.data)
+    }
+
     /// return all symbol pairs used on the exchange.
     pub fn common_symbols(&self) -> APIResult<Vec<Pair>> {
         let data = self.get("/v1/common/symbols", "")?;
@@ -2,6 +2,7 @@
 #![allow(unused_variables)]
 
 use serde::de::{self, Unexpected, Visitor};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Deserializer, Serialize};
 use std::fmt::{self, Display};
 use std::str::FromStr;
@@ -74,25 +75,34 @@ pub struct Pair {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Kline {
     pub id: u32,
-    pub amount: f64,
+    pub amount: Decimal,
     pub count: u32,
-    pub open: f64,
-    pub close: f64,
-    pub low: f64,
-    pub high: f64,
-    pub vol: f64,
+    pub open: Decimal,
+    pub close: Decimal,
+    pub low: Decimal,
+    pub high: Decimal,
+    pub vol: Decimal,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Ticker {
-    pub amount: f64,
+    pub amount: Decimal,
     pub count: u32,
-    pub open: f64,
-    pub close: f64,
-    pub low: f64,
-    pub high: f64,
-    pub vol: f64,
+    pub open: Decimal,
+    pub close: Decimal,
+    pub low: Decimal,
+    pub high: Decimal,
+    pub vol: Decimal,
     pub symbol: String,
+    /// best bid and ask, missing from older responses.
+    #[serde(default)]
+    pub bid: Option<Decimal>,
+    #[serde(default, rename = "bidSize")]
+    pub bid_size: Option<Decimal>,
+    #[serde(default)]
+    pub ask: Option<Decimal>,
+    #[serde(default, rename = "askSize")]
+    pub ask_size: Option<Decimal>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -109,8 +119,7 @@ pub struct Asset {
     pub currency: String,
     #[serde(rename = "type")]
     pub trade_type: String,
-    #[serde(deserialize_with = "string_as_f64")]
-    pub balance: f64,
+    pub balance: Decimal,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -123,11 +132,9 @@ pub struct Order {
     #[serde(rename = "account-id")]
     pub account_id: u64,
 
-    #[serde(deserialize_with = "string_as_f64")]
-    pub amount: f64,
+    pub amount: Decimal,
 
-    #[serde(deserialize_with = "string_as_f64")]
-    pub price: f64,
+    pub price: Decimal,
 
     #[serde(rename = "created-at")]
     pub created_at: u64,
@@ -136,16 +143,13 @@ pub struct Order {
     pub order_type: String,
 
     #[serde(rename = "field-amount")]
-    #[serde(deserialize_with = "string_as_f64")]
-    pub field_amount: f64,
+    pub field_amount: Decimal,
 
     #[serde(rename = "field-cash-amount")]
-    #[serde(deserialize_with = "string_as_f64")]
-    pub field_cash_amount: f64,
+    pub field_cash_amount: Decimal,
 
     #[serde(rename = "field-fees")]
-    #[serde(deserialize_with = "string_as_f64")]
-    pub field_fees: f64,
+    pub field_fees: Decimal,
 
     #[serde(rename = "finished-at")]
     pub finished_at: u64,
@@ -153,30 +157,3 @@ pub struct Order {
     #[serde(rename = "canceled-at")]
     pub canceled_at: u64,
 }
-
-fn string_as_f64<'de, D>(deserializer: D) -> Result<f64, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    deserializer.deserialize_any(F64Visitor)
-}
-
-struct F64Visitor;
-impl<'de> Visitor<'de> for F64Visitor {
-    type Value = f64;
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("a string representation of a f64")
-    }
-    fn visit_str<E>(self, value: &str) -> Result<f64, E>
-    where
-        E: de::Error,
-    {
-        if let Ok(integer) = value.parse::<i32>() {
-            Ok(integer as f64)
-        } else {
-            value.parse::<f64>().map_err(|err| {
-                E::invalid_value(Unexpected::Str(value), &"a string representation of a f64")
-            })
-        }
-    }
-}
@@ -1,6 +1,8 @@
 use crate::{error::*, models::*};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use ring::{digest, hmac};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use reqwest::Method;
 use serde_json::from_str;
@@ -15,6 +17,11 @@ pub struct Client {
 
 static API_HOST: &'static str = "www.okex.com";
 
+/// Candle sizes in seconds accepted by the candles endpoint.
+pub static CANDLE_GRANULARITIES: [u32; 12] = [
+    60, 180, 300, 900, 1800, 3600, 7200, 14400, 21600, 43200, 86400, 604800,
+];
+
 impl Client {
     pub fn new(api_key: &str, secret_key: &str, passphrase: &str) -> Self {
         Client {
@@ -35,13 +42,14 @@ impl Client {
             }
         }
         else {
-            if params.len() != 0 {
-                body = serde_json::to_string(params).unwrap();
-            }
+            body = serde_json::to_string(params).unwrap();
         }
-        let hash_str = format!("{}{}{}{}", timestamp, method.as_str().to_uppercase(), request_path, &body);
-        let sign_str = sign_hmac_sha256_base64(&self.secret_key, &hash_str);
-        sign_str
+        self.sign(&request_path, method, &body, timestamp)
+    }
+
+    fn sign(&self, request_path: &str, method: Method, body: &str, timestamp: &str) -> String {
+        let hash_str = format!("{}{}{}{}", timestamp, method.as_str().to_uppercase(), request_path, body);
+        sign_hmac_sha256_base64(&self.secret_key, &hash_str)
     }
 
     pub fn get(&self, endpoint: &str, params: &BTreeMap<String, String>, signed: bool) -> APIResult<String> {
@@ -66,27 +74,26 @@ impl Client {
         .headers(header_map)
         .send().unwrap().text()?;   
 
-        let err_response: OkexAPIErrorResponse  = serde_json::from_str(body.as_str())?;
-        if err_response.error_code != None &&  err_response.error_msg != None {
-            if let Some(err_msg) = err_response.error_msg {
+        // list endpoints return a json array, only objects can carry an error.
+        if let Ok(err_response) = serde_json::from_str::<OkexAPIErrorResponse>(body.as_str()) {
+            if let Some(err_msg) = err_response.error() {
                 return Err(Box::new(OkexError::ApiError(err_msg)));
-            } else {
-                return Err(Box::new(OkexError::ApiError(format!(
-                    "result dump: {:?}",
-                    err_response
-                ))));
             }
-            
         }
         Ok(body)
     }
 
     pub fn post(&self, endpoint: &str, params: &BTreeMap<String, String>, signed: bool) -> APIResult<String> {
+        self.post_body(endpoint, serde_json::to_string(params)?, signed)
+    }
+
+    /// POST a raw json body, used by the batch endpoints which take an array.
+    pub fn post_body(&self, endpoint: &str, body: String, signed: bool) -> APIResult<String> {
         let url = format!("https://{}{}", API_HOST, endpoint);
         let timestamp = get_timestamp();
         let mut sign_str = String::new();
         if signed {
-            sign_str = self.authenticate(endpoint, Method::POST, params, &timestamp);
+            sign_str = self.sign(endpoint, Method::POST, &body, &timestamp);
         }
         let mut header_map = HeaderMap::new();
         header_map.insert("OK-ACCESS-KEY", HeaderValue::from_str(&self.api_key).unwrap());
@@ -98,23 +105,17 @@ impl Client {
         let client = reqwest::Client::new();
         let body = client
         .post(&url)
-        .json(params)
+        .body(body)
         .headers(header_map)
-        .send().unwrap().text()?;   
+        .send()?.text()?;   
 
-        let err_response: OkexAPIErrorResponse  = serde_json::from_str(body.as_str())?;
-        if err_response.error_code != None &&  err_response.error_msg != None {
-            if let Some(err_msg) = err_response.error_msg {
+        // batch endpoints return a map of results, rejected entries are checked by the caller.
+        if let Ok(err_response) = serde_json::from_str::<OkexAPIErrorResponse>(body.as_str()) {
+            if let Some(err_msg) = err_response.error() {
                 return Err(Box::new(OkexError::ApiError(err_msg)));
-            } else {
-                return Err(Box::new(OkexError::ApiError(format!(
-                    "result dump: {:?}",
-                    err_response
-                ))));
             }
-            
         }
-        Ok(body)     
+        Ok(body)
 
     }
 
@@ -132,6 +133,66 @@ impl Client {
         Ok(response)
     }
 
+    /// All spot instruments with their tick size and minimum order size.
+    pub fn get_instruments(&self) -> APIResult<Vec<OkexInstrument>> {
+        let endpoint = "/api/spot/v3/instruments";
+        let params: BTreeMap<String, String> = BTreeMap::new();
+        let data = self.get(&endpoint, &params, false)?;
+        let response = serde_json::from_str(data.as_str())?;
+
+        Ok(response)
+    }
+
+    /// Latest tickers for all instruments.
+    pub fn get_tickers(&self) -> APIResult<Vec<OkexTicker>> {
+        let endpoint = "/api/spot/v3/instruments/ticker";
+        let params: BTreeMap<String, String> = BTreeMap::new();
+        let data = self.get(&endpoint, &params, false)?;
+        let response = serde_json::from_str(data.as_str())?;
+
+        Ok(response)
+    }
+
+    pub fn get_ticker(&self, symbol: &str) -> APIResult<OkexTicker> {
+        let endpoint = format!("/api/spot/v3/instruments/{}/ticker", symbol);
+        let params: BTreeMap<String, String> = BTreeMap::new();
+        let data = self.get(&endpoint, &params, false)?;
+        let response = serde_json::from_str(data.as_str())?;
+
+        Ok(response)
+    }
+
+    /// Candles for an instrument, newest first. `granularity` is in seconds and must be one of
+    /// `CANDLE_GRANULARITIES`, the exchange returns at most 200 candles per request.
+    pub fn get_candles(
+        &self,
+        symbol: &str,
+        granularity: u32,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> APIResult<Vec<OkexCandle>> {
+        if !CANDLE_GRANULARITIES.contains(&granularity) {
+            return Err(Box::new(OkexError::ApiError(format!(
+                "unsupported candle granularity: {}",
+                granularity
+            ))));
+        }
+
+        let endpoint = format!("/api/spot/v3/instruments/{}/candles", symbol);
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+        params.insert("granularity".into(), granularity.to_string());
+        if let Some(start) = start {
+            params.insert("start".into(), format_timestamp(start));
+        }
+        if let Some(end) = end {
+            params.insert("end".into(), format_timestamp(end));
+        }
+
+        let data = self.get(&endpoint, &params, false)?;
+        let response = serde_json::from_str(data.as_str())?;
+
+        Ok(response)
+    }
 
     pub fn get_balance(&self) -> APIResult<Vec<OkexBalance>> {
         let endpoint =  "/api/spot/v3/accounts";
@@ -142,29 +203,73 @@ impl Client {
          Ok(response)
     }
 
-    pub fn place_order(&self, trade_type: &str, symbol: &str, price: f64, amount: f64, user_orderid: Option<String>) -> APIResult<OkexPlaceOrderResponse> {
-        let okex_symbol = symbol;
-        let mut trade_type_convert: String = String::new();
-        if trade_type == "buy-limit" {
-            trade_type_convert = String::from("buy");
-        } else if trade_type == "sell-limit" {
-            trade_type_convert = String::from("sell");
+    /// Places a limit or market order from the huobi style `trade_type` strings, eg. "buy-limit"
+    /// or "sell-market". Market buys are sized by `amount` of the quote currency.
+    pub fn place_order(&self, trade_type: &str, symbol: &str, price: Decimal, amount: Decimal, user_orderid: Option<String>) -> APIResult<OkexPlaceOrderResponse> {
+        let mut request = match trade_type {
+            "buy-limit" => OkexOrderRequest::limit(symbol, OkexOrderSide::Buy, price, amount),
+            "sell-limit" => OkexOrderRequest::limit(symbol, OkexOrderSide::Sell, price, amount),
+            "buy-market" => OkexOrderRequest::market_notional(symbol, OkexOrderSide::Buy, amount),
+            "sell-market" => OkexOrderRequest::market_size(symbol, OkexOrderSide::Sell, amount),
+            _ => {
+                return Err(Box::new(OkexError::ApiError(format!(
+                    "unsupported trade type: {}",
+                    trade_type
+                ))))
+            }
+        };
+        if let Some(user_orderid) = user_orderid {
+            request = request.client_oid(&user_orderid);
         }
-        let endpoint = "/api/spot/v3/orders";
-        let mut params: BTreeMap<String, String> = BTreeMap::new();
-        params.insert("client_oid".into(), user_orderid.unwrap());
-        params.insert("type".into(), "limit".into());
-        params.insert("side".into(), trade_type_convert);
-        params.insert("price".into(), price.to_string());
-        params.insert("amount".into(), amount.to_string());
-        params.insert("instrument_id".into(), okex_symbol.into());
 
-        let data = self.post(&endpoint, &params, true)?;
+        self.place(&request)
+    }
+
+    pub fn place(&self, request: &OkexOrderRequest) -> APIResult<OkexPlaceOrderResponse> {
+        let endpoint = "/api/spot/v3/orders";
+        let data = self.post(&endpoint, &request.to_params(), true)?;
         let response = serde_json::from_str(data.as_str())?;
 
         Ok(response)
     }
 
+    /// Places up to `BATCH_MAX_ORDERS_PER_INSTRUMENT` orders for each of at most
+    /// `BATCH_MAX_INSTRUMENTS` instruments. Results are keyed by instrument id.
+    pub fn batch_orders(&self, requests: &[OkexOrderRequest]) -> APIResult<HashMap<String, Vec<OkexPlaceOrderResponse>>> {
+        check_batch_limits(requests.iter().map(|r| r.instrument_id.as_str()))?;
+
+        let endpoint = "/api/spot/v3/batch_orders";
+        let orders: Vec<BTreeMap<String, String>> = requests.iter().map(|r| r.to_params()).collect();
+        let data = self.post_body(&endpoint, serde_json::to_string(&orders)?, true)?;
+        let response: HashMap<String, Vec<OkexPlaceOrderResponse>> = serde_json::from_str(data.as_str())?;
+
+        check_batch_results(response.iter().flat_map(|(instrument_id, results)| {
+            results.iter().map(move |r| (r.result, format!("{} order {}", instrument_id, r.client_oid), r.error_message.clone()))
+        }))?;
+        Ok(response)
+    }
+
+    pub fn cancel_batch_orders(&self, cancels: &[OkexBatchCancel]) -> APIResult<HashMap<String, Vec<OkexCancelOrderResponse>>> {
+        for cancel in cancels {
+            if cancel.order_ids.len() + cancel.client_oids.len() > BATCH_MAX_ORDERS_PER_INSTRUMENT {
+                return Err(Box::new(OkexError::ApiError(format!(
+                    "at most {} orders can be cancelled per instrument",
+                    BATCH_MAX_ORDERS_PER_INSTRUMENT
+                ))));
+            }
+        }
+        check_batch_limits(cancels.iter().map(|c| c.instrument_id.as_str()))?;
+
+        let endpoint = "/api/spot/v3/cancel_batch_orders";
+        let data = self.post_body(&endpoint, serde_json::to_string(cancels)?, true)?;
+        let response: HashMap<String, Vec<OkexCancelOrderResponse>> = serde_json::from_str(data.as_str())?;
+
+        check_batch_results(response.iter().flat_map(|(instrument_id, results)| {
+            results.iter().map(move |r| (r.result, format!("{} cancel {}", instrument_id, r.order_id), r.error_message.clone()))
+        }))?;
+        Ok(response)
+    }
+
     pub fn cancel_order(&self, orderid: &str, symbol: &str) -> APIResult<OkexCancelOrderResponse> {
         let okex_symbol = symbol;
         let endpoint =  format!("/api/spot/v3/cancel_orders/{}", orderid);
@@ -206,6 +311,47 @@ impl Client {
 
 }
 
+/// Fails when any entry of a batch was rejected, naming each rejected entry and its reason.
+/// Entries that were accepted stay placed or cancelled.
+fn check_batch_results<I>(results: I) -> APIResult<()>
+where
+    I: Iterator<Item = (bool, String, Option<String>)>,
+{
+    let rejected: Vec<String> = results
+        .filter(|(result, _, _)| !result)
+        .map(|(_, entry, reason)| format!("{}: {}", entry, reason.unwrap_or_default()))
+        .collect();
+
+    if !rejected.is_empty() {
+        return Err(Box::new(OkexError::ApiError(rejected.join("; "))));
+    }
+    Ok(())
+}
+
+fn check_batch_limits<'a, I>(instrument_ids: I) -> APIResult<()>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for instrument_id in instrument_ids {
+        *counts.entry(instrument_id.to_lowercase()).or_insert(0) += 1;
+    }
+
+    if counts.len() > BATCH_MAX_INSTRUMENTS {
+        return Err(Box::new(OkexError::ApiError(format!(
+            "a batch can contain at most {} instruments",
+            BATCH_MAX_INSTRUMENTS
+        ))));
+    }
+    if counts.values().any(|count| *count > BATCH_MAX_ORDERS_PER_INSTRUMENT) {
+        return Err(Box::new(OkexError::ApiError(format!(
+            "a batch can contain at most {} orders per instrument",
+            BATCH_MAX_ORDERS_PER_INSTRUMENT
+        ))));
+    }
+    Ok(())
+}
+
 pub fn build_query_string(parameters: &BTreeMap<String, String>) -> String {
     parameters
         .into_iter()
@@ -227,10 +373,18 @@ pub fn sign_hmac_sha256_base64(secret: &str, digest: &str) -> String {
 
 
 pub fn get_timestamp() -> String {
-    let utc_time = chrono::Utc::now();
-    let formatted_time = utc_time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+    format_timestamp(chrono::Utc::now())
+}
+
+pub fn format_timestamp(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// Seconds since the epoch with millisecond precision, as used by the websocket login.
+pub fn get_epoch_timestamp() -> String {
+    let millis = chrono::Utc::now().timestamp_millis();
 
-    formatted_time
+    format!("{}.{:03}", millis / 1000, millis % 1000)
 }
 
 
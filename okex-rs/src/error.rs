@@ -18,6 +18,31 @@ pub struct OkexAPIErrorResponse {
 
     pub error_msg: Option<String>,
 
+    pub error_message: Option<String>,
+
+    pub result: Option<bool>,
+
+}
+
+impl OkexAPIErrorResponse {
+    /// The reason a request failed, `None` when the body is not an error. Order endpoints answer
+    /// with `result: false` and `error_message`, others with a non zero `error_code` or `code`.
+    pub fn error(&self) -> Option<String> {
+        let set = |code: &Option<String>| code.as_ref().map_or(false, |c| !c.is_empty() && c != "0");
+
+        if self.result != Some(false) && !set(&self.error_code) && !set(&self.code) {
+            return None;
+        }
+
+        let reason = self
+            .error_msg
+            .iter()
+            .chain(self.error_message.iter())
+            .chain(self.message.iter())
+            .find(|m| !m.is_empty())
+            .cloned();
+        Some(reason.unwrap_or_else(|| format!("result dump: {:?}", self)))
+    }
 }
 
 
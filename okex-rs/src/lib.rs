@@ -1,6 +1,7 @@
 pub mod client;
 pub mod error;
 pub mod models;
+pub mod websockets;
 
 // pub use crate::client::Client;
 // pub use crate::models::*;
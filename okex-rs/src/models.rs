@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
+use rust_decimal::Decimal;
 use serde::de::{self, Unexpected, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
 use std::fmt::{self, Display};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
 
@@ -11,34 +13,184 @@ use std::str::FromStr;
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct OkexOrderBook {
     pub timestamp: String,
-    pub bids: Vec<[f64;2]>,
-    pub asks: Vec<[f64;2]>,
+    pub bids: Vec<OkexBookLevel>,
+    pub asks: Vec<OkexBookLevel>,
 }
 
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct OkexBalance {
     pub currency: String,
-    pub balance: f64,
-    pub hold: f64,
-    pub available: f64,
+    pub balance: Decimal,
+    pub hold: Decimal,
+    pub available: Decimal,
 }
 
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OkexPlaceOrderResponse {
     pub order_id: String,
+    #[serde(default)]
     pub client_oid: String,
     pub result: bool,
+    pub error_code: Option<String>,
+    pub error_message: Option<String>,
 }
 
 
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OkexCancelOrderResponse {
     pub order_id: String,
+    #[serde(default)]
     pub client_oid: String,
     pub result: bool,
+    pub error_code: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Most instruments accepted by a single batch request.
+pub static BATCH_MAX_INSTRUMENTS: usize = 4;
+/// Most orders per instrument accepted by a single batch request.
+pub static BATCH_MAX_ORDERS_PER_INSTRUMENT: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OkexOrderSide {
+    Buy,
+    Sell,
+}
+
+impl OkexOrderSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OkexOrderSide::Buy => "buy",
+            OkexOrderSide::Sell => "sell",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OkexOrderKind {
+    Limit { price: Decimal, size: Decimal },
+    /// market order for an amount of the base currency, eg. selling 0.1 BTC.
+    MarketSize(Decimal),
+    /// market order for an amount of the quote currency, eg. buying 100 USDT worth of BTC.
+    MarketNotional(Decimal),
+}
+
+/// The `order_type` field, only honoured for limit orders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OkexExecution {
+    Normal,
+    PostOnly,
+    FillOrKill,
+    ImmediateOrCancel,
+}
+
+impl OkexExecution {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OkexExecution::Normal => "0",
+            OkexExecution::PostOnly => "1",
+            OkexExecution::FillOrKill => "2",
+            OkexExecution::ImmediateOrCancel => "3",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OkexOrderRequest {
+    pub instrument_id: String,
+    pub side: OkexOrderSide,
+    pub kind: OkexOrderKind,
+    pub execution: OkexExecution,
+    pub client_oid: Option<String>,
+}
+
+impl OkexOrderRequest {
+    pub fn limit(instrument_id: &str, side: OkexOrderSide, price: Decimal, size: Decimal) -> Self {
+        Self::new(instrument_id, side, OkexOrderKind::Limit { price, size })
+    }
+
+    pub fn market_size(instrument_id: &str, side: OkexOrderSide, size: Decimal) -> Self {
+        Self::new(instrument_id, side, OkexOrderKind::MarketSize(size))
+    }
+
+    pub fn market_notional(instrument_id: &str, side: OkexOrderSide, notional: Decimal) -> Self {
+        Self::new(instrument_id, side, OkexOrderKind::MarketNotional(notional))
+    }
+
+    fn new(instrument_id: &str, side: OkexOrderSide, kind: OkexOrderKind) -> Self {
+        OkexOrderRequest {
+            instrument_id: instrument_id.into(),
+            side,
+            kind,
+            execution: OkexExecution::Normal,
+            client_oid: None,
+        }
+    }
+
+    pub fn post_only(self) -> Self {
+        self.execution(OkexExecution::PostOnly)
+    }
+
+    pub fn fill_or_kill(self) -> Self {
+        self.execution(OkexExecution::FillOrKill)
+    }
+
+    pub fn immediate_or_cancel(self) -> Self {
+        self.execution(OkexExecution::ImmediateOrCancel)
+    }
+
+    pub fn execution(mut self, execution: OkexExecution) -> Self {
+        self.execution = execution;
+        self
+    }
+
+    /// Client supplied id, starting with a letter and at most 32 alphanumeric characters.
+    pub fn client_oid(mut self, client_oid: &str) -> Self {
+        self.client_oid = Some(client_oid.into());
+        self
+    }
+
+    pub fn to_params(&self) -> BTreeMap<String, String> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+        params.insert("instrument_id".into(), self.instrument_id.clone());
+        params.insert("side".into(), self.side.as_str().into());
+
+        match self.kind {
+            OkexOrderKind::Limit { price, size } => {
+                params.insert("type".into(), "limit".into());
+                params.insert("price".into(), price.to_string());
+                params.insert("size".into(), size.to_string());
+                params.insert("order_type".into(), self.execution.as_str().into());
+            }
+            OkexOrderKind::MarketSize(size) => {
+                params.insert("type".into(), "market".into());
+                params.insert("size".into(), size.to_string());
+            }
+            OkexOrderKind::MarketNotional(notional) => {
+                params.insert("type".into(), "market".into());
+                params.insert("notional".into(), notional.to_string());
+            }
+        }
+
+        if let Some(ref client_oid) = self.client_oid {
+            params.insert("client_oid".into(), client_oid.clone());
+        }
+
+        params
+    }
+}
+
+/// Orders to cancel for one instrument, either by exchange id or by client id.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct OkexBatchCancel {
+    pub instrument_id: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub order_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub client_oids: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -49,16 +201,16 @@ pub struct OkexOrderDetailResponse {
     pub size: String,
     pub instrument_id: String,
     pub side: String,
-    pub filled_size: f64,
-    pub filled_notional: f64,
+    pub filled_size: Decimal,
+    pub filled_notional: Decimal,
     pub status: String,
     pub state: i16,
-    pub notional: f64,
+    pub notional: Decimal,
     pub timestamp: String,    
     pub fee_currency: String,
-    pub fee: f64,
+    pub fee: Decimal,
     pub rebate_currency: String,
-    pub rebate: f64,
+    pub rebate: Decimal,
 }
 
 
@@ -70,3 +222,144 @@ pub struct OkexOrderDetailResponse {
 
 
 
+
+/// Trading rules for a spot instrument, eg. `BTC-USDT`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OkexInstrument {
+    pub instrument_id: String,
+    pub base_currency: String,
+    pub quote_currency: String,
+    pub min_size: Decimal,
+    pub size_increment: Decimal,
+    pub tick_size: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OkexTicker {
+    pub instrument_id: String,
+    pub last: Decimal,
+    pub best_bid: Decimal,
+    pub best_bid_size: Decimal,
+    pub best_ask: Decimal,
+    pub best_ask_size: Decimal,
+    pub open_24h: Decimal,
+    pub high_24h: Decimal,
+    pub low_24h: Decimal,
+    pub base_volume_24h: Decimal,
+    pub quote_volume_24h: Decimal,
+    pub timestamp: String,
+}
+
+/// A single candle, sent by OKEx as `[time, open, high, low, close, volume]`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "(String, String, String, String, String, String)")]
+pub struct OkexCandle {
+    pub time: String,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+}
+
+impl std::convert::TryFrom<(String, String, String, String, String, String)> for OkexCandle {
+    type Error = rust_decimal::Error;
+
+    fn try_from(
+        row: (String, String, String, String, String, String),
+    ) -> Result<Self, Self::Error> {
+        Ok(OkexCandle {
+            time: row.0,
+            open: row.1.parse()?,
+            high: row.2.parse()?,
+            low: row.3.parse()?,
+            close: row.4.parse()?,
+            volume: row.5.parse()?,
+        })
+    }
+}
+
+// websocket messages
+
+/// Control frames sent back by the websocket server (login, subscribe, error).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OkexWsEvent {
+    pub event: String,
+    pub channel: Option<String>,
+    pub success: Option<bool>,
+    pub message: Option<String>,
+    #[serde(rename = "errorCode")]
+    pub error_code: Option<i64>,
+}
+
+/// Data frames pushed for a subscribed channel, eg. `spot/ticker`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OkexWsTable {
+    pub table: String,
+    pub action: Option<String>,
+    pub data: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum OkexWsMessage {
+    Event(OkexWsEvent),
+    Table(OkexWsTable),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OkexWsCandle {
+    pub instrument_id: String,
+    pub candle: OkexCandle,
+}
+
+/// A book level as `[price, size, num_orders]`. Prices and sizes are kept as the exchange
+/// sent them, the depth checksum is calculated over the original strings.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OkexBookLevel(pub String, pub String, pub String);
+
+impl OkexBookLevel {
+    pub fn price(&self) -> Decimal {
+        self.0.parse().unwrap_or(Decimal::ZERO)
+    }
+
+    pub fn size(&self) -> Decimal {
+        self.1.parse().unwrap_or(Decimal::ZERO)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OkexWsDepth {
+    pub instrument_id: String,
+    pub asks: Vec<OkexBookLevel>,
+    pub bids: Vec<OkexBookLevel>,
+    pub timestamp: String,
+    pub checksum: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OkexWsOrder {
+    pub client_oid: String,
+    pub order_id: String,
+    pub instrument_id: String,
+    pub side: String,
+    #[serde(rename = "type")]
+    pub order_type: String,
+    pub price: String,
+    pub size: String,
+    pub notional: String,
+    pub filled_size: Decimal,
+    pub filled_notional: Decimal,
+    pub last_fill_px: Decimal,
+    pub last_fill_qty: Decimal,
+    pub state: String,
+    pub timestamp: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OkexWsAccount {
+    pub currency: String,
+    pub balance: Decimal,
+    pub hold: Decimal,
+    pub available: Decimal,
+}
@@ -0,0 +1,584 @@
+use crate::client::{get_epoch_timestamp, sign_hmac_sha256_base64};
+use crate::{error::*, models::*};
+use flate2::read::DeflateDecoder;
+use flate2::Crc;
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde_json::{from_str, from_value, Value};
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::client::AutoStream;
+use tungstenite::handshake::client::Response;
+use tungstenite::protocol::WebSocket;
+use tungstenite::stream::Stream;
+use tungstenite::{connect, Message};
+
+static WEBSOCKET_URL: &'static str = "wss://real.okex.com:8443/ws/v3";
+
+// the server drops connections that are silent for 30 seconds.
+static PING_INTERVAL: Duration = Duration::from_secs(20);
+static MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+// number of levels per side covered by the depth checksum.
+static CHECKSUM_DEPTH: usize = 25;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Channel {
+    Ticker(String),
+    /// candles for an instrument, granularity in seconds (60, 180, 300 ... 604800).
+    Candle(u32, String),
+    /// incremental 400 level book, verified against the checksum of each update.
+    Depth(String),
+    Order(String),
+    Account(String),
+}
+
+impl Channel {
+    /// private channels can only be subscribed after a successful login.
+    pub fn is_private(&self) -> bool {
+        match self {
+            Channel::Order(_) | Channel::Account(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Channel::Ticker(instrument) => write!(f, "spot/ticker:{}", instrument),
+            Channel::Candle(granularity, instrument) => {
+                write!(f, "spot/candle{}s:{}", granularity, instrument)
+            }
+            Channel::Depth(instrument) => write!(f, "spot/depth:{}", instrument),
+            Channel::Order(instrument) => write!(f, "spot/order:{}", instrument),
+            Channel::Account(currency) => write!(f, "spot/account:{}", currency),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum WebsocketEvent {
+    Login(bool),
+    Subscribed(String),
+    Reconnected,
+    Ticker(OkexTicker),
+    Candle(u32, OkexWsCandle),
+    /// the full local book after applying a partial or an update with a valid checksum.
+    Depth(OkexWsDepth),
+    Order(OkexWsOrder),
+    Account(OkexWsAccount),
+    Error(String),
+}
+
+#[derive(Clone)]
+struct Credentials {
+    api_key: String,
+    secret_key: String,
+    passphrase: String,
+}
+
+pub struct WebSockets<'a> {
+    socket: Option<(WebSocket<AutoStream>, Response)>,
+    credentials: Option<Credentials>,
+    channels: Vec<Channel>,
+    books: HashMap<String, OkexWsDepth>,
+    handler: Box<dyn FnMut(WebsocketEvent) -> APIResult<()> + 'a>,
+}
+
+impl<'a> WebSockets<'a> {
+    pub fn new<Callback>(handler: Callback) -> WebSockets<'a>
+    where
+        Callback: FnMut(WebsocketEvent) -> APIResult<()> + 'a,
+    {
+        WebSockets {
+            socket: None,
+            credentials: None,
+            channels: Vec::new(),
+            books: HashMap::new(),
+            handler: Box::new(handler),
+        }
+    }
+
+    /// Credentials used to log in on every (re)connect, required for private channels.
+    pub fn login(&mut self, api_key: &str, secret_key: &str, passphrase: &str) -> APIResult<()> {
+        self.credentials = Some(Credentials {
+            api_key: api_key.into(),
+            secret_key: secret_key.into(),
+            passphrase: passphrase.into(),
+        });
+
+        if self.socket.is_some() {
+            self.send_login()?;
+        }
+        Ok(())
+    }
+
+    pub fn connect(&mut self) -> APIResult<()> {
+        let (socket, response) = connect(WEBSOCKET_URL)
+            .map_err(|e| OkexError::ApiError(format!("error during handshake: {}", e)))?;
+
+        // wake up periodically so the connection can be kept alive with pings.
+        match socket.get_ref() {
+            Stream::Plain(stream) => stream.set_read_timeout(Some(PING_INTERVAL))?,
+            Stream::Tls(stream) => stream.get_ref().set_read_timeout(Some(PING_INTERVAL))?,
+        }
+
+        self.socket = Some((socket, response));
+        self.books.clear();
+
+        if self.credentials.is_some() {
+            self.send_login()?;
+        }
+
+        let public: Vec<Channel> = self
+            .channels
+            .iter()
+            .filter(|c| !c.is_private())
+            .cloned()
+            .collect();
+        self.send_op("subscribe", &public)
+    }
+
+    pub fn disconnect(&mut self) -> APIResult<()> {
+        if let Some(ref mut socket) = self.socket {
+            socket.0.close(None)?;
+        }
+        self.socket = None;
+        Ok(())
+    }
+
+    /// Subscribes to the channels now and again after every reconnect. Private channels are
+    /// sent once the login has been accepted.
+    pub fn subscribe(&mut self, channels: &[Channel]) -> APIResult<()> {
+        let mut added = Vec::new();
+        for channel in channels {
+            if !self.channels.contains(channel) {
+                self.channels.push(channel.clone());
+                added.push(channel.clone());
+            }
+        }
+
+        if self.socket.is_some() {
+            let ready: Vec<Channel> = added
+                .into_iter()
+                .filter(|c| !c.is_private() || self.credentials.is_some())
+                .collect();
+            self.send_op("subscribe", &ready)?;
+        }
+        Ok(())
+    }
+
+    pub fn unsubscribe(&mut self, channels: &[Channel]) -> APIResult<()> {
+        self.channels.retain(|c| !channels.contains(c));
+        if self.socket.is_some() {
+            self.send_op("unsubscribe", channels)?;
+        }
+        Ok(())
+    }
+
+    /// Reads messages until `running` is set to false, reconnecting with a backoff whenever
+    /// the connection drops.
+    pub fn event_loop(&mut self, running: &AtomicBool) -> APIResult<()> {
+        let mut last_ping = Instant::now();
+        let mut reconnect_delay = Duration::from_secs(1);
+
+        while running.load(Ordering::Relaxed) {
+            if self.socket.is_none() {
+                thread::sleep(reconnect_delay);
+                match self.connect() {
+                    Ok(()) => {
+                        reconnect_delay = Duration::from_secs(1);
+                        last_ping = Instant::now();
+                        (self.handler)(WebsocketEvent::Reconnected)?;
+                    }
+                    Err(e) => {
+                        ::log::warn!("okex websocket reconnect failed: {}", e);
+                        self.socket = None;
+                        reconnect_delay = std::cmp::min(reconnect_delay * 2, MAX_RECONNECT_DELAY);
+                    }
+                }
+                continue;
+            }
+
+            if last_ping.elapsed() >= PING_INTERVAL {
+                if let Err(e) = self.send_text("ping") {
+                    ::log::warn!("okex websocket ping failed: {}", e);
+                    self.socket = None;
+                    continue;
+                }
+                last_ping = Instant::now();
+            }
+
+            let message = match self.socket.as_mut().map(|s| s.0.read_message()) {
+                Some(Ok(message)) => message,
+                Some(Err(tungstenite::Error::Io(ref e)))
+                    if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut =>
+                {
+                    continue;
+                }
+                Some(Err(e)) => {
+                    ::log::warn!("okex websocket disconnected: {}", e);
+                    self.socket = None;
+                    continue;
+                }
+                None => continue,
+            };
+
+            let text = match message {
+                Message::Binary(data) => match inflate(&data) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        ::log::warn!("okex websocket: cannot inflate frame: {}", e);
+                        self.socket = None;
+                        continue;
+                    }
+                },
+                Message::Text(text) => text,
+                Message::Close(_) => {
+                    self.socket = None;
+                    continue;
+                }
+                _ => continue,
+            };
+
+            if text == "pong" {
+                continue;
+            }
+
+            self.handle_msg(&text)?;
+        }
+
+        self.disconnect()
+    }
+
+    fn handle_msg(&mut self, msg: &str) -> APIResult<()> {
+        // frames the models don't cover yet should not take the connection down.
+        let message = match from_str::<OkexWsMessage>(msg) {
+            Ok(message) => message,
+            Err(e) => {
+                ::log::warn!("okex websocket: skipping frame {}: {}", msg, e);
+                return Ok(());
+            }
+        };
+
+        match message {
+            OkexWsMessage::Event(event) => match event.event.as_str() {
+                "login" => {
+                    let success = event.success.unwrap_or(false);
+                    if success {
+                        let private: Vec<Channel> = self
+                            .channels
+                            .iter()
+                            .filter(|c| c.is_private())
+                            .cloned()
+                            .collect();
+                        self.resend_op("subscribe", &private);
+                    }
+                    (self.handler)(WebsocketEvent::Login(success))
+                }
+                "subscribe" => (self.handler)(WebsocketEvent::Subscribed(
+                    event.channel.unwrap_or_default(),
+                )),
+                "error" => (self.handler)(WebsocketEvent::Error(format!(
+                    "{}: {}",
+                    event.error_code.unwrap_or(0),
+                    event.message.unwrap_or_default()
+                ))),
+                _ => Ok(()),
+            },
+            OkexWsMessage::Table(table) => self.handle_table(table),
+        }
+    }
+
+    fn handle_table(&mut self, table: OkexWsTable) -> APIResult<()> {
+        let name = table.table.as_str();
+
+        if name == "spot/ticker" {
+            for ticker in decode_table::<OkexTicker>(name, table.data) {
+                (self.handler)(WebsocketEvent::Ticker(ticker))?;
+            }
+        } else if name.starts_with("spot/candle") {
+            let granularity = match name
+                .trim_start_matches("spot/candle")
+                .trim_end_matches('s')
+                .parse::<u32>()
+            {
+                Ok(granularity) => granularity,
+                Err(e) => {
+                    ::log::warn!("okex websocket: skipping {} table: {}", name, e);
+                    return Ok(());
+                }
+            };
+            for candle in decode_table::<OkexWsCandle>(name, table.data) {
+                (self.handler)(WebsocketEvent::Candle(granularity, candle))?;
+            }
+        } else if name == "spot/depth" {
+            let is_partial = table.action.as_ref().map_or(false, |a| a == "partial");
+            for update in decode_table::<OkexWsDepth>(name, table.data) {
+                self.handle_depth(update, is_partial)?;
+            }
+        } else if name == "spot/order" {
+            for order in decode_table::<OkexWsOrder>(name, table.data) {
+                (self.handler)(WebsocketEvent::Order(order))?;
+            }
+        } else if name == "spot/account" {
+            for account in decode_table::<OkexWsAccount>(name, table.data) {
+                (self.handler)(WebsocketEvent::Account(account))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_depth(&mut self, update: OkexWsDepth, is_partial: bool) -> APIResult<()> {
+        let instrument_id = update.instrument_id.clone();
+
+        let book = if is_partial {
+            update
+        } else if let Some(book) = self.books.get(&instrument_id) {
+            let mut book = book.clone();
+            merge_levels(&mut book.asks, &update.asks, |a, b| a < b);
+            merge_levels(&mut book.bids, &update.bids, |a, b| a > b);
+            book.timestamp = update.timestamp;
+            book.checksum = update.checksum;
+            book
+        } else {
+            // an update without a snapshot, wait for the partial after resubscribing.
+            return Ok(());
+        };
+
+        if depth_checksum(&book) != book.checksum {
+            self.books.remove(&instrument_id);
+            let channel = [Channel::Depth(instrument_id.clone())];
+            self.resend_op("unsubscribe", &channel);
+            self.resend_op("subscribe", &channel);
+            return (self.handler)(WebsocketEvent::Error(format!(
+                "depth checksum mismatch for {}, resubscribing",
+                instrument_id
+            )));
+        }
+
+        self.books.insert(instrument_id, book.clone());
+        (self.handler)(WebsocketEvent::Depth(book))
+    }
+
+    fn send_login(&mut self) -> APIResult<()> {
+        let credentials = match self.credentials.clone() {
+            Some(credentials) => credentials,
+            None => return Ok(()),
+        };
+
+        // same pre-hash format as the rest api: timestamp + method + request path.
+        let timestamp = get_epoch_timestamp();
+        let sign = sign_hmac_sha256_base64(
+            &credentials.secret_key,
+            &format!("{}GET/users/self/verify", timestamp),
+        );
+
+        let msg = serde_json::json!({
+            "op": "login",
+            "args": [credentials.api_key, credentials.passphrase, timestamp, sign],
+        });
+        self.send_text(&msg.to_string())
+    }
+
+    fn send_op(&mut self, op: &str, channels: &[Channel]) -> APIResult<()> {
+        if channels.is_empty() {
+            return Ok(());
+        }
+
+        let args: Vec<String> = channels.iter().map(|c| c.to_string()).collect();
+        let msg = serde_json::json!({ "op": op, "args": args });
+        self.send_text(&msg.to_string())
+    }
+
+    /// Sends an op from inside the event loop. A failed write drops the socket so the loop
+    /// reconnects and subscribes again instead of stopping.
+    fn resend_op(&mut self, op: &str, channels: &[Channel]) {
+        if let Err(e) = self.send_op(op, channels) {
+            ::log::warn!("okex websocket: {} failed: {}", op, e);
+            self.socket = None;
+        }
+    }
+
+    fn send_text(&mut self, text: &str) -> APIResult<()> {
+        if let Some(ref mut socket) = self.socket {
+            socket.0.write_message(Message::Text(text.into()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes the data of a table, skipping it with a warning when the models don't match.
+fn decode_table<T: DeserializeOwned>(name: &str, data: Value) -> Vec<T> {
+    from_value(data).unwrap_or_else(|e| {
+        ::log::warn!("okex websocket: skipping {} table: {}", name, e);
+        Vec::new()
+    })
+}
+
+/// OKEx compresses every frame with raw deflate (no zlib header).
+pub fn inflate(data: &[u8]) -> APIResult<String> {
+    let mut decoder = DeflateDecoder::new(data);
+    let mut text = String::new();
+    decoder.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Applies incremental levels to one side of a book. A size of zero removes the level,
+/// `before` decides the sort order of the side (ascending asks, descending bids).
+fn merge_levels<F>(levels: &mut Vec<OkexBookLevel>, updates: &[OkexBookLevel], before: F)
+where
+    F: Fn(Decimal, Decimal) -> bool,
+{
+    for update in updates {
+        let price = update.price();
+        let position = levels.iter().position(|l| !before(l.price(), price));
+
+        match position {
+            Some(i) if levels[i].price() == price => {
+                if update.size().is_zero() {
+                    levels.remove(i);
+                } else {
+                    levels[i] = update.clone();
+                }
+            }
+            Some(i) => {
+                if !update.size().is_zero() {
+                    levels.insert(i, update.clone());
+                }
+            }
+            None => {
+                if !update.size().is_zero() {
+                    levels.push(update.clone());
+                }
+            }
+        }
+    }
+}
+
+/// CRC32 over the top 25 bids and asks, interleaved as `bid:size:ask:size:...`, as a signed int.
+pub fn depth_checksum(book: &OkexWsDepth) -> i32 {
+    let mut fields: Vec<&str> = Vec::new();
+
+    for i in 0..CHECKSUM_DEPTH {
+        if let Some(bid) = book.bids.get(i) {
+            fields.push(&bid.0);
+            fields.push(&bid.1);
+        }
+        if let Some(ask) = book.asks.get(i) {
+            fields.push(&ask.0);
+            fields.push(&ask.1);
+        }
+    }
+
+    let mut crc = Crc::new();
+    crc.update(fields.join(":").as_bytes());
+    crc.sum() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(price: &str, size: &str) -> OkexBookLevel {
+        OkexBookLevel(price.into(), size.into(), "1".into())
+    }
+
+    fn book(bids: Vec<OkexBookLevel>, asks: Vec<OkexBookLevel>) -> OkexWsDepth {
+        OkexWsDepth {
+            instrument_id: "BTC-USDT".into(),
+            asks,
+            bids,
+            timestamp: "2019-10-01T00:00:00.000Z".into(),
+            checksum: 0,
+        }
+    }
+
+    // the two level book of the OKEx docs, checksummed over "3366.1:7:3366.8:9:3366:6:3368:8".
+    fn documented_book() -> OkexWsDepth {
+        book(
+            vec![level("3366.1", "7"), level("3366", "6")],
+            vec![level("3366.8", "9"), level("3368", "8")],
+        )
+    }
+
+    #[test]
+    fn checksum_of_documented_book() {
+        assert_eq!(depth_checksum(&documented_book()), -1881014294);
+    }
+
+    #[test]
+    fn checksum_of_uneven_sides() {
+        // bids run out first, the remaining asks follow on their own.
+        let book = book(
+            vec![level("3366.1", "7")],
+            vec![level("3366.8", "9"), level("3368", "8")],
+        );
+        let mut crc = Crc::new();
+        crc.update(b"3366.1:7:3366.8:9:3368:8");
+
+        assert_eq!(depth_checksum(&book), crc.sum() as i32);
+    }
+
+    #[test]
+    fn merge_updates_inserts_and_removes_levels() {
+        let mut book = documented_book();
+
+        merge_levels(
+            &mut book.asks,
+            &[level("3366.8", "0"), level("3367", "2"), level("3370", "1")],
+            |a, b| a < b,
+        );
+        merge_levels(
+            &mut book.bids,
+            &[
+                level("3366.1", "5"),
+                level("3366.5", "3"),
+                level("3360", "0"),
+            ],
+            |a, b| a > b,
+        );
+
+        let prices = |levels: &[OkexBookLevel]| -> Vec<(String, String)> {
+            levels.iter().map(|l| (l.0.clone(), l.1.clone())).collect()
+        };
+        assert_eq!(
+            prices(&book.asks),
+            vec![
+                ("3367".to_string(), "2".to_string()),
+                ("3368".to_string(), "8".to_string()),
+                ("3370".to_string(), "1".to_string()),
+            ]
+        );
+        assert_eq!(
+            prices(&book.bids),
+            vec![
+                ("3366.5".to_string(), "3".to_string()),
+                ("3366.1".to_string(), "5".to_string()),
+                ("3366".to_string(), "6".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_back_to_documented_book() {
+        let mut book = book(
+            vec![level("3366.1", "7"), level("3365", "1")],
+            vec![level("3368", "8")],
+        );
+
+        merge_levels(&mut book.asks, &[level("3366.8", "9")], |a, b| a < b);
+        merge_levels(
+            &mut book.bids,
+            &[level("3366", "6"), level("3365", "0")],
+            |a, b| a > b,
+        );
+
+        assert_eq!(depth_checksum(&book), depth_checksum(&documented_book()));
+    }
+}
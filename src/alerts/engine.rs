@@ -0,0 +1,572 @@
+use crate::alerts::*;
+use crate::config::APIConfig;
+use crate::error::*;
+use crate::exchanges::ExchangeAPI;
+use crate::models::*;
+use crate::strategies::{FeedEvent, MarketEvent};
+use crate::utils::price_percent;
+use log::warn;
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Whether a rule's condition holds after an observation, and what to report if it fires.
+struct Check {
+    holds: bool,
+    value: Decimal,
+    message: String,
+}
+
+#[derive(Debug)]
+struct RuleState {
+    rule: AlertRule,
+    last_price: Option<Decimal>,
+    /// prices inside a percent move's window, oldest first.
+    prices: VecDeque<(u64, Decimal)>,
+    /// candle volumes by open time, oldest first. The last candle may still be forming.
+    volumes: VecDeque<(u64, Decimal)>,
+    /// whether the condition held at the last observation.
+    active: bool,
+    last_fired: Option<u64>,
+}
+
+impl RuleState {
+    fn new(rule: AlertRule) -> Self {
+        RuleState {
+            rule,
+            last_price: None,
+            prices: VecDeque::new(),
+            volumes: VecDeque::new(),
+            active: false,
+            last_fired: None,
+        }
+    }
+
+    fn on_price(&mut self, time: u64, price: Decimal) -> Option<Check> {
+        let symbol = &self.rule.symbol;
+        let previous = self.last_price.replace(price);
+
+        match self.rule.condition {
+            AlertCondition::CrossesAbove { price: level } => Some(Check {
+                holds: previous.map_or(false, |previous| previous <= level && price > level),
+                value: price,
+                message: format!("{} crossed above {} at {}", symbol, level, price),
+            }),
+            AlertCondition::CrossesBelow { price: level } => Some(Check {
+                holds: previous.map_or(false, |previous| previous >= level && price < level),
+                value: price,
+                message: format!("{} crossed below {} at {}", symbol, level, price),
+            }),
+            AlertCondition::PercentMove { percent, window } => {
+                self.prices.push_back((time, price));
+                while let Some((since, _)) = self.prices.front() {
+                    if since + window * 1000 >= time {
+                        break;
+                    }
+                    self.prices.pop_front();
+                }
+
+                let (_, first) = self.prices.front()?;
+                let change = price_percent(*first, price);
+                Some(Check {
+                    holds: change.abs() >= percent,
+                    value: change,
+                    message: format!(
+                        "{} moved {}% in {}s, from {} to {}",
+                        symbol,
+                        change.round_dp(2),
+                        window,
+                        first,
+                        price
+                    ),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn on_spread(&mut self, bid: Decimal, ask: Decimal) -> Option<Check> {
+        match self.rule.condition {
+            AlertCondition::SpreadAbove { bps } => {
+                let mid = (bid + ask) / Decimal::new(2, 0);
+                let spread = (ask - bid).checked_div(mid)? * Decimal::new(10000, 0);
+                Some(Check {
+                    holds: spread > bps,
+                    value: spread,
+                    message: format!(
+                        "{} spread at {}bps, bid {} ask {}",
+                        self.rule.symbol,
+                        spread.round_dp(2),
+                        bid,
+                        ask
+                    ),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn record_candle(&mut self, candle: &Candlestick) {
+        let periods = match self.rule.condition {
+            AlertCondition::VolumeSpike { periods, .. } => periods,
+            _ => return,
+        };
+
+        match self.volumes.back_mut() {
+            Some((open_time, volume)) if *open_time == candle.open_time => *volume = candle.volume,
+            Some((open_time, _)) if *open_time > candle.open_time => return,
+            _ => self.volumes.push_back((candle.open_time, candle.volume)),
+        }
+        while self.volumes.len() > periods + 1 {
+            self.volumes.pop_front();
+        }
+    }
+
+    fn on_candle(&mut self, candle: &Candlestick) -> Option<Check> {
+        self.record_candle(candle);
+
+        let (multiple, periods) = match self.rule.condition {
+            AlertCondition::VolumeSpike { multiple, periods } => (multiple, periods),
+            _ => return None,
+        };
+        if periods == 0 || self.volumes.len() <= periods {
+            return None;
+        }
+
+        let (_, volume) = *self.volumes.back()?;
+        let before: Decimal = self.volumes.iter().rev().skip(1).map(|(_, v)| *v).sum();
+        let average = before / Decimal::from(periods);
+        let ratio = volume.checked_div(average)?;
+
+        Some(Check {
+            holds: ratio >= multiple,
+            value: volume,
+            message: format!(
+                "{} traded {} in a candle, {}x the average of the last {}",
+                self.rule.symbol,
+                volume,
+                ratio.round_dp(2),
+                periods
+            ),
+        })
+    }
+
+    /// Fires when the condition starts to hold, unless still cooling down. A condition that keeps
+    /// holding fires once, and again only after it has cleared.
+    fn gate(&mut self, time: u64, check: Check) -> Option<Alert> {
+        let was_active = self.active;
+        self.active = check.holds;
+
+        let cooled = self
+            .last_fired
+            .map_or(true, |fired| time >= fired + self.rule.cooldown * 1000);
+        if !check.holds || was_active || !cooled {
+            return None;
+        }
+
+        self.last_fired = Some(time);
+        Some(Alert {
+            time,
+            rule: self.rule.label(),
+            symbol: self.rule.symbol.clone(),
+            value: check.value,
+            message: check.message,
+        })
+    }
+
+    /// rules match symbols whatever their case, like the `Watchlist`.
+    fn watches(&self, symbol: &str) -> bool {
+        self.rule.symbol.eq_ignore_ascii_case(symbol)
+    }
+
+    fn watches_price(&self) -> bool {
+        match self.rule.condition {
+            AlertCondition::CrossesAbove { .. }
+            | AlertCondition::CrossesBelow { .. }
+            | AlertCondition::PercentMove { .. } => true,
+            _ => false,
+        }
+    }
+
+    fn watches_spread(&self) -> bool {
+        match self.rule.condition {
+            AlertCondition::SpreadAbove { .. } => true,
+            _ => false,
+        }
+    }
+
+    fn watches_volume(&self) -> bool {
+        match self.rule.condition {
+            AlertCondition::VolumeSpike { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+/// Checks alert rules against prices, spreads and candles, and sends the alerts that fire to
+/// every sink. Prices come from polling an exchange or from a feed. A rule fires when its
+/// condition starts to hold, not on every price while it does, and waits out its cooldown before
+/// firing again.
+///
+/// ```rust,ignore
+/// let mut engine = AlertEngine::from_config(&config.exchange["binance"])
+///     .rule(AlertRule::new("ETHUSDT", AlertCondition::PercentMove {
+///         percent: Decimal::new(5, 0),
+///         window: 3600,
+///     }))
+///     .sink(Box::new(StdoutSink))
+///     .sink(Box::new(FileSink::open_default()?));
+/// engine.watch(&binance, Duration::from_secs(10), &running)?;
+/// ```
+pub struct AlertEngine {
+    states: Vec<RuleState>,
+    sinks: Vec<Box<dyn AlertSink>>,
+    candle_interval: Interval,
+}
+
+impl AlertEngine {
+    /// an engine without rules or sinks, alerts are only returned.
+    pub fn new() -> Self {
+        AlertEngine {
+            states: Vec::new(),
+            sinks: Vec::new(),
+            candle_interval: Interval::FiveMinutes,
+        }
+    }
+
+    /// the `alerts` rules of an exchange's config.
+    pub fn from_config(config: &APIConfig) -> Self {
+        config
+            .alerts
+            .iter()
+            .flatten()
+            .cloned()
+            .fold(AlertEngine::new(), AlertEngine::rule)
+    }
+
+    pub fn rule(mut self, rule: AlertRule) -> Self {
+        self.add(rule);
+        self
+    }
+
+    pub fn sink(mut self, sink: Box<dyn AlertSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// the candles fetched for volume spikes when polling, five minutes by default.
+    pub fn candle_interval(mut self, interval: Interval) -> Self {
+        self.candle_interval = interval;
+        self
+    }
+
+    pub fn add(&mut self, rule: AlertRule) {
+        self.states.push(RuleState::new(rule));
+    }
+
+    pub fn rules(&self) -> Vec<&AlertRule> {
+        self.states.iter().map(|state| &state.rule).collect()
+    }
+
+    /// the markets the rules watch, sorted, spelled as in the first rule for each.
+    pub fn symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self
+            .states
+            .iter()
+            .map(|state| state.rule.symbol.clone())
+            .collect();
+        symbols.sort_by_key(|symbol| symbol.to_uppercase());
+        symbols.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        symbols
+    }
+
+    pub fn on_price(&mut self, symbol: &str, time: u64, price: Decimal) -> Vec<Alert> {
+        self.observe(symbol, time, |state| state.on_price(time, price))
+    }
+
+    pub fn on_spread(&mut self, symbol: &str, time: u64, bid: Decimal, ask: Decimal) -> Vec<Alert> {
+        self.observe(symbol, time, |state| state.on_spread(bid, ask))
+    }
+
+    /// Checks volume spikes against the candle, and price rules against its close. A candle
+    /// seen again as it forms replaces the earlier version.
+    pub fn on_candle(&mut self, symbol: &str, candle: &Candlestick) -> Vec<Alert> {
+        let mut alerts = self.on_price(symbol, candle.close_time, candle.close_price);
+        alerts
+            .append(&mut self.observe(symbol, candle.close_time, |state| state.on_candle(candle)));
+        alerts
+    }
+
+    /// Checks the rules against a websocket or replayed market event. Books are checked for
+    /// their spread, and their mid price stands in for the last price.
+    pub fn on_event(&mut self, event: &FeedEvent) -> Vec<Alert> {
+        let symbol = &event.symbol;
+        let time = event.event.time();
+
+        match &event.event {
+            MarketEvent::Candle(candle) => self.on_candle(symbol, candle),
+            MarketEvent::Trade(trade) => self.on_price(symbol, time, trade.sale_price),
+            MarketEvent::BookTicker(_, _) | MarketEvent::Depth(_, _) => {
+                let quote = match event.event.quote() {
+                    Some(quote) => quote,
+                    None => return Vec::new(),
+                };
+                let mut alerts = self.on_price(symbol, time, quote.last);
+                alerts.append(&mut self.on_spread(symbol, time, quote.bid_open, quote.ask_open));
+                alerts
+            }
+        }
+    }
+
+    /// Checks every rule against the exchange's current prices, book tickers and candles,
+    /// fetching only what the rules need. Symbols whose candles cannot be fetched are skipped
+    /// with a warning.
+    pub fn poll(&mut self, exchange: &dyn ExchangeAPI) -> CoreResult<Vec<Alert>> {
+        let now = now_millis();
+        let mut alerts = Vec::new();
+
+        let watched = |states: &[RuleState], symbol: &str, watches: fn(&RuleState) -> bool| {
+            states
+                .iter()
+                .any(|state| state.watches(symbol) && watches(state))
+        };
+
+        if self.states.iter().any(RuleState::watches_price) {
+            for price in exchange.all_prices()? {
+                let symbol = match exchange.native_symbol(&price.pair) {
+                    Ok(symbol) => symbol,
+                    Err(_) => continue,
+                };
+                if watched(&self.states, &symbol, RuleState::watches_price) {
+                    alerts.append(&mut self.on_price(&symbol, now, price.price));
+                }
+            }
+        }
+
+        if self.states.iter().any(RuleState::watches_spread) {
+            for ticker in exchange.book_tickers()? {
+                let symbol = match exchange.native_symbol(&ticker.pair) {
+                    Ok(symbol) => symbol,
+                    Err(_) => continue,
+                };
+                if watched(&self.states, &symbol, RuleState::watches_spread) {
+                    alerts.append(&mut self.on_spread(
+                        &symbol,
+                        now,
+                        ticker.bid_price,
+                        ticker.ask_price,
+                    ));
+                }
+            }
+        }
+
+        for symbol in self.symbols() {
+            if !watched(&self.states, &symbol, RuleState::watches_volume) {
+                continue;
+            }
+
+            let candles = match exchange.chart_data(&symbol, self.candle_interval) {
+                Ok(candles) => candles,
+                Err(e) => {
+                    warn!("{}: candles for {}: {}", exchange.display(), symbol, e);
+                    continue;
+                }
+            };
+            if let Some((last, history)) = candles.split_last() {
+                for candle in history {
+                    for state in self.states.iter_mut().filter(|s| s.watches(&symbol)) {
+                        state.record_candle(candle);
+                    }
+                }
+                alerts.append(&mut self.observe(&symbol, now, |state| state.on_candle(last)));
+            }
+        }
+
+        Ok(alerts)
+    }
+
+    /// Polls the exchange every `every` until `running` is cleared. Failed polls are logged and
+    /// retried.
+    pub fn watch(
+        &mut self,
+        exchange: &dyn ExchangeAPI,
+        every: Duration,
+        running: &AtomicBool,
+    ) -> CoreResult<()> {
+        while running.load(Ordering::Relaxed) {
+            if let Err(e) = self.poll(exchange) {
+                warn!("{}: polling alerts failed: {}", exchange.display(), e);
+            }
+            thread::sleep(every);
+        }
+        Ok(())
+    }
+
+    fn observe<F>(&mut self, symbol: &str, time: u64, mut check: F) -> Vec<Alert>
+    where
+        F: FnMut(&mut RuleState) -> Option<Check>,
+    {
+        let alerts: Vec<Alert> = self
+            .states
+            .iter_mut()
+            .filter(|state| state.watches(symbol))
+            .filter_map(|state| check(state).and_then(|result| state.gate(time, result)))
+            .collect();
+
+        for alert in &alerts {
+            for sink in &self.sinks {
+                if let Err(e) = sink.send(alert) {
+                    warn!("sending alert {}: {}", alert.rule, e);
+                }
+            }
+        }
+        alerts
+    }
+}
+
+impl Default for AlertEngine {
+    fn default() -> Self {
+        AlertEngine::new()
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn state(condition: AlertCondition) -> RuleState {
+        RuleState::new(AlertRule::new("BTCUSDT", condition))
+    }
+
+    /// feeds a price, returning whether the rule fired.
+    fn price(state: &mut RuleState, time: u64, price: &str) -> bool {
+        state
+            .on_price(time, dec(price))
+            .and_then(|check| state.gate(time, check))
+            .is_some()
+    }
+
+    /// feeds a one minute candle, returning whether the rule fired.
+    fn candle(state: &mut RuleState, open_time: u64, volume: &str) -> bool {
+        let candle = Candlestick {
+            open_time,
+            close_time: open_time + 59_999,
+            open_price: Decimal::ONE,
+            close_price: Decimal::ONE,
+            high_price: Decimal::ONE,
+            low_price: Decimal::ONE,
+            volume: dec(volume),
+            quote_volume: None,
+            number_of_trades: 1,
+        };
+        state
+            .on_candle(&candle)
+            .and_then(|check| state.gate(candle.close_time, check))
+            .is_some()
+    }
+
+    #[test]
+    fn crossing_fires_once_per_cross() {
+        let mut above = state(AlertCondition::CrossesAbove { price: dec("100") });
+        // the first price has nothing to cross from.
+        assert!(!price(&mut above, 0, "101"));
+        assert!(!price(&mut above, 1, "99"));
+        assert!(price(&mut above, 2, "101"));
+        assert!(!price(&mut above, 3, "102"));
+        assert!(!price(&mut above, 4, "100"));
+        assert!(price(&mut above, 5, "100.5"));
+
+        let mut below = state(AlertCondition::CrossesBelow { price: dec("100") });
+        assert!(!price(&mut below, 0, "100"));
+        assert!(price(&mut below, 1, "99"));
+        assert!(!price(&mut below, 2, "98"));
+    }
+
+    #[test]
+    fn percent_move_within_the_window() {
+        let mut moves = state(AlertCondition::PercentMove {
+            percent: dec("5"),
+            window: 60,
+        });
+
+        assert!(!price(&mut moves, 0, "100"));
+        assert!(!price(&mut moves, 30_000, "104"));
+        assert!(price(&mut moves, 60_000, "95"));
+        // 100 has left the window, 104 to 105 is under 1%.
+        assert!(!price(&mut moves, 61_000, "105"));
+        assert_eq!(moves.prices.front(), Some(&(30_000, dec("104"))));
+        // everything before has left the window.
+        assert!(!price(&mut moves, 200_000, "120"));
+        assert_eq!(moves.prices.len(), 1);
+    }
+
+    #[test]
+    fn volume_spike_against_the_candles_before() {
+        let mut spike = state(AlertCondition::VolumeSpike {
+            multiple: dec("3"),
+            periods: 2,
+        });
+
+        assert!(!candle(&mut spike, 0, "10"));
+        assert!(!candle(&mut spike, 60_000, "10"));
+        assert!(!candle(&mut spike, 120_000, "25"));
+        // the same candle growing as it forms.
+        assert!(candle(&mut spike, 120_000, "30"));
+        assert_eq!(spike.volumes.len(), 3);
+        // an older candle arriving late is ignored.
+        assert!(!candle(&mut spike, 60_000, "1000"));
+        assert!(!candle(&mut spike, 180_000, "10"));
+        assert_eq!(spike.volumes.front(), Some(&(60_000, dec("10"))));
+    }
+
+    #[test]
+    fn cooldown_holds_back_firing_again() {
+        let mut above = RuleState::new(
+            AlertRule::new(
+                "BTCUSDT",
+                AlertCondition::CrossesAbove { price: dec("100") },
+            )
+            .cooldown(Duration::from_secs(60)),
+        );
+
+        assert!(!price(&mut above, 0, "99"));
+        assert!(price(&mut above, 1_000, "101"));
+        assert!(!price(&mut above, 2_000, "99"));
+        assert!(!price(&mut above, 3_000, "101"));
+        assert!(!price(&mut above, 4_000, "99"));
+        assert!(price(&mut above, 61_000, "101"));
+    }
+
+    #[test]
+    fn symbols_match_whatever_their_case() {
+        let mut engine = AlertEngine::new()
+            .rule(AlertRule::new(
+                "btcusdt",
+                AlertCondition::CrossesAbove { price: dec("100") },
+            ))
+            .rule(AlertRule::new(
+                "BTCUSDT",
+                AlertCondition::CrossesBelow { price: dec("90") },
+            ));
+        assert_eq!(engine.symbols(), vec!["btcusdt"]);
+
+        assert!(engine.on_price("BTCUSDT", 0, dec("99")).is_empty());
+        let alerts = engine.on_price("BTCUSDT", 1, dec("101"));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].value, dec("101"));
+        assert_eq!(engine.on_price("BtcUsdt", 2, dec("89")).len(), 1);
+    }
+}
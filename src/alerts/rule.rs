@@ -0,0 +1,109 @@
+use crate::utils::local_datetime_from_unix;
+use rust_decimal::Decimal;
+use serde_derive::{Deserialize, Serialize};
+use std::time::Duration;
+use std::{fmt, fmt::Display};
+
+/// What an alert rule waits for. In the config file the condition is a table tagged by `type`, eg.
+/// `{ type = "percent_move", percent = "5", window = 3600 }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// the price moving from at or below `price` to above it.
+    CrossesAbove { price: Decimal },
+    /// the price moving from at or above `price` to below it.
+    CrossesBelow { price: Decimal },
+    /// the price moving `percent` or more either way within `window` seconds.
+    PercentMove { percent: Decimal, window: u64 },
+    /// a candle trading `multiple` times the average volume of the `periods` candles before it.
+    VolumeSpike { multiple: Decimal, periods: usize },
+    /// the spread between the best bid and ask widening past `bps` basis points of the mid.
+    SpreadAbove { bps: Decimal },
+}
+
+impl Display for AlertCondition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AlertCondition::CrossesAbove { price } => write!(f, "crosses above {}", price),
+            AlertCondition::CrossesBelow { price } => write!(f, "crosses below {}", price),
+            AlertCondition::PercentMove { percent, window } => {
+                write!(f, "moves {}% in {}s", percent, window)
+            }
+            AlertCondition::VolumeSpike { multiple, periods } => {
+                write!(f, "volume {}x the last {} candles", multiple, periods)
+            }
+            AlertCondition::SpreadAbove { bps } => write!(f, "spread above {}bps", bps),
+        }
+    }
+}
+
+/// A condition watched on one market.
+///
+/// ```toml
+/// [[exchange.binance.alerts]]
+/// symbol = "BTCUSDT"
+/// cooldown = 900
+/// condition = { type = "crosses_above", price = "70000" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    /// names the rule in its alerts, the condition when unset.
+    pub name: Option<String>,
+    /// the exchange's native symbol.
+    pub symbol: String,
+    pub condition: AlertCondition,
+    /// seconds after firing before the rule may fire again.
+    #[serde(default)]
+    pub cooldown: u64,
+}
+
+impl AlertRule {
+    pub fn new(symbol: &str, condition: AlertCondition) -> Self {
+        AlertRule {
+            name: None,
+            symbol: symbol.to_string(),
+            condition,
+            cooldown: 0,
+        }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown.as_secs();
+        self
+    }
+
+    pub fn label(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("{} {}", self.symbol, self.condition))
+    }
+}
+
+/// A rule that fired.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    /// unix millis.
+    pub time: u64,
+    pub rule: String,
+    pub symbol: String,
+    /// the price, percent move, volume or spread that set it off.
+    pub value: Decimal,
+    pub message: String,
+}
+
+impl Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}] {}",
+            local_datetime_from_unix(self.time).format("%Y-%m-%d %H:%M:%S"),
+            self.rule,
+            self.message
+        )
+    }
+}
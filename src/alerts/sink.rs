@@ -0,0 +1,101 @@
+use crate::alerts::*;
+use crate::error::*;
+use log::warn;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Where alerts are delivered. Implement it to route alerts anywhere else, eg. a chat bot.
+pub trait AlertSink {
+    fn send(&self, alert: &Alert) -> CoreResult<()>;
+}
+
+/// logs alerts as warnings.
+#[derive(Debug, Clone, Default)]
+pub struct LogSink;
+
+impl AlertSink for LogSink {
+    fn send(&self, alert: &Alert) -> CoreResult<()> {
+        warn!("alert: {}", alert);
+        Ok(())
+    }
+}
+
+/// prints alerts, one per line.
+#[derive(Debug, Clone, Default)]
+pub struct StdoutSink;
+
+impl AlertSink for StdoutSink {
+    fn send(&self, alert: &Alert) -> CoreResult<()> {
+        println!("{}", alert);
+        Ok(())
+    }
+}
+
+/// Posts each alert as json to a url, eg. a Slack or Discord incoming webhook behind a relay.
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    url: String,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str) -> Self {
+        WebhookSink {
+            url: url.to_string(),
+        }
+    }
+}
+
+impl AlertSink for WebhookSink {
+    fn send(&self, alert: &Alert) -> CoreResult<()> {
+        reqwest::Client::new()
+            .post(self.url.as_str())
+            .json(alert)
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Appends alerts to a file as json lines.
+#[derive(Debug, Clone)]
+pub struct FileSink {
+    path: PathBuf,
+}
+
+impl FileSink {
+    pub fn open<P: AsRef<Path>>(path: P) -> CoreResult<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        Ok(FileSink { path })
+    }
+
+    /// `~/.crypto/alerts.jsonl`, next to the config file.
+    pub fn open_default() -> CoreResult<Self> {
+        let home_path =
+            dirs::home_dir().ok_or_else(|| TrailerError::Generic(format!("cannot get homedir")))?;
+
+        Self::open(home_path.join(".crypto").join("alerts.jsonl"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AlertSink for FileSink {
+    fn send(&self, alert: &Alert) -> CoreResult<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(alert)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use crate::config::APIConfig;
+use crate::error::*;
+use crate::exchanges::ExchangeAPI;
+use crate::models::*;
+use std::collections::HashMap;
+
+/// Markets to keep an eye on, in the exchange's native symbols, eg. the `watch` list of an
+/// exchange's config.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Watchlist {
+    symbols: Vec<String>,
+}
+
+impl Watchlist {
+    pub fn new(symbols: Vec<String>) -> Self {
+        symbols
+            .iter()
+            .fold(Watchlist::default(), |list, symbol| list.symbol(symbol))
+    }
+
+    pub fn from_config(config: &APIConfig) -> Self {
+        Self::new(config.watch.clone().unwrap_or_default())
+    }
+
+    pub fn symbol(mut self, symbol: &str) -> Self {
+        self.add(symbol);
+        self
+    }
+
+    /// adds a market, returning false if it was already watched.
+    pub fn add(&mut self, symbol: &str) -> bool {
+        if self.contains(symbol) {
+            return false;
+        }
+        self.symbols.push(symbol.to_uppercase());
+        true
+    }
+
+    /// removes a market, returning whether it was watched.
+    pub fn remove(&mut self, symbol: &str) -> bool {
+        let before = self.symbols.len();
+        self.symbols.retain(|s| *s != symbol.to_uppercase());
+        self.symbols.len() != before
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.symbols.contains(&symbol.to_uppercase())
+    }
+
+    pub fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// The last price of each watched market in watchlist order, `None` for markets the
+    /// exchange does not list.
+    pub fn prices(&self, exchange: &dyn ExchangeAPI) -> CoreResult<Vec<(String, Option<Price>)>> {
+        let mut prices: HashMap<String, Price> = HashMap::new();
+        for price in exchange.all_prices()? {
+            if let Ok(symbol) = exchange.native_symbol(&price.pair) {
+                prices.insert(symbol.to_uppercase(), price);
+            }
+        }
+
+        Ok(self
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.clone(), prices.remove(symbol)))
+            .collect())
+    }
+}
@@ -0,0 +1,365 @@
+use crate::error::*;
+use crate::exchanges::{Exchange, ExchangeAPI};
+use crate::models::*;
+use crate::utils::*;
+use chrono::{offset::Local, prelude::DateTime};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use std::{fmt, fmt::Display};
+
+/// What it costs to take liquidity on an exchange and to move funds off it.
+#[derive(Debug, Clone)]
+pub struct ExchangeCosts {
+    /// a fraction of the notional, eg. `0.001` for 0.1%.
+    pub taker_fee: Decimal,
+    /// flat withdrawal fees by asset, in that asset.
+    pub withdrawal_fees: BTreeMap<String, Decimal>,
+}
+
+impl ExchangeCosts {
+    pub fn new(taker_fee: Decimal) -> Self {
+        ExchangeCosts {
+            taker_fee,
+            withdrawal_fees: BTreeMap::new(),
+        }
+    }
+
+    /// the exchange's standard taker fee, without volume discounts.
+    pub fn for_exchange(exchange: Exchange) -> Self {
+        match exchange {
+            Exchange::Huobi => Self::new(Decimal::new(2, 3)),
+            Exchange::Okex => Self::new(Decimal::new(15, 4)),
+            _ => Self::new(Decimal::new(1, 3)),
+        }
+    }
+
+    pub fn withdrawal_fee(mut self, asset: &str, fee: Decimal) -> Self {
+        self.withdrawal_fees.insert(asset.to_uppercase(), fee);
+        self
+    }
+
+    fn withdrawal(&self, asset: &str) -> Decimal {
+        self.withdrawal_fees
+            .get(&asset.to_uppercase())
+            .cloned()
+            .unwrap_or(Decimal::ZERO)
+    }
+}
+
+/// Buying a pair at the best ask on one exchange and selling it at the best bid on another, for
+/// the quantity available at both.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArbitrageOpportunity {
+    /// unix time in milliseconds.
+    pub time: u64,
+    pub symbol: String,
+    pub base: String,
+    pub buy_exchange: String,
+    pub buy_price: Decimal,
+    pub sell_exchange: String,
+    pub sell_price: Decimal,
+    pub qty: Decimal,
+    /// the difference between the prices as a percentage of the buy price, before costs.
+    pub gross_spread: Decimal,
+    /// in the quote asset, after fees on both sides and withdrawing the bought asset to the
+    /// selling exchange and the proceeds back.
+    pub net_profit: Decimal,
+    /// `net_profit` as a percentage of the cost of the buy.
+    pub net_spread: Decimal,
+}
+
+impl ArbitrageOpportunity {
+    /// Prices buying at `buy`'s ask and selling at `sell`'s bid, `None` when either side shows no
+    /// quantity.
+    pub fn evaluate(
+        time: u64,
+        buy: (Exchange, &BookTicker, &ExchangeCosts),
+        sell: (Exchange, &BookTicker, &ExchangeCosts),
+    ) -> Option<Self> {
+        let (buy_exchange, buy_ticker, buy_costs) = buy;
+        let (sell_exchange, sell_ticker, sell_costs) = sell;
+        let pair = &buy_ticker.pair;
+
+        let qty = buy_ticker.ask_qty.min(sell_ticker.bid_qty);
+        if qty <= Decimal::ZERO || buy_ticker.ask_price <= Decimal::ZERO {
+            return None;
+        }
+
+        let cost = qty * buy_ticker.ask_price * (Decimal::ONE + buy_costs.taker_fee);
+        let delivered = qty - buy_costs.withdrawal(&pair.symbol);
+        let proceeds = delivered.max(Decimal::ZERO)
+            * sell_ticker.bid_price
+            * (Decimal::ONE - sell_costs.taker_fee)
+            - sell_costs.withdrawal(&pair.base);
+        let net_profit = proceeds - cost;
+
+        Some(ArbitrageOpportunity {
+            time,
+            symbol: pair.symbol.clone(),
+            base: pair.base.clone(),
+            buy_exchange: buy_exchange.to_string(),
+            buy_price: buy_ticker.ask_price,
+            sell_exchange: sell_exchange.to_string(),
+            sell_price: sell_ticker.bid_price,
+            qty,
+            gross_spread: price_percent(buy_ticker.ask_price, sell_ticker.bid_price),
+            net_profit,
+            net_spread: net_profit
+                .checked_div(cost)
+                .map_or(Decimal::ZERO, |spread| spread * Decimal::new(100, 0)),
+        })
+    }
+
+    pub fn pair(&self) -> Pair {
+        Pair::new(&self.symbol, &self.base)
+    }
+
+    pub fn datetime(&self) -> DateTime<Local> {
+        local_datetime_from_unix(self.time)
+    }
+
+    pub fn is_profitable(&self) -> bool {
+        self.net_profit > Decimal::ZERO
+    }
+}
+
+impl Display for ArbitrageOpportunity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} buy {} @ {} sell {} @ {} qty {} gross {}% net {}% ({} {})",
+            self.datetime().format("%Y-%m-%d %H:%M:%S"),
+            self.pair(),
+            self.buy_exchange,
+            self.buy_price,
+            self.sell_exchange,
+            self.sell_price,
+            self.qty,
+            self.gross_spread.round_dp(4),
+            self.net_spread.round_dp(4),
+            self.net_profit.round_dp(8),
+            self.base
+        )
+    }
+}
+
+/// Compares the best bid and ask of every pair listed on more than one exchange. Read only, no
+/// orders are sent.
+///
+/// ```rust,ignore
+/// let scanner = ArbitrageScanner::new(configured_exchanges(&config)?)
+///     .costs(Exchange::Binance, ExchangeCosts::for_exchange(Exchange::Binance)
+///         .withdrawal_fee("BTC", Decimal::new(5, 4)))
+///     .min_net_spread(Decimal::new(1, 1));
+/// scanner.watch(&ArbitrageLog::open_default()?, Duration::from_secs(10), &running)?;
+/// ```
+pub struct ArbitrageScanner {
+    exchanges: Vec<Box<dyn ExchangeAPI>>,
+    costs: HashMap<Exchange, ExchangeCosts>,
+    pairs: Option<Vec<Pair>>,
+    min_net_spread: Option<Decimal>,
+}
+
+impl ArbitrageScanner {
+    pub fn new(exchanges: Vec<Box<dyn ExchangeAPI>>) -> Self {
+        ArbitrageScanner {
+            exchanges,
+            costs: HashMap::new(),
+            pairs: None,
+            min_net_spread: None,
+        }
+    }
+
+    /// overrides the standard costs of an exchange.
+    pub fn costs(mut self, exchange: Exchange, costs: ExchangeCosts) -> Self {
+        self.costs.insert(exchange, costs);
+        self
+    }
+
+    /// only scan these pairs.
+    pub fn pairs(mut self, pairs: Vec<Pair>) -> Self {
+        self.pairs = Some(pairs);
+        self
+    }
+
+    /// only report opportunities at least this net spread, in percent.
+    pub fn min_net_spread(mut self, min_net_spread: Decimal) -> Self {
+        self.min_net_spread = Some(min_net_spread);
+        self
+    }
+
+    /// Every buy/sell combination across exchanges, best net spread first. Exchanges whose
+    /// tickers cannot be fetched are left out with a warning.
+    pub fn scan(&self) -> CoreResult<Vec<ArbitrageOpportunity>> {
+        let time = Local::now().timestamp_millis() as u64;
+        let mut books: Vec<(Exchange, ExchangeCosts, Vec<BookTicker>)> = Vec::new();
+
+        for api in &self.exchanges {
+            let exchange = api.exchange();
+            match api.book_tickers() {
+                Ok(tickers) => books.push((
+                    exchange,
+                    self.costs
+                        .get(&exchange)
+                        .cloned()
+                        .unwrap_or_else(|| ExchangeCosts::for_exchange(exchange)),
+                    tickers
+                        .into_iter()
+                        .filter(|ticker| self.wanted(&ticker.pair))
+                        .collect(),
+                )),
+                Err(e) => warn!("{}: cannot fetch book tickers: {}", api.display(), e),
+            }
+        }
+
+        let mut opportunities = Vec::new();
+        for (buy_exchange, buy_costs, buy_tickers) in &books {
+            for (sell_exchange, sell_costs, sell_tickers) in &books {
+                if buy_exchange == sell_exchange {
+                    continue;
+                }
+
+                for buy in buy_tickers {
+                    let sell = match sell_tickers.iter().find(|sell| sell.pair == buy.pair) {
+                        Some(sell) => sell,
+                        None => continue,
+                    };
+
+                    if let Some(opportunity) = ArbitrageOpportunity::evaluate(
+                        time,
+                        (*buy_exchange, buy, buy_costs),
+                        (*sell_exchange, sell, sell_costs),
+                    ) {
+                        let wanted = self
+                            .min_net_spread
+                            .map_or(true, |min| opportunity.net_spread >= min);
+                        if wanted {
+                            opportunities.push(opportunity);
+                        }
+                    }
+                }
+            }
+        }
+
+        opportunities.sort_by(|a, b| b.net_spread.cmp(&a.net_spread));
+        Ok(opportunities)
+    }
+
+    /// Scans every `every` until `running` is cleared, logging the profitable opportunities.
+    /// Failed scans are logged and retried.
+    pub fn watch(
+        &self,
+        log: &ArbitrageLog,
+        every: Duration,
+        running: &AtomicBool,
+    ) -> CoreResult<()> {
+        while running.load(Ordering::Relaxed) {
+            match self.scan() {
+                Ok(opportunities) => {
+                    let profitable: Vec<ArbitrageOpportunity> = opportunities
+                        .into_iter()
+                        .filter(|opportunity| opportunity.is_profitable())
+                        .collect();
+                    for opportunity in &profitable {
+                        info!("{}", opportunity);
+                    }
+                    log.append(&profitable)?;
+                }
+                Err(e) => warn!("arbitrage scan failed: {}", e),
+            }
+            thread::sleep(every);
+        }
+        Ok(())
+    }
+
+    fn wanted(&self, pair: &Pair) -> bool {
+        self.pairs
+            .as_ref()
+            .map_or(true, |pairs| pairs.contains(pair))
+    }
+}
+
+/// Opportunities appended to a local file, one json document per line, oldest first.
+#[derive(Debug, Clone)]
+pub struct ArbitrageLog {
+    path: PathBuf,
+}
+
+impl ArbitrageLog {
+    pub fn open<P: AsRef<Path>>(path: P) -> CoreResult<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        Ok(ArbitrageLog { path })
+    }
+
+    /// `~/.crypto/arbitrage.jsonl`, next to the config file.
+    pub fn open_default() -> CoreResult<Self> {
+        let home_path =
+            dirs::home_dir().ok_or_else(|| TrailerError::Generic(format!("cannot get homedir")))?;
+
+        Self::open(home_path.join(".crypto").join("arbitrage.jsonl"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, opportunities: &[ArbitrageOpportunity]) -> CoreResult<()> {
+        if opportunities.is_empty() {
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        for opportunity in opportunities {
+            writeln!(file, "{}", serde_json::to_string(opportunity)?)?;
+        }
+        Ok(())
+    }
+
+    /// every logged opportunity, oldest first. Lines that cannot be read are skipped with a
+    /// warning.
+    pub fn entries(&self) -> CoreResult<Vec<ArbitrageOpportunity>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for (number, line) in BufReader::new(File::open(&self.path)?).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<ArbitrageOpportunity>(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!(
+                    "skipping opportunity on line {} of {}: {}",
+                    number + 1,
+                    self.path.display(),
+                    e
+                ),
+            }
+        }
+
+        entries.sort_by_key(|entry| entry.time);
+        Ok(entries)
+    }
+}
@@ -0,0 +1,457 @@
+use crate::arbitrage::*;
+use crate::error::*;
+use crate::exchanges::ExchangeAPI;
+use crate::models::*;
+use rust_decimal::Decimal;
+use std::collections::{BTreeSet, HashMap};
+use std::{fmt, fmt::Display};
+
+/// One trade of a cycle: buying at the ask when spending the quote asset, selling at the bid when
+/// spending the base asset.
+#[derive(Debug, Clone)]
+pub struct TriangularLeg {
+    pub pair: Pair,
+    pub side: TradeType,
+    pub price: Decimal,
+    /// the quantity shown at `price`, in the pair's base asset.
+    pub qty: Decimal,
+}
+
+/// Three trades through three assets, back to the one started with.
+#[derive(Debug, Clone)]
+pub struct TriangularOpportunity {
+    /// the assets in trading order, the first repeated at the end.
+    pub path: Vec<String>,
+    pub legs: Vec<TriangularLeg>,
+    /// percent gained on the round trip, after fees.
+    pub return_percent: Decimal,
+    /// the most of the starting asset the top of each book can take, in that asset.
+    pub max_start: Decimal,
+    /// gained on `max_start`, in the starting asset.
+    pub profit: Decimal,
+}
+
+impl TriangularOpportunity {
+    pub fn start_asset(&self) -> &str {
+        &self.path[0]
+    }
+
+    /// whether `amount` of the starting asset fits in the top of every book.
+    pub fn is_executable(&self, amount: Decimal) -> bool {
+        amount > Decimal::ZERO && amount <= self.max_start
+    }
+}
+
+impl Display for TriangularOpportunity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {}% up to {} {} ({} {})",
+            self.path.join(" -> "),
+            self.return_percent.round_dp(4),
+            self.max_start.round_dp(8),
+            self.start_asset(),
+            self.profit.round_dp(8),
+            self.start_asset()
+        )
+    }
+}
+
+type MarketKey = (String, String);
+
+fn market_key(pair: &Pair) -> MarketKey {
+    (pair.symbol.to_uppercase(), pair.base.to_uppercase())
+}
+
+#[derive(Debug, Clone)]
+struct Cycle {
+    path: Vec<String>,
+    /// the market of each leg.
+    markets: Vec<Pair>,
+}
+
+/// Finds round trips through three markets of one exchange that return more than they cost,
+/// eg. USDT -> BTC -> ETH -> USDT over BTC/USDT, ETH/BTC and ETH/USDT. Cycles are worked out
+/// once from the exchange's pairs; quotes are then fed one at a time and only the cycles through
+/// the updated market are priced again.
+///
+/// ```rust,ignore
+/// let mut detector = TriangularDetector::for_exchange(&binance)?;
+/// for opportunity in detector.scan(&binance)? {
+///     println!("{}", opportunity);
+/// }
+/// // or from a feed
+/// let found = detector.update(&ticker);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TriangularDetector {
+    cycles: Vec<Cycle>,
+    /// the cycles trading through each market.
+    by_market: HashMap<MarketKey, Vec<usize>>,
+    books: HashMap<MarketKey, BookTicker>,
+    taker_fee: Decimal,
+    min_return: Decimal,
+}
+
+impl TriangularDetector {
+    /// Enumerates every triangle the pairs form. Each is traded in both directions, starting from
+    /// the asset that is the quote of most of its markets (eg. USDT), see `start_asset`.
+    pub fn new(pairs: &[Pair]) -> Self {
+        let mut links: HashMap<String, BTreeSet<String>> = HashMap::new();
+        let mut markets: HashMap<MarketKey, Pair> = HashMap::new();
+
+        for pair in pairs {
+            let (symbol, base) = market_key(pair);
+            if symbol == base {
+                continue;
+            }
+            links
+                .entry(symbol.clone())
+                .or_default()
+                .insert(base.clone());
+            links
+                .entry(base.clone())
+                .or_default()
+                .insert(symbol.clone());
+            markets.insert((symbol.clone(), base.clone()), pair.clone());
+        }
+
+        let mut triangles: BTreeSet<Vec<String>> = BTreeSet::new();
+        for (symbol, base) in markets.keys() {
+            let (symbol_links, base_links) = (&links[symbol], &links[base]);
+            for third in symbol_links.intersection(base_links) {
+                let mut triangle = vec![symbol.clone(), base.clone(), third.clone()];
+                triangle.sort();
+                triangles.insert(triangle);
+            }
+        }
+
+        let market = |a: &str, b: &str| {
+            markets
+                .get(&(a.to_string(), b.to_string()))
+                .or_else(|| markets.get(&(b.to_string(), a.to_string())))
+                .cloned()
+        };
+
+        let mut cycles = Vec::new();
+        for triangle in triangles {
+            let start = triangle
+                .iter()
+                .max_by_key(|asset| {
+                    let quoted = markets
+                        .keys()
+                        .filter(|(symbol, base)| &base == asset && triangle.contains(symbol))
+                        .count();
+                    // most quoted first, ties to the first alphabetically.
+                    (quoted, std::cmp::Reverse((*asset).clone()))
+                })
+                .cloned()
+                .unwrap_or_default();
+            let others: Vec<&String> = triangle.iter().filter(|a| **a != start).collect();
+
+            for (first, second) in vec![(others[0], others[1]), (others[1], others[0])] {
+                let path = vec![start.clone(), first.clone(), second.clone(), start.clone()];
+                let legs: Option<Vec<Pair>> =
+                    path.windows(2).map(|w| market(&w[0], &w[1])).collect();
+
+                if let Some(legs) = legs {
+                    cycles.push(Cycle {
+                        path,
+                        markets: legs,
+                    });
+                }
+            }
+        }
+
+        let mut by_market: HashMap<MarketKey, Vec<usize>> = HashMap::new();
+        for (index, cycle) in cycles.iter().enumerate() {
+            for pair in &cycle.markets {
+                by_market.entry(market_key(pair)).or_default().push(index);
+            }
+        }
+
+        TriangularDetector {
+            cycles,
+            by_market,
+            books: HashMap::new(),
+            taker_fee: Decimal::new(1, 3),
+            min_return: Decimal::ZERO,
+        }
+    }
+
+    /// the triangles of every pair listed on the exchange, at its standard taker fee.
+    pub fn for_exchange(exchange: &dyn ExchangeAPI) -> CoreResult<Self> {
+        Ok(Self::new(&exchange.all_pairs()?)
+            .taker_fee(ExchangeCosts::for_exchange(exchange.exchange()).taker_fee))
+    }
+
+    /// a fraction of the notional paid on every leg, eg. `0.001` for 0.1%.
+    pub fn taker_fee(mut self, taker_fee: Decimal) -> Self {
+        self.taker_fee = taker_fee;
+        self
+    }
+
+    /// only report cycles returning more than this, in percent.
+    pub fn min_return(mut self, min_return: Decimal) -> Self {
+        self.min_return = min_return;
+        self
+    }
+
+    /// Starts cycles from `asset` wherever it is part of the triangle, eg. the asset held.
+    pub fn start_asset(mut self, asset: &str) -> Self {
+        let asset = asset.to_uppercase();
+
+        for cycle in self.cycles.iter_mut() {
+            if let Some(at) = cycle.path[..3].iter().position(|a| *a == asset) {
+                cycle.path.pop();
+                cycle.path.rotate_left(at);
+                cycle.path.push(asset.clone());
+                cycle.markets.rotate_left(at);
+            }
+        }
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.cycles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cycles.is_empty()
+    }
+
+    /// Records a market's best bid and ask, returning the cycles through it that are now
+    /// profitable.
+    pub fn update(&mut self, ticker: &BookTicker) -> Vec<TriangularOpportunity> {
+        let key = market_key(&ticker.pair);
+        self.books.insert(key.clone(), ticker.clone());
+
+        let mut found: Vec<TriangularOpportunity> = self
+            .by_market
+            .get(&key)
+            .map(|cycles| cycles.iter().filter_map(|&c| self.evaluate(c)).collect())
+            .unwrap_or_default();
+        sort_by_return(&mut found);
+        found
+    }
+
+    /// records the top of a streamed book, see `update`.
+    pub fn update_depth(&mut self, pair: &Pair, depth: &Depth) -> Vec<TriangularOpportunity> {
+        match (depth.best_bid(), depth.best_ask()) {
+            (Some(bid), Some(ask)) => self.update(&BookTicker {
+                pair: pair.clone(),
+                bid_price: bid.price,
+                bid_qty: bid.qty,
+                ask_price: ask.price,
+                ask_qty: ask.qty,
+            }),
+            _ => Vec::new(),
+        }
+    }
+
+    /// every profitable cycle on the quotes known so far, best return first.
+    pub fn opportunities(&self) -> Vec<TriangularOpportunity> {
+        let mut found: Vec<TriangularOpportunity> = (0..self.cycles.len())
+            .filter_map(|c| self.evaluate(c))
+            .collect();
+        sort_by_return(&mut found);
+        found
+    }
+
+    /// Refreshes every book from the exchange's book tickers and returns the profitable cycles.
+    pub fn scan(&mut self, exchange: &dyn ExchangeAPI) -> CoreResult<Vec<TriangularOpportunity>> {
+        for ticker in exchange.book_tickers()? {
+            self.books.insert(market_key(&ticker.pair), ticker);
+        }
+        Ok(self.opportunities())
+    }
+
+    fn evaluate(&self, index: usize) -> Option<TriangularOpportunity> {
+        let cycle = &self.cycles[index];
+        let keep = Decimal::ONE - self.taker_fee;
+
+        // units of the asset held before each leg, per unit of the starting asset.
+        let mut rate = Decimal::ONE;
+        let mut max_start: Option<Decimal> = None;
+        let mut legs = Vec::new();
+
+        for (from, pair) in cycle.path.iter().zip(cycle.markets.iter()) {
+            let book = self.books.get(&market_key(pair))?;
+
+            let (side, price, qty, capacity, next_rate) = if pair.base.to_uppercase() == *from {
+                let capacity = book.ask_qty * book.ask_price;
+                (
+                    TradeType::Buy,
+                    book.ask_price,
+                    book.ask_qty,
+                    capacity,
+                    (rate * keep).checked_div(book.ask_price)?,
+                )
+            } else {
+                (
+                    TradeType::Sell,
+                    book.bid_price,
+                    book.bid_qty,
+                    book.bid_qty,
+                    rate * book.bid_price * keep,
+                )
+            };
+            if price <= Decimal::ZERO {
+                return None;
+            }
+
+            let start_capacity = capacity.checked_div(rate)?;
+            max_start = Some(max_start.map_or(start_capacity, |max| max.min(start_capacity)));
+            rate = next_rate;
+            legs.push(TriangularLeg {
+                pair: pair.clone(),
+                side,
+                price,
+                qty,
+            });
+        }
+
+        let return_percent = (rate - Decimal::ONE) * Decimal::new(100, 0);
+        if return_percent <= self.min_return {
+            return None;
+        }
+
+        let max_start = max_start.unwrap_or(Decimal::ZERO);
+        Some(TriangularOpportunity {
+            path: cycle.path.clone(),
+            legs,
+            return_percent,
+            max_start,
+            profit: max_start * (rate - Decimal::ONE),
+        })
+    }
+}
+
+fn sort_by_return(opportunities: &mut Vec<TriangularOpportunity>) {
+    opportunities.sort_by(|a, b| b.return_percent.cmp(&a.return_percent));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn ticker(symbol: &str, base: &str, bid: (&str, &str), ask: (&str, &str)) -> BookTicker {
+        BookTicker {
+            pair: Pair::new(symbol, base),
+            bid_price: dec(bid.0),
+            bid_qty: dec(bid.1),
+            ask_price: dec(ask.0),
+            ask_qty: dec(ask.1),
+        }
+    }
+
+    fn detector() -> TriangularDetector {
+        TriangularDetector::new(&[
+            Pair::new("BTC", "USDT"),
+            Pair::new("ETH", "BTC"),
+            Pair::new("ETH", "USDT"),
+        ])
+        .taker_fee(Decimal::ZERO)
+    }
+
+    /// USDT -> BTC -> ETH -> USDT returns 10% before fees, the other way round loses.
+    fn tickers() -> Vec<BookTicker> {
+        vec![
+            ticker("BTC", "USDT", ("9990", "1"), ("10000", "1")),
+            ticker("ETH", "BTC", ("0.0199", "10"), ("0.02", "10")),
+            ticker("ETH", "USDT", ("220", "5"), ("221", "5")),
+        ]
+    }
+
+    fn feed(
+        detector: &mut TriangularDetector,
+        tickers: &[BookTicker],
+    ) -> Vec<TriangularOpportunity> {
+        tickers
+            .iter()
+            .map(|ticker| detector.update(ticker))
+            .last()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn cycles_start_from_the_most_quoted_asset() {
+        let detector = detector();
+        let paths: Vec<Vec<String>> = detector.cycles.iter().map(|c| c.path.clone()).collect();
+
+        assert_eq!(detector.len(), 2);
+        assert!(paths
+            .iter()
+            .all(|path| path[0] == "USDT" && path[3] == "USDT"));
+    }
+
+    #[test]
+    fn profitable_cycle_is_found_once_every_book_is_known() {
+        let mut detector = detector();
+
+        assert!(detector.update(&tickers()[0]).is_empty());
+        assert!(detector.update(&tickers()[1]).is_empty());
+
+        let found = detector.update(&tickers()[2]);
+        assert_eq!(found.len(), 1);
+
+        let opportunity = &found[0];
+        assert_eq!(opportunity.path, vec!["USDT", "BTC", "ETH", "USDT"]);
+        let sides: Vec<TradeType> = opportunity.legs.iter().map(|leg| leg.side).collect();
+        assert_eq!(sides, vec![TradeType::Buy, TradeType::Buy, TradeType::Sell]);
+        assert_eq!(opportunity.return_percent, dec("10"));
+    }
+
+    #[test]
+    fn capacity_is_the_smallest_book_in_starting_units() {
+        let mut detector = detector();
+        let found = feed(&mut detector, &tickers());
+
+        // 10000 USDT of BTC, 0.2 BTC of ETH worth 2000 USDT, 5 ETH worth 1000 USDT.
+        assert_eq!(found[0].max_start, dec("1000"));
+        assert_eq!(found[0].profit, dec("100"));
+        assert!(found[0].is_executable(dec("1000")));
+        assert!(!found[0].is_executable(dec("1000.01")));
+
+        let mut tickers = tickers();
+        tickers[0].ask_qty = dec("0.05");
+        let found = feed(&mut detector, &tickers);
+        assert_eq!(found[0].max_start, dec("500"));
+    }
+
+    #[test]
+    fn fees_are_paid_on_every_leg() {
+        let mut detector = detector().taker_fee(dec("0.001"));
+        let found = feed(&mut detector, &tickers());
+
+        // 1.1 * 0.999^3
+        assert_eq!(found[0].return_percent.round_dp(6), dec("9.670330"));
+        assert_eq!(found[0].max_start.round_dp(2), dec("1002.00"));
+    }
+
+    #[test]
+    fn min_return_and_dead_books_are_left_out() {
+        let mut strict = detector().min_return(dec("15"));
+        assert!(feed(&mut strict, &tickers()).is_empty());
+
+        let mut detector = detector();
+        let mut tickers = tickers();
+        tickers[2].bid_price = Decimal::ZERO;
+        assert!(feed(&mut detector, &tickers).is_empty());
+        assert!(detector.opportunities().is_empty());
+    }
+
+    #[test]
+    fn cycles_can_start_from_the_asset_held() {
+        let mut detector = detector().start_asset("btc");
+        let found = feed(&mut detector, &tickers());
+
+        assert_eq!(found[0].path, vec!["BTC", "ETH", "USDT", "BTC"]);
+        assert_eq!(found[0].start_asset(), "BTC");
+        assert_eq!(found[0].return_percent, dec("10"));
+    }
+}
@@ -0,0 +1,116 @@
+use crate::backtest::*;
+use crate::error::*;
+use crate::exchanges::{paper_api::*, ExchangeAPI};
+use crate::models::*;
+use crate::strategies::{MarketEvent, Strategy};
+use crate::utils::*;
+use rust_decimal::Decimal;
+
+#[derive(Debug, Clone)]
+pub struct BacktestReport {
+    pub pair: Pair,
+    pub equity_curve: Vec<BacktestPoint>,
+    pub fills: Vec<Trade>,
+    pub statistics: BacktestStatistics,
+}
+
+/// Replays the history of one market through a strategy, trading on a `PaperAPI`.
+///
+/// ```rust,ignore
+/// let report = Backtester::new("BTCUSDT", &pair)
+///     .fill_model(FillModel { slippage: Decimal::new(5, 4), ..FillModel::default() })
+///     .deposit("USDT", Decimal::new(10_000, 0))
+///     .run_candles(&mut strategy, &candles)?;
+/// println!("{}", report.statistics);
+/// ```
+pub struct Backtester {
+    symbol: String,
+    pair: Pair,
+    exchange: PaperAPI,
+}
+
+impl Backtester {
+    pub fn new(symbol: &str, pair: &Pair) -> Self {
+        Backtester {
+            symbol: symbol.to_string(),
+            pair: pair.clone(),
+            exchange: PaperAPI::new().market(symbol, pair),
+        }
+    }
+
+    pub fn fill_model(mut self, fill_model: FillModel) -> Self {
+        self.exchange = self.exchange.fill_model(fill_model);
+        self
+    }
+
+    /// starting balance of an asset.
+    pub fn deposit(self, symbol: &str, amount: Decimal) -> Self {
+        self.exchange.deposit(symbol, amount);
+        self
+    }
+
+    pub fn run_candles<S: Strategy + ?Sized>(
+        self,
+        strategy: &mut S,
+        candles: &[Candlestick],
+    ) -> CoreResult<BacktestReport> {
+        self.run(
+            strategy,
+            candles.iter().cloned().map(MarketEvent::Candle).collect(),
+        )
+    }
+
+    /// Replays `events` in time order. Orders are matched against each event before the strategy
+    /// sees it, so an order placed on one candle fills at the earliest on the next.
+    pub fn run<S: Strategy + ?Sized>(
+        self,
+        strategy: &mut S,
+        mut events: Vec<MarketEvent>,
+    ) -> CoreResult<BacktestReport> {
+        events.sort_by_key(|event| event.time());
+
+        let exchange = &self.exchange;
+        let mut equity_curve = Vec::with_capacity(events.len());
+
+        for event in &events {
+            if let Some(quote) = event.quote() {
+                for fill in exchange.update(&self.symbol, &quote) {
+                    strategy.on_fill(exchange, &fill)?;
+                }
+            }
+
+            match event {
+                MarketEvent::Candle(candle) => {
+                    strategy.on_candle(exchange, &self.symbol, candle)?
+                }
+                MarketEvent::Trade(trade) => strategy.on_trade(exchange, &self.symbol, trade)?,
+                MarketEvent::BookTicker(_, ticker) => {
+                    exchange.update_book_ticker(&self.symbol, ticker);
+                    strategy.on_book_ticker(exchange, &self.symbol, ticker)?
+                }
+                MarketEvent::Depth(_, depth) => strategy.on_depth(exchange, &self.symbol, depth)?,
+            }
+
+            equity_curve.push(BacktestPoint {
+                time: local_datetime_from_unix(event.time()),
+                equity: exchange.equity(&self.pair.base).ok_or_else(|| {
+                    Box::new(TrailerError::Generic(format!(
+                        "can't value the balances in {}",
+                        self.pair.base
+                    )))
+                })?,
+                position: exchange.balance(&self.pair.symbol),
+            });
+        }
+
+        let fills = exchange.fills();
+        let statistics = BacktestStatistics::new(&equity_curve, &fills);
+
+        Ok(BacktestReport {
+            pair: self.pair,
+            equity_curve,
+            fills,
+            statistics,
+        })
+    }
+}
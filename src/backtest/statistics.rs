@@ -0,0 +1,260 @@
+use crate::indicators::sqrt;
+use crate::models::*;
+use crate::utils::*;
+use chrono::prelude::*;
+use rust_decimal::Decimal;
+use std::{fmt, fmt::Display};
+
+static YEAR_MILLIS: i64 = 365 * 24 * 60 * 60 * 1000;
+
+/// Account value after an event of the backtest.
+#[derive(Debug, Clone)]
+pub struct BacktestPoint {
+    pub time: DateTime<Local>,
+    /// every balance valued in the quote asset of the market.
+    pub equity: Decimal,
+    /// the amount of the traded asset held.
+    pub position: Decimal,
+}
+
+#[derive(Debug, Clone)]
+pub struct BacktestStatistics {
+    pub starting_equity: Decimal,
+    pub final_equity: Decimal,
+    /// percent.
+    pub total_return: Decimal,
+    /// annualized, with no risk free rate. `None` without any variation in returns.
+    pub sharpe_ratio: Option<Decimal>,
+    /// annualized, like sharpe but only penalising losses. `None` without any losses.
+    pub sortino_ratio: Option<Decimal>,
+    /// largest fall from a peak, in percent.
+    pub max_drawdown: Decimal,
+    /// percent of closing trades that made a profit after fees.
+    pub win_rate: Decimal,
+    pub fills: usize,
+    pub closing_trades: usize,
+    /// percent of the time a position was held.
+    pub exposure: Decimal,
+    pub fees: Decimal,
+}
+
+impl BacktestStatistics {
+    pub fn new(curve: &[BacktestPoint], fills: &[Trade]) -> Self {
+        let starting_equity = curve.first().map_or(Decimal::ZERO, |point| point.equity);
+        let final_equity = curve.last().map_or(Decimal::ZERO, |point| point.equity);
+
+        let returns: Vec<Decimal> = curve
+            .windows(2)
+            .filter_map(|pair| {
+                pair[1]
+                    .equity
+                    .checked_div(pair[0].equity)
+                    .map(|ratio| ratio - Decimal::ONE)
+            })
+            .collect();
+
+        let periods_per_year = periods_per_year(curve);
+        let mean = average(&returns);
+        let deviation = sqrt(average(
+            &returns.iter().map(|r| (*r - mean) * (*r - mean)).collect(),
+        ));
+        let downside = sqrt(average(
+            &returns
+                .iter()
+                .map(|r| (*r).min(Decimal::ZERO) * (*r).min(Decimal::ZERO))
+                .collect(),
+        ));
+        let annualize = |ratio: Decimal| ratio * sqrt(periods_per_year);
+
+        let mut peak = Decimal::ZERO;
+        let mut max_drawdown = Decimal::ZERO;
+        for point in curve {
+            peak = peak.max(point.equity);
+            max_drawdown = max_drawdown.max(-price_percent(peak, point.equity));
+        }
+
+        let disposals: Vec<Disposal> = LotLedger::from_trades(CostBasisMethod::Fifo, fills)
+            .map(|ledger| ledger.disposals().to_vec())
+            .unwrap_or_default();
+        let wins = disposals
+            .iter()
+            .filter(|disposal| disposal.realized_pnl() > Decimal::ZERO)
+            .count();
+
+        let exposed = curve
+            .iter()
+            .filter(|point| !point.position.is_zero())
+            .count();
+
+        BacktestStatistics {
+            starting_equity,
+            final_equity,
+            total_return: price_percent(starting_equity, final_equity),
+            sharpe_ratio: mean.checked_div(deviation).map(annualize),
+            sortino_ratio: mean.checked_div(downside).map(annualize),
+            max_drawdown,
+            win_rate: percent_of(wins, disposals.len()),
+            fills: fills.len(),
+            closing_trades: disposals.len(),
+            exposure: percent_of(exposed, curve.len()),
+            fees: fills.iter().map(|fill| fill.fee).sum(),
+        }
+    }
+}
+
+/// how many points of the curve make a year, by their average spacing.
+fn periods_per_year(curve: &[BacktestPoint]) -> Decimal {
+    match (curve.first(), curve.last()) {
+        (Some(first), Some(last)) if curve.len() > 1 => {
+            let span = (last.time - first.time).num_milliseconds();
+            Decimal::from(YEAR_MILLIS * (curve.len() as i64 - 1))
+                .checked_div(Decimal::from(span))
+                .unwrap_or(Decimal::ZERO)
+        }
+        _ => Decimal::ZERO,
+    }
+}
+
+fn percent_of(count: usize, total: usize) -> Decimal {
+    (Decimal::from(count) * Decimal::new(100, 0))
+        .checked_div(Decimal::from(total))
+        .unwrap_or(Decimal::ZERO)
+}
+
+impl Display for BacktestStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ratio =
+            |value: Option<Decimal>| value.map_or("-".to_string(), |v| v.round_dp(2).to_string());
+
+        writeln!(
+            f,
+            "equity       {} -> {}",
+            self.starting_equity.round_dp(2),
+            self.final_equity.round_dp(2)
+        )?;
+        writeln!(f, "return       {}%", self.total_return.round_dp(2))?;
+        writeln!(f, "sharpe       {}", ratio(self.sharpe_ratio))?;
+        writeln!(f, "sortino      {}", ratio(self.sortino_ratio))?;
+        writeln!(f, "max drawdown {}%", self.max_drawdown.round_dp(2))?;
+        writeln!(
+            f,
+            "win rate     {}% of {} closing trades",
+            self.win_rate.round_dp(2),
+            self.closing_trades
+        )?;
+        writeln!(f, "exposure     {}%", self.exposure.round_dp(2))?;
+        write!(
+            f,
+            "fills        {} paying {} in fees",
+            self.fills,
+            self.fees.round_dp(2)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn day(day: u32) -> DateTime<Local> {
+        Local
+            .from_local_datetime(
+                &NaiveDate::from_ymd_opt(2019, 1, day)
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    .unwrap(),
+            )
+            .unwrap()
+    }
+
+    fn point(day_of_month: u32, equity: &str, position: &str) -> BacktestPoint {
+        BacktestPoint {
+            time: day(day_of_month),
+            equity: dec(equity),
+            position: dec(position),
+        }
+    }
+
+    fn fill(day_of_month: u32, trade_type: TradeType, price: &str, fee: &str) -> Trade {
+        Trade {
+            id: day_of_month.to_string(),
+            pair: Pair::new("BTC", "USDT"),
+            trade_type,
+            qty: Decimal::ONE,
+            sale_price: dec(price),
+            fee: dec(fee),
+            fee_symbol: Some("USDT".to_string()),
+            time: day(day_of_month),
+        }
+    }
+
+    fn statistics() -> BacktestStatistics {
+        let curve = vec![
+            point(1, "1000", "0"),
+            point(2, "1100", "1"),
+            point(3, "990", "1"),
+            point(4, "1050", "0"),
+        ];
+        let fills = vec![
+            fill(1, TradeType::Buy, "100", "0.1"),
+            fill(2, TradeType::Sell, "110", "0.11"),
+            fill(2, TradeType::Buy, "110", "0.11"),
+            fill(3, TradeType::Sell, "100", "0.1"),
+        ];
+        BacktestStatistics::new(&curve, &fills)
+    }
+
+    #[test]
+    fn return_and_drawdown_from_the_curve() {
+        let statistics = statistics();
+
+        assert_eq!(statistics.starting_equity, dec("1000"));
+        assert_eq!(statistics.final_equity, dec("1050"));
+        assert_eq!(statistics.total_return, dec("5"));
+        // 1100 down to 990.
+        assert_eq!(statistics.max_drawdown, dec("10"));
+        assert_eq!(statistics.exposure, dec("50"));
+        assert!(statistics.sharpe_ratio.is_some());
+        assert!(statistics.sortino_ratio.is_some());
+    }
+
+    #[test]
+    fn win_rate_and_fees_from_the_fills() {
+        let statistics = statistics();
+
+        assert_eq!(statistics.fills, 4);
+        assert_eq!(statistics.closing_trades, 2);
+        assert_eq!(statistics.win_rate, dec("50"));
+        assert_eq!(statistics.fees, dec("0.42"));
+    }
+
+    #[test]
+    fn ratios_need_variation_and_losses() {
+        let rising = vec![
+            point(1, "1000", "1"),
+            point(2, "1100", "1"),
+            point(3, "1210", "1"),
+        ];
+        let statistics = BacktestStatistics::new(&rising, &[]);
+
+        assert_eq!(statistics.total_return, dec("21"));
+        assert_eq!(statistics.max_drawdown, Decimal::ZERO);
+        assert_eq!(statistics.sharpe_ratio, None);
+        assert_eq!(statistics.sortino_ratio, None);
+        assert_eq!(statistics.win_rate, Decimal::ZERO);
+        assert_eq!(statistics.exposure, dec("100"));
+    }
+
+    #[test]
+    fn an_empty_backtest_is_all_zero() {
+        let statistics = BacktestStatistics::new(&[], &[]);
+
+        assert_eq!(statistics.total_return, Decimal::ZERO);
+        assert_eq!(statistics.max_drawdown, Decimal::ZERO);
+        assert_eq!(statistics.exposure, Decimal::ZERO);
+        assert_eq!(statistics.fees, Decimal::ZERO);
+    }
+}
@@ -5,7 +5,9 @@ use serde_derive::Deserialize;
 use std::collections::BTreeMap;
 use toml;
 
+use crate::alerts::AlertRule;
 use crate::error::*;
+use crate::risk::RiskLimits;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
@@ -16,8 +18,11 @@ pub struct Config {
 pub struct APIConfig {
     pub api_key: String,                // todo: optional
     pub secret_key: String,             // todo: optional
+    pub passphrase: Option<String>,     // okex only
     pub positions: Option<Vec<String>>, // todo: remove
-    pub watch: Option<Vec<String>>,     // todo: remove
+    pub watch: Option<Vec<String>>,     // native symbols, see `Watchlist`
+    pub alerts: Option<Vec<AlertRule>>,
+    pub risk: Option<RiskLimits>,
 }
 
 pub fn read() -> CoreResult<Config> {
@@ -14,6 +14,7 @@ pub enum TrailerError {
     APIError(String),
     MissingArgumentError(String),
     PairNotFound(String),
+    RiskRejected(String),
 }
 
 impl Error for TrailerError {
@@ -28,11 +29,12 @@ impl Display for TrailerError {
             TrailerError::ImportError => write!(f, "ImportError"),
             TrailerError::APIError(err) => write!(f, "APIError: {}", err),
             TrailerError::CommandError => write!(f, "CommandError"),
-            TrailerError::ConfigError(err) => write!(f, "Config error"),
+            TrailerError::ConfigError(err) => write!(f, "ConfigError: {}", err),
             TrailerError::Unsupported => write!(f, "Unsupported"),
             TrailerError::Generic(err) => write!(f, "Generic: {}", err),
             TrailerError::MissingArgumentError(err) => write!(f, "MissingArgumentError: {}", err),
             TrailerError::PairNotFound(pair) => write!(f, "PairNotFound: {}", pair),
+            TrailerError::RiskRejected(reason) => write!(f, "RiskRejected: {}", reason),
         }
     }
 }
@@ -1,40 +1,100 @@
 pub mod binance_api;
 pub mod huobi_api;
 pub mod okex_api;
+pub mod paper_api;
+use crate::config::*;
 use crate::error::*;
 use crate::models::*;
+use crate::risk::RiskGuard;
+use rust_decimal::Decimal;
 use serde_derive::Deserialize;
+use std::sync::Arc;
 
 pub trait ExchangeAPI {
-    fn new() -> Self;
-    fn authenticate(&self, key: &str, secret: &str) -> Self;
+    fn new() -> Self
+    where
+        Self: Sized;
+    fn authenticate(&self, key: &str, secret: &str) -> Self
+    where
+        Self: Sized;
     fn display(&self) -> String;
     fn btc_symbol(&self) -> String;
     fn usd_symbol(&self) -> String;
     fn base_pairs(&self) -> Vec<String>;
     fn balances(&self) -> CoreResult<Vec<Asset>>;
-    fn pair(&self, pair: &str) -> CoreResult<Pair>;
-    fn all_pairs(&self) -> CoreResult<Vec<Pair>>;
+    /// every market listed by the exchange, mapped to and from its native symbols.
+    fn instruments(&self) -> CoreResult<Arc<InstrumentMap>>;
     fn all_prices(&self) -> CoreResult<Vec<Price>>;
-    fn limit_buy(&self, symbol: &str, amount: f64, price: f64) -> CoreResult<()>;
-    fn limit_sell(&self, symbol: &str, amount: f64, price: f64) -> CoreResult<()>;
+    fn limit_buy(&self, symbol: &str, amount: Decimal, price: Decimal) -> CoreResult<()>;
+    fn limit_sell(&self, symbol: &str, amount: Decimal, price: Decimal) -> CoreResult<()>;
+    /// withdraws an open order, `order_id` as in `open_orders`.
+    fn cancel_order(&self, symbol: &str, order_id: &str) -> CoreResult<()>;
     fn open_orders(&self) -> CoreResult<Vec<Order>>;
     fn past_orders(&self) -> CoreResult<Vec<Order>>;
     fn book_tickers(&self) -> CoreResult<Vec<BookTicker>>;
     fn trades_for_pair(&self, pair: Pair) -> CoreResult<Vec<Trade>>;
-    fn chart_data(&self, pair: &str, interval: &str) -> CoreResult<Vec<Candlestick>>;
+    /// the candle intervals the exchange serves natively.
+    fn intervals(&self) -> Vec<Interval>;
+    /// candles in one of the exchange's own `intervals`, oldest first.
+    fn chart_data(&self, pair: &str, interval: Interval) -> CoreResult<Vec<Candlestick>>;
     fn market_depth(&self, pair: &str) -> CoreResult<Depth>;
-    fn symbol_and_base_to_pair_format(&self, symbol: &str, base: &str) -> String;
     fn stop_loss(
         &self,
         symbol: &str,
-        amount: f64,
-        stop_price: f64,
-        limit_price: f64,
+        amount: Decimal,
+        stop_price: Decimal,
+        limit_price: Decimal,
     ) -> CoreResult<()>;
 
     // default implementations
 
+    fn exchange(&self) -> Exchange {
+        self.display().parse().unwrap_or(Exchange::Unknown)
+    }
+
+    /// look up a pair by its native symbol, eg. `BTCUSDT` on binance.
+    fn pair(&self, pair: &str) -> CoreResult<Pair> {
+        self.instruments()?
+            .pair(pair)
+            .ok_or(Box::new(TrailerError::PairNotFound(pair.to_string())))
+    }
+
+    /// Candles in any interval, oldest first. Intervals the exchange doesn't offer are resampled
+    /// from the largest native interval they are a multiple of.
+    fn candles(&self, pair: &str, interval: Interval) -> CoreResult<Vec<Candlestick>> {
+        let intervals = self.intervals();
+        if intervals.contains(&interval) {
+            return self.chart_data(pair, interval);
+        }
+
+        let source = resample_source(interval, &intervals).ok_or_else(|| {
+            Box::new(TrailerError::MissingArgumentError(format!(
+                "unsupported interval: {}",
+                interval
+            )))
+        })?;
+
+        Ok(resample(&self.chart_data(pair, source)?, interval))
+    }
+
+    /// One order by id, open or not, for exchanges that can look it up without listing
+    /// `past_orders`.
+    fn order(&self, _symbol: &str, _order_id: &str) -> CoreResult<Order> {
+        Err(Box::new(TrailerError::Unsupported))
+    }
+
+    fn all_pairs(&self) -> CoreResult<Vec<Pair>> {
+        Ok(self.instruments()?.pairs())
+    }
+
+    /// the exchange's own spelling of a pair, used for api calls.
+    fn native_symbol(&self, pair: &Pair) -> CoreResult<String> {
+        self.instruments()?
+            .pair_to_native_symbol(pair)
+            .map(|symbol| symbol.to_string())
+            .ok_or(Box::new(TrailerError::PairNotFound(pair.to_string())))
+    }
+
     fn btc_usd_pair(&self) -> Pair {
         // find_pair_by_symbol_and_base(&self.btc_symbol(), &self.usd_symbol(), pairs)
         Pair {
@@ -62,12 +122,56 @@ pub trait ExchangeAPI {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// An authenticated api for every exchange in the config, in config order.
+pub fn configured_exchanges(config: &Config) -> CoreResult<Vec<Box<dyn ExchangeAPI>>> {
+    config
+        .exchange
+        .iter()
+        .map(|(name, api_config)| exchange_api(name, api_config))
+        .collect()
+}
+
+/// The exchange's api, behind a `RiskGuard` when the config sets risk limits for it.
+pub fn exchange_api(name: &str, config: &APIConfig) -> CoreResult<Box<dyn ExchangeAPI>> {
+    let api: Box<dyn ExchangeAPI> = match name.parse::<Exchange>() {
+        Ok(Exchange::Binance) => Box::new(binance_api::BinanceAPI::authenticated(
+            &config.api_key,
+            &config.secret_key,
+        )),
+        Ok(Exchange::Huobi) => {
+            Box::new(huobi_api::HuobiAPI::new().authenticate(&config.api_key, &config.secret_key))
+        }
+        Ok(Exchange::Okex) => Box::new(okex_api::OkexAPI::authenticated(
+            &config.api_key,
+            &config.secret_key,
+            config.passphrase.as_ref().map_or("", |p| p.as_str()),
+        )),
+        _ => {
+            return Err(Box::new(TrailerError::ConfigError(format!(
+                "unknown exchange: {}",
+                name
+            ))))
+        }
+    };
+
+    match &config.risk {
+        Some(limits) => {
+            let state = RiskGuard::default_state_file(api.exchange())?;
+            let guard = RiskGuard::wrap(api, limits.clone()).state_file(state)?;
+            guard.check_supported()?;
+            Ok(Box::new(guard))
+        }
+        None => Ok(api),
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Exchange {
     Unknown,
     Binance,
     Huobi,
     Okex,
+    Paper,
 }
 
 impl std::str::FromStr for Exchange {
@@ -79,7 +183,8 @@ impl std::str::FromStr for Exchange {
             "-" => Ok(Exchange::Unknown),
             "huobi" => Ok(Exchange::Huobi),
             "binance" => Ok(Exchange::Binance),
-            "okex" => Ok(Exchange::Binance),
+            "okex" => Ok(Exchange::Okex),
+            "paper" => Ok(Exchange::Paper),
             _ => Err(()),
         }
     }
@@ -92,6 +197,7 @@ impl ToString for Exchange {
             &Exchange::Binance => "binance".into(),
             &Exchange::Huobi => "huobi".into(),
             &Exchange::Okex => "okex".into(),
+            &Exchange::Paper => "paper".into(),
             _ => "-".into(),
         }
     }
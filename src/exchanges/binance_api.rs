@@ -3,17 +3,21 @@
 
 use crate::{error::*, exchanges::*, models::*, utils::*};
 use log::info;
+use rust_decimal::Decimal;
+use std::sync::Arc;
 
-use binance::{account::*, api::*, market::*};
+use binance::{account::*, api::*, general::*, market::*};
 
 #[derive(Clone)]
 pub struct BinanceAPI {
     account: Account,
     market: Market,
+    general: General,
+    instruments: InstrumentCache,
 }
 
-pub static BASE_PAIRS: [&str; 10] = [
-    "USDT", "BTC", "ETH", "USDC", "TUSD", "BNB", "USDS", "BNB", "PAX", "XRP",
+pub static BASE_PAIRS: [&str; 9] = [
+    "USDT", "BTC", "ETH", "USDC", "TUSD", "BNB", "USDS", "PAX", "XRP",
 ];
 pub static BTC_SYMBOL: &str = "BTC";
 pub static USD_SYMBOL: &str = "USDT";
@@ -23,6 +27,8 @@ impl ExchangeAPI for BinanceAPI {
         Self {
             account: Binance::new(None, None),
             market: Market::new(None, None),
+            general: Binance::new(None, None),
+            instruments: InstrumentCache::default(),
         }
     }
 
@@ -30,6 +36,8 @@ impl ExchangeAPI for BinanceAPI {
         Self {
             account: Binance::new(Some(api_key.to_string()), Some(secret_key.to_string())),
             market: Market::new(None, None),
+            general: self.general.clone(),
+            instruments: self.instruments.clone(),
         }
     }
 
@@ -84,12 +92,11 @@ impl ExchangeAPI for BinanceAPI {
             .into_iter()
             .map(|balance| Asset {
                 symbol: balance.asset,
-                amount: balance.free.parse::<f64>().unwrap()
-                    + balance.locked.parse::<f64>().unwrap(),
-                locked: balance.locked.parse::<f64>().unwrap(),
+                amount: balance.free + balance.locked,
+                locked: balance.locked,
                 exchange: Exchange::Binance,
             })
-            .filter(|b| b.amount > 0.0)
+            .filter(|b| b.amount > Decimal::ZERO)
             .collect();
 
         info!(
@@ -105,146 +112,127 @@ impl ExchangeAPI for BinanceAPI {
         Ok(assets)
     }
 
-    fn pair(&self, pair: &str) -> CoreResult<Pair> {
-        let price = self.market.get_price(pair)?;
-        let (symbol, base) =
-            split_symbol_and_base(pair).ok_or(TrailerError::PairNotFound(pair.to_string()))?;
+    fn instruments(&self) -> CoreResult<Arc<InstrumentMap>> {
+        self.instruments.get_or_load(|| {
+            let info = self.general.exchange_info()?;
+            let mut map = InstrumentMap::new(Exchange::Binance);
+
+            for symbol in info.symbols {
+                map.insert(&symbol.symbol, &symbol.base_asset, &symbol.quote_asset);
+
+                let mut rules = MarketRules::default();
+                for filter in &symbol.filters {
+                    match filter {
+                        binance::model::Filters::LotSize {
+                            min_qty, step_size, ..
+                        } => {
+                            rules.min_qty = *min_qty;
+                            rules.step_size = *step_size;
+                        }
+                        binance::model::Filters::PriceFilter { tick_size, .. } => {
+                            rules.tick_size = *tick_size
+                        }
+                        binance::model::Filters::MinNotional { min_notional } => {
+                            rules.min_notional = *min_notional
+                        }
+                        binance::model::Filters::Other => {}
+                    }
+                }
+                map.set_rules(&symbol.symbol, rules);
+            }
 
-        Ok(Pair {
-            symbol: symbol.to_string(),
-            base: base.to_string(),
+            Ok(map)
         })
     }
 
-    fn all_pairs(&self) -> CoreResult<Vec<Pair>> {
-        let binance::model::Prices::AllPrices(prices) = self.market.get_all_prices()?;
-
-        Ok(prices
-            .into_iter()
-            .map(|pair| string_to_pair(&pair.symbol))
-            .filter(|r| r.is_some())
-            .map(|r| r.unwrap())
-            .collect())
-    }
-
     fn all_prices(&self) -> CoreResult<Vec<Price>> {
+        let instruments = self.instruments()?;
         let binance::model::Prices::AllPrices(prices) = self.market.get_all_prices()?;
 
         Ok(prices
             .into_iter()
-            .map(|price| {
-                string_to_pair(&price.symbol).map(|pair| Price {
+            .filter_map(|price| {
+                instruments.pair(&price.symbol).map(|pair| Price {
                     pair: pair,
                     price: price.price,
                 })
             })
-            .filter(|r| r.is_some())
-            .map(|r| r.unwrap())
             .collect())
     }
 
-    fn symbol_and_base_to_pair_format(&self, symbol: &str, base: &str) -> String {
-        format!("{}{}", symbol, base)
-    }
-
     fn book_tickers(&self) -> CoreResult<Vec<BookTicker>> {
+        let instruments = self.instruments()?;
         let binance::model::BookTickers::AllBookTickers(tickers) =
             self.market.get_all_book_tickers()?;
 
         Ok(tickers
             .into_iter()
-            .map(|ticker| {
-                let (symbol, base) = split_symbol_and_base(&ticker.symbol)
-                    .expect(&format!("could not split symbol: {:?}", &ticker.symbol));
-
-                BookTicker {
-                    pair: Pair {
-                        symbol: symbol,
-                        base: base,
-                    },
+            .filter_map(|ticker| {
+                instruments.pair(&ticker.symbol).map(|pair| BookTicker {
+                    pair,
                     bid_price: ticker.bid_price,
                     bid_qty: ticker.bid_qty,
                     ask_price: ticker.ask_price,
                     ask_qty: ticker.ask_qty,
-                }
+                })
             })
             .collect())
     }
 
-    fn limit_buy(&self, symbol: &str, amount: f64, price: f64) -> CoreResult<()> {
+    fn limit_buy(&self, symbol: &str, amount: Decimal, price: Decimal) -> CoreResult<()> {
         let result = self.account.limit_buy(symbol, amount, price)?;
         println!("{:?}", result);
         Ok(())
     }
 
-    fn limit_sell(&self, symbol: &str, amount: f64, price: f64) -> CoreResult<()> {
+    fn limit_sell(&self, symbol: &str, amount: Decimal, price: Decimal) -> CoreResult<()> {
         let result = self.account.limit_sell(symbol, amount, price)?;
         println!("{:?}", result);
         Ok(())
     }
 
+    fn cancel_order(&self, symbol: &str, order_id: &str) -> CoreResult<()> {
+        let order_id = order_id.parse::<u64>().map_err(|_| {
+            TrailerError::MissingArgumentError(format!("invalid order id: {}", order_id))
+        })?;
+        let result = self.account.cancel_order(symbol, order_id)?;
+        info!("cancelled: {:?}", result);
+        Ok(())
+    }
+
     fn stop_loss(
         &self,
         symbol: &str,
-        amount: f64,
-        stop_price: f64,
-        limit_price: f64,
+        amount: Decimal,
+        stop_price: Decimal,
+        limit_price: Decimal,
     ) -> CoreResult<()> {
         Err(Box::new(TrailerError::Unsupported))
     }
 
     fn open_orders(&self) -> CoreResult<Vec<Order>> {
-        fn parse_order_type(order_type: &str) -> OrderType {
-            match order_type {
-                "LIMIT" => OrderType::Limit,
-                "MARKET" => OrderType::Market,
-                "STOP_LOSS" => OrderType::StopLoss,
-                "STOP_LOSS_LIMIT" => OrderType::StopLossLimit,
-                "TAKE_PROFIT" => OrderType::TakeProfit,
-                "TAKE_PROFIT_LIMIT" => OrderType::TakeProfitLimit,
-                "LIMIT_MAKER" => OrderType::LimitMaker,
-                _ => OrderType::Limit, // TODO: throw Err here...
-            }
-        }
-
-        fn parse_trade_type(trade_type: &str) -> TradeType {
-            match trade_type {
-                "BUY" => TradeType::Buy,
-                "SELL" => TradeType::Sell,
-                _ => TradeType::Sell, // TODO: throw Err here...
-            }
-        }
-
+        let instruments = self.instruments()?;
         let mut results = Vec::new();
 
         for order in self.account.get_open_orders_all()? {
-            // let pair = self
-            //     .string_to_pair(order.symbol.to_string(), 0.0)
-            //     .ok_or(TrailerError::PairNotFound(order.symbol.clone()))?;
-            let pair = string_to_pair(&order.symbol)
-                .ok_or(TrailerError::PairNotFound(order.symbol.to_string()))?;
-
-            let (symbol, base) = split_symbol_and_base(&order.symbol)
-                .ok_or(TrailerError::PairNotFound(order.symbol.clone()))?;
-
-            // let pair = find_pair_by_symbol_and_base(&symbol, &base, PRICES.clone())
-            // .ok_or(TrailerError::PairNotFound(order.symbol.clone()))?;
-
-            results.push(Order {
-                id: order.order_id.to_string(),
-                pair,
-                order_type: parse_order_type(&order.type_name),
-                trade_type: parse_trade_type(&order.side),
-                purchase_price: order.price,
-                qty: order.orig_qty.parse::<f64>().unwrap(),
-                executed_qty: order.executed_qty.parse::<f64>().unwrap(),
-                time: local_datetime_from_unix(order.time),
-            });
+            results.push(to_order(&instruments, order)?);
         }
 
         Ok(results)
     }
 
+    fn order(&self, symbol: &str, order_id: &str) -> CoreResult<Order> {
+        let order_id = order_id.parse::<u64>().map_err(|e| {
+            Box::new(TrailerError::APIError(format!(
+                "order id {}: {}",
+                order_id, e
+            )))
+        })?;
+        let order = self.account.order_status(symbol, order_id)?;
+        let instruments = self.instruments()?;
+        to_order(&instruments, order)
+    }
+
     fn past_orders(&self) -> CoreResult<Vec<Order>> {
         Err(Box::new(TrailerError::Unsupported))
     }
@@ -259,7 +247,7 @@ impl ExchangeAPI for BinanceAPI {
     fn trades_for_pair(&self, pair: Pair) -> CoreResult<Vec<Trade>> {
         info!("BINANCE: trades_for_pair({})", pair.clone());
 
-        let result = self.account.trade_history(pair_to_string(pair.clone()))?;
+        let result = self.account.trade_history(self.native_symbol(&pair)?)?;
         info!("result: {:?}", result);
 
         let mut trades: Vec<Trade> = result
@@ -271,7 +259,7 @@ impl ExchangeAPI for BinanceAPI {
                 trade_type: TradeType::is_buy(trade.is_buyer),
                 qty: trade.qty,
                 sale_price: trade.price,
-                fee: trade.commission.parse::<f64>().unwrap_or(0.0),
+                fee: trade.commission,
                 fee_symbol: Some(trade.commission_asset),
             })
             .collect();
@@ -282,19 +270,25 @@ impl ExchangeAPI for BinanceAPI {
         Ok(trades)
     }
 
-    fn chart_data(&self, pair: &str, interval: &str) -> CoreResult<Vec<Candlestick>> {
+    fn intervals(&self) -> Vec<Interval> {
+        Interval::all()
+    }
+
+    fn chart_data(&self, pair: &str, interval: Interval) -> CoreResult<Vec<Candlestick>> {
         Ok(self
             .market
-            .get_klines(pair, interval)?
+            .get_klines(pair, interval.as_str())?
             .iter()
             .map(|cs| Candlestick {
-                open_time: cs.open_time as i32,
+                open_time: cs.open_time,
+                close_time: cs.close_time,
                 open_price: cs.open_price,
                 close_price: cs.close_price,
                 high_price: cs.high_price,
                 low_price: cs.low_price,
                 volume: cs.volume,
-                number_of_trades: cs.trades as u64,
+                quote_volume: Some(cs.quote_asset_volume),
+                number_of_trades: cs.trades,
             })
             .collect())
     }
@@ -312,23 +306,46 @@ impl BinanceAPI {
         BinanceAPI {
             account: Binance::new(Some(api_key.to_string()), Some(secret_key.to_string())),
             market: Market::new(None, None),
+            general: Binance::new(None, None),
+            instruments: InstrumentCache::default(),
         }
     }
 }
 
-fn split_symbol_and_base(pair: &str) -> Option<(String, String)> {
-    for base in BASE_PAIRS.iter() {
-        if pair.ends_with(base) {
-            return Some((pair.trim_end_matches(base).to_string(), base.to_string()));
-        };
+fn to_order(instruments: &InstrumentMap, order: binance::model::Order) -> CoreResult<Order> {
+    fn parse_order_type(order_type: &str) -> OrderType {
+        match order_type {
+            "LIMIT" => OrderType::Limit,
+            "MARKET" => OrderType::Market,
+            "STOP_LOSS" => OrderType::StopLoss,
+            "STOP_LOSS_LIMIT" => OrderType::StopLossLimit,
+            "TAKE_PROFIT" => OrderType::TakeProfit,
+            "TAKE_PROFIT_LIMIT" => OrderType::TakeProfitLimit,
+            "LIMIT_MAKER" => OrderType::LimitMaker,
+            _ => OrderType::Limit, // TODO: throw Err here...
+        }
     }
-    None
-}
 
-fn string_to_pair(pair: &str) -> Option<Pair> {
-    split_symbol_and_base(&pair).map(|(symbol, base)| Pair { base, symbol })
-}
+    fn parse_trade_type(trade_type: &str) -> TradeType {
+        match trade_type {
+            "BUY" => TradeType::Buy,
+            "SELL" => TradeType::Sell,
+            _ => TradeType::Sell, // TODO: throw Err here...
+        }
+    }
 
-fn pair_to_string(pair: Pair) -> String {
-    format!("{}{}", pair.symbol, pair.base)
+    let pair = instruments
+        .pair(&order.symbol)
+        .ok_or(TrailerError::PairNotFound(order.symbol.to_string()))?;
+
+    Ok(Order {
+        id: order.order_id.to_string(),
+        pair,
+        order_type: parse_order_type(&order.type_name),
+        trade_type: parse_trade_type(&order.side),
+        purchase_price: order.price,
+        qty: order.orig_qty,
+        executed_qty: order.executed_qty,
+        time: local_datetime_from_unix(order.time),
+    })
 }
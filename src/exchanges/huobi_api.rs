@@ -4,17 +4,24 @@
 use crate::utils::*;
 use crate::{error::*, exchanges::*, models::*};
 use log::info;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct HuobiAPI {
     client: huobi::Client,
     default_account: u32,
+    instruments: InstrumentCache,
 }
 
 pub static BASE_PAIRS: [&str; 2] = ["USDT", "BTC"];
 pub static BTC_SYMBOL: &str = "BTC";
 pub static USD_SYMBOL: &str = "USDT";
 
+/// huobi candles follow the calendar in UTC+8, in millis.
+static UTC_OFFSET: u64 = 8 * 60 * 60 * 1000;
+
 impl HuobiAPI {
     pub fn cached_default_account(&self) -> u32 {
         if self.default_account == 0 {
@@ -35,6 +42,7 @@ impl ExchangeAPI for HuobiAPI {
         Self {
             client: huobi::Client::new("", ""),
             default_account: 0,
+            instruments: InstrumentCache::default(),
         }
     }
 
@@ -42,6 +50,7 @@ impl ExchangeAPI for HuobiAPI {
         Self {
             client: huobi::Client::new(key, secret),
             default_account: 0,
+            instruments: self.instruments.clone(),
         }
     }
 
@@ -64,76 +73,107 @@ impl ExchangeAPI for HuobiAPI {
             .collect()
     }
 
+    /// huobi lists a `trade` and a `frozen` entry per currency, frozen funds are locked in orders.
     fn balances(&self) -> CoreResult<Vec<Asset>> {
-        Ok(self
-            .client
-            .balance(self.cached_default_account())?
-            .list
-            .into_iter()
-            .map(|a| Asset {
-                symbol: a.currency.to_uppercase(),
-                amount: a.balance,
-                locked: 0.0,
+        let mut assets: BTreeMap<String, Asset> = BTreeMap::new();
+
+        for a in self.client.balance(self.cached_default_account())?.list {
+            let symbol = a.currency.to_uppercase();
+            let asset = assets.entry(symbol.clone()).or_insert_with(|| Asset {
+                symbol,
+                amount: Decimal::ZERO,
+                locked: Decimal::ZERO,
                 exchange: Exchange::Huobi,
-            })
-            .collect())
-    }
+            });
 
-    fn pair(&self, pair: &str) -> CoreResult<Pair> {
-        unimplemented!()
+            asset.amount += a.balance;
+            if a.trade_type == "frozen" {
+                asset.locked += a.balance;
+            }
+        }
+        Ok(assets.into_iter().map(|(_, asset)| asset).collect())
     }
 
-    fn all_pairs(&self) -> CoreResult<Vec<Pair>> {
-        Ok(self
-            .client
-            .common_symbols()?
-            .into_iter()
-            .map(|p: huobi::Pair| Pair {
-                base: p.base_currency.to_uppercase(),
-                symbol: p.symbol.to_uppercase(),
-            })
-            .collect())
+    fn instruments(&self) -> CoreResult<Arc<InstrumentMap>> {
+        self.instruments.get_or_load(|| {
+            let mut map = InstrumentMap::new(Exchange::Huobi);
+
+            for p in self.client.common_symbols()? {
+                map.insert(&p.symbol, &p.base_currency, &p.quote_currency);
+                map.set_rules(
+                    &p.symbol,
+                    MarketRules {
+                        step_size: Decimal::new(1, p.amount_precision),
+                        tick_size: Decimal::new(1, p.price_precision),
+                        ..MarketRules::default()
+                    },
+                );
+            }
+
+            Ok(map)
+        })
     }
 
     fn all_prices(&self) -> CoreResult<Vec<Price>> {
+        let instruments = self.instruments()?;
+
         Ok(self
             .client
             .tickers()?
             .into_iter()
-            .map(|t: huobi::Ticker| {
-                string_to_pair(&t.symbol.to_uppercase()).map(|pair| Price {
+            .filter_map(|t: huobi::Ticker| {
+                instruments.pair(&t.symbol).map(|pair| Price {
                     pair,
                     price: t.close,
                 })
             })
-            .filter_map(|e| e)
             .collect())
     }
 
-    fn limit_buy(&self, symbol: &str, amount: f64, price: f64) -> CoreResult<()> {
-        unimplemented!()
+    fn limit_buy(&self, symbol: &str, amount: Decimal, price: Decimal) -> CoreResult<()> {
+        Err(Box::new(TrailerError::Unsupported))
     }
 
-    fn limit_sell(&self, symbol: &str, amount: f64, price: f64) -> CoreResult<()> {
-        unimplemented!()
+    fn limit_sell(&self, symbol: &str, amount: Decimal, price: Decimal) -> CoreResult<()> {
+        Err(Box::new(TrailerError::Unsupported))
+    }
+
+    fn cancel_order(&self, symbol: &str, order_id: &str) -> CoreResult<()> {
+        Err(Box::new(TrailerError::Unsupported))
     }
 
     fn open_orders(&self) -> CoreResult<Vec<Order>> {
-        unimplemented!()
+        Err(Box::new(TrailerError::Unsupported))
     }
 
     fn past_orders(&self) -> CoreResult<Vec<Order>> {
-        unimplemented!()
+        Err(Box::new(TrailerError::Unsupported))
     }
 
     fn book_tickers(&self) -> CoreResult<Vec<BookTicker>> {
-        unimplemented!()
+        let instruments = self.instruments()?;
+
+        Ok(self
+            .client
+            .tickers()?
+            .into_iter()
+            .filter_map(|t: huobi::Ticker| {
+                let pair = instruments.pair(&t.symbol)?;
+                Some(BookTicker {
+                    pair,
+                    bid_price: t.bid?,
+                    bid_qty: t.bid_size.unwrap_or(Decimal::ZERO),
+                    ask_price: t.ask?,
+                    ask_qty: t.ask_size.unwrap_or(Decimal::ZERO),
+                })
+            })
+            .collect())
     }
 
     fn trades_for_pair(&self, pair: Pair) -> CoreResult<Vec<Trade>> {
         Ok(self
             .client
-            .orders(&pair_to_string(pair.clone()), "filled")?
+            .orders(&self.native_symbol(&pair)?, "filled")?
             .into_iter()
             .map(|t| {
                 //                let pair = string_to_pair(&t.symbol).expect("pair to be found");
@@ -151,49 +191,71 @@ impl ExchangeAPI for HuobiAPI {
             .collect())
     }
 
-    fn chart_data(&self, pair: &str, interval: &str) -> CoreResult<Vec<Candlestick>> {
-        unimplemented!()
+    fn intervals(&self) -> Vec<Interval> {
+        Interval::all()
+            .into_iter()
+            .filter(|interval| interval.huobi_period().is_some())
+            .collect()
     }
 
-    fn market_depth(&self, pair: &str) -> CoreResult<Depth> {
-        unimplemented!()
+    fn chart_data(&self, pair: &str, interval: Interval) -> CoreResult<Vec<Candlestick>> {
+        info!("HUOBI: chart_data({}, {})", pair, interval);
+
+        let period =
+            interval
+                .huobi_period()
+                .ok_or(Box::new(TrailerError::MissingArgumentError(format!(
+                    "unsupported interval: {}",
+                    interval
+                ))))?;
+
+        let mut candles: Vec<Candlestick> = self
+            .client
+            .klines(&pair.to_lowercase(), period, 500)?
+            .into_iter()
+            .map(|kline| {
+                let open_time = u64::from(kline.id) * 1000;
+                // huobi days start at midnight in UTC+8, only months need the calendar, which
+                // is shifted into UTC and back.
+                let close_time = match interval.millis() {
+                    Some(width) => open_time + width - 1,
+                    None => interval_bounds(open_time + UTC_OFFSET, interval).1 - UTC_OFFSET,
+                };
+                Candlestick {
+                    open_time,
+                    close_time,
+                    open_price: kline.open,
+                    close_price: kline.close,
+                    high_price: kline.high,
+                    low_price: kline.low,
+                    volume: kline.amount,
+                    quote_volume: Some(kline.vol),
+                    number_of_trades: u64::from(kline.count),
+                }
+            })
+            .collect();
+
+        // huobi returns the newest candle first
+        candles.reverse();
+        Ok(candles)
     }
 
-    fn symbol_and_base_to_pair_format(&self, symbol: &str, base: &str) -> String {
-        unimplemented!()
+    fn market_depth(&self, pair: &str) -> CoreResult<Depth> {
+        Err(Box::new(TrailerError::Unsupported))
     }
 
     fn stop_loss(
         &self,
         symbol: &str,
-        amount: f64,
-        stop_price: f64,
-        limit_price: f64,
+        amount: Decimal,
+        stop_price: Decimal,
+        limit_price: Decimal,
     ) -> CoreResult<()> {
-        unimplemented!()
+        // huobi has no native stop orders, use a `TriggerEngine` instead.
+        Err(Box::new(TrailerError::Unsupported))
     }
 }
 
-fn split_symbol_and_base(pair: &str) -> Option<(String, String)> {
-    for base in BASE_PAIRS.iter() {
-        if pair.ends_with(base) {
-            return Some((
-                pair.trim_end_matches(base).to_string().to_uppercase(),
-                base.to_string().to_uppercase(),
-            ));
-        };
-    }
-    None
-}
-
-fn string_to_pair(pair: &str) -> Option<Pair> {
-    split_symbol_and_base(&pair).map(|(symbol, base)| Pair { base, symbol })
-}
-
-fn pair_to_string(pair: Pair) -> String {
-    format!("{}{}", pair.symbol.to_lowercase(), pair.base.to_lowercase())
-}
-
 fn string_to_trade_type(order_type: &str) -> OrderType {
     match order_type {
         "buy-market" | "sell-limit" => OrderType::Limit,
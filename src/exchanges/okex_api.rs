@@ -1 +1,270 @@
+#![allow(dead_code)]
+#![allow(unused_variables)]
 
+use crate::{error::*, exchanges::*, models::*};
+use chrono::DateTime;
+use log::info;
+use rust_decimal::Decimal;
+use std::sync::Arc;
+
+use okex::client::Client;
+use okex::models::{OkexOrderRequest, OkexOrderSide};
+
+#[derive(Clone)]
+pub struct OkexAPI {
+    client: Client,
+    instruments: InstrumentCache,
+}
+
+pub static BASE_PAIRS: [&str; 5] = ["USDT", "BTC", "ETH", "OKB", "USDK"];
+pub static BTC_SYMBOL: &str = "BTC";
+pub static USD_SYMBOL: &str = "USDT";
+
+impl OkexAPI {
+    pub fn authenticated(api_key: &str, secret_key: &str, passphrase: &str) -> Self {
+        OkexAPI {
+            client: Client::new(api_key, secret_key, passphrase),
+            instruments: InstrumentCache::default(),
+        }
+    }
+}
+
+impl ExchangeAPI for OkexAPI {
+    fn new() -> Self {
+        Self {
+            client: Client::new("", "", ""),
+            instruments: InstrumentCache::default(),
+        }
+    }
+
+    fn authenticate(&self, api_key: &str, secret_key: &str) -> Self {
+        Self {
+            client: Client::new(api_key, secret_key, ""),
+            instruments: self.instruments.clone(),
+        }
+    }
+
+    fn display(&self) -> String {
+        "okex".to_string()
+    }
+
+    fn btc_symbol(&self) -> String {
+        BTC_SYMBOL.into()
+    }
+
+    fn usd_symbol(&self) -> String {
+        USD_SYMBOL.into()
+    }
+
+    fn base_pairs(&self) -> Vec<String> {
+        BASE_PAIRS.iter().map(|pair| pair.to_string()).collect()
+    }
+
+    fn balances(&self) -> CoreResult<Vec<Asset>> {
+        let assets: Vec<Asset> = self
+            .client
+            .get_balance()?
+            .into_iter()
+            .map(|balance| Asset {
+                symbol: balance.currency.to_uppercase(),
+                amount: balance.balance,
+                locked: balance.hold,
+                exchange: Exchange::Okex,
+            })
+            .filter(|b| b.amount > Decimal::ZERO)
+            .collect();
+
+        info!(
+            "response: found assets: {}",
+            assets
+                .iter()
+                .map(|p| format!("[{} - {}]", p.symbol, p.amount))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+
+        Ok(assets)
+    }
+
+    fn instruments(&self) -> CoreResult<Arc<InstrumentMap>> {
+        self.instruments.get_or_load(|| {
+            let mut map = InstrumentMap::new(Exchange::Okex);
+
+            for instrument in self.client.get_instruments()? {
+                map.insert(
+                    &instrument.instrument_id,
+                    &instrument.base_currency,
+                    &instrument.quote_currency,
+                );
+                map.set_rules(
+                    &instrument.instrument_id,
+                    MarketRules {
+                        min_qty: instrument.min_size,
+                        step_size: instrument.size_increment,
+                        tick_size: instrument.tick_size,
+                        min_notional: Decimal::ZERO,
+                    },
+                );
+            }
+
+            Ok(map)
+        })
+    }
+
+    fn all_prices(&self) -> CoreResult<Vec<Price>> {
+        let instruments = self.instruments()?;
+
+        Ok(self
+            .client
+            .get_tickers()?
+            .into_iter()
+            .filter_map(|ticker| {
+                instruments.pair(&ticker.instrument_id).map(|pair| Price {
+                    pair,
+                    price: ticker.last,
+                })
+            })
+            .collect())
+    }
+
+    fn limit_buy(&self, symbol: &str, amount: Decimal, price: Decimal) -> CoreResult<()> {
+        let request = OkexOrderRequest::limit(symbol, OkexOrderSide::Buy, price, amount);
+        let result = self.client.place(&request)?;
+        info!("response: {:?}", result);
+
+        if !result.result {
+            return Err(Box::new(TrailerError::APIError(format!(
+                "buy {} {} @ {} failed: {}",
+                amount,
+                symbol,
+                price,
+                result.error_message.unwrap_or_default()
+            ))));
+        }
+        Ok(())
+    }
+
+    fn limit_sell(&self, symbol: &str, amount: Decimal, price: Decimal) -> CoreResult<()> {
+        let request = OkexOrderRequest::limit(symbol, OkexOrderSide::Sell, price, amount);
+        let result = self.client.place(&request)?;
+        info!("response: {:?}", result);
+
+        if !result.result {
+            return Err(Box::new(TrailerError::APIError(format!(
+                "sell {} {} @ {} failed: {}",
+                amount,
+                symbol,
+                price,
+                result.error_message.unwrap_or_default()
+            ))));
+        }
+        Ok(())
+    }
+
+    fn cancel_order(&self, symbol: &str, order_id: &str) -> CoreResult<()> {
+        let result = self.client.cancel_order(order_id, symbol)?;
+        info!("response: {:?}", result);
+
+        if !result.result {
+            return Err(Box::new(TrailerError::APIError(format!(
+                "cancel {} failed: {}",
+                order_id,
+                result.error_message.unwrap_or_default()
+            ))));
+        }
+        Ok(())
+    }
+
+    fn open_orders(&self) -> CoreResult<Vec<Order>> {
+        Err(Box::new(TrailerError::Unsupported))
+    }
+
+    fn past_orders(&self) -> CoreResult<Vec<Order>> {
+        Err(Box::new(TrailerError::Unsupported))
+    }
+
+    fn book_tickers(&self) -> CoreResult<Vec<BookTicker>> {
+        let instruments = self.instruments()?;
+
+        Ok(self
+            .client
+            .get_tickers()?
+            .into_iter()
+            .filter_map(|ticker| {
+                instruments
+                    .pair(&ticker.instrument_id)
+                    .map(|pair| BookTicker {
+                        pair,
+                        bid_price: ticker.best_bid,
+                        bid_qty: ticker.best_bid_size,
+                        ask_price: ticker.best_ask,
+                        ask_qty: ticker.best_ask_size,
+                    })
+            })
+            .collect())
+    }
+
+    fn trades_for_pair(&self, pair: Pair) -> CoreResult<Vec<Trade>> {
+        Err(Box::new(TrailerError::Unsupported))
+    }
+
+    fn intervals(&self) -> Vec<Interval> {
+        Interval::all()
+            .into_iter()
+            .filter(|interval| interval.okex_granularity().is_some())
+            .collect()
+    }
+
+    fn chart_data(&self, pair: &str, interval: Interval) -> CoreResult<Vec<Candlestick>> {
+        info!("OKEX: chart_data({}, {})", pair, interval);
+
+        let granularity =
+            interval
+                .okex_granularity()
+                .ok_or(Box::new(TrailerError::MissingArgumentError(format!(
+                    "unsupported interval: {}",
+                    interval
+                ))))?;
+        let width = u64::from(granularity) * 1000;
+
+        let mut candles: Vec<Candlestick> = self
+            .client
+            .get_candles(pair, granularity, None, None)?
+            .into_iter()
+            .map(|candle| {
+                let open_time = DateTime::parse_from_rfc3339(&candle.time)
+                    .map(|time| time.timestamp_millis())
+                    .unwrap_or(0) as u64;
+                Candlestick {
+                    open_time,
+                    close_time: open_time + width - 1,
+                    open_price: candle.open,
+                    close_price: candle.close,
+                    high_price: candle.high,
+                    low_price: candle.low,
+                    volume: candle.volume,
+                    quote_volume: None,
+                    number_of_trades: 0,
+                }
+            })
+            .collect();
+
+        // okex returns the newest candle first
+        candles.reverse();
+        Ok(candles)
+    }
+
+    fn market_depth(&self, pair: &str) -> CoreResult<Depth> {
+        Err(Box::new(TrailerError::Unsupported))
+    }
+
+    fn stop_loss(
+        &self,
+        symbol: &str,
+        amount: Decimal,
+        stop_price: Decimal,
+        limit_price: Decimal,
+    ) -> CoreResult<()> {
+        Err(Box::new(TrailerError::Unsupported))
+    }
+}
@@ -0,0 +1,755 @@
+#![allow(unused_variables)]
+
+use crate::utils::*;
+use crate::{error::*, exchanges::*, models::*};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How the paper exchange fills orders. Fees are a fraction of the notional, eg. `0.001` for
+/// 0.1%, and `slippage` the fraction orders crossing the spread lose to the market.
+#[derive(Debug, Clone)]
+pub struct FillModel {
+    pub maker_fee: Decimal,
+    pub taker_fee: Decimal,
+    pub slippage: Decimal,
+    /// delay between placing an order and it reaching the book.
+    pub latency: Duration,
+}
+
+impl Default for FillModel {
+    fn default() -> Self {
+        FillModel {
+            maker_fee: Decimal::new(1, 3),
+            taker_fee: Decimal::new(1, 3),
+            slippage: Decimal::ZERO,
+            latency: Duration::from_millis(0),
+        }
+    }
+}
+
+/// The prices a market traded at over a span of time, from `start` to `time` (unix millis). Buy
+/// orders match against the ask and sell orders against the bid.
+#[derive(Debug, Clone)]
+pub struct Quote {
+    /// the same as `time` for a single price.
+    pub start: u64,
+    pub time: u64,
+    pub ask_open: Decimal,
+    pub ask_low: Decimal,
+    pub bid_open: Decimal,
+    pub bid_high: Decimal,
+    pub bid_low: Decimal,
+    pub last: Decimal,
+}
+
+impl Quote {
+    pub fn from_candle(candle: &Candlestick) -> Self {
+        Quote {
+            start: candle.open_time,
+            time: candle.close_time,
+            ask_open: candle.open_price,
+            ask_low: candle.low_price,
+            bid_open: candle.open_price,
+            bid_high: candle.high_price,
+            bid_low: candle.low_price,
+            last: candle.close_price,
+        }
+    }
+
+    pub fn from_trade(trade: &Trade) -> Self {
+        Quote::from_price(
+            trade.time.timestamp_millis().max(0) as u64,
+            trade.sale_price,
+        )
+    }
+
+    pub fn from_price(time: u64, price: Decimal) -> Self {
+        Quote {
+            start: time,
+            time,
+            ask_open: price,
+            ask_low: price,
+            bid_open: price,
+            bid_high: price,
+            bid_low: price,
+            last: price,
+        }
+    }
+
+    pub fn from_book_ticker(time: u64, ticker: &BookTicker) -> Self {
+        Quote {
+            start: time,
+            time,
+            ask_open: ticker.ask_price,
+            ask_low: ticker.ask_price,
+            bid_open: ticker.bid_price,
+            bid_high: ticker.bid_price,
+            bid_low: ticker.bid_price,
+            last: (ticker.bid_price + ticker.ask_price) / Decimal::new(2, 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PaperOrder {
+    id: u64,
+    symbol: String,
+    pair: Pair,
+    trade_type: TradeType,
+    order_type: OrderType,
+    qty: Decimal,
+    price: Decimal,
+    stop_price: Option<Decimal>,
+    placed: u64,
+    active: u64,
+    /// whether the order has sat on the book, resting orders fill at their own price.
+    rested: bool,
+}
+
+impl PaperOrder {
+    fn to_order(&self, executed_qty: Decimal) -> Order {
+        Order {
+            id: self.id.to_string(),
+            pair: self.pair.clone(),
+            order_type: self.order_type.clone(),
+            trade_type: self.trade_type,
+            qty: self.qty,
+            executed_qty,
+            purchase_price: self.price,
+            time: local_datetime_from_unix(self.placed),
+        }
+    }
+
+    /// the price and whether it took liquidity, if `quote` fills the order.
+    fn fill_price(&mut self, quote: &Quote, slippage: Decimal) -> Option<(Decimal, bool)> {
+        if let Some(stop) = self.stop_price {
+            if quote.bid_low > stop {
+                return None;
+            }
+
+            // triggered: sells at the market unless it has fallen past the limit.
+            self.stop_price = None;
+            self.order_type = OrderType::Limit;
+            let market = stop.min(quote.bid_open) * (Decimal::ONE - slippage);
+            if market >= self.price {
+                return Some((market, true));
+            }
+            self.rested = true;
+        }
+
+        let filled = match self.trade_type {
+            TradeType::Buy if !self.rested && quote.ask_open <= self.price => Some((
+                (quote.ask_open * (Decimal::ONE + slippage)).min(self.price),
+                true,
+            )),
+            TradeType::Buy if quote.ask_low <= self.price => Some((self.price, false)),
+            TradeType::Sell if !self.rested && quote.bid_open >= self.price => Some((
+                (quote.bid_open * (Decimal::ONE - slippage)).max(self.price),
+                true,
+            )),
+            TradeType::Sell if quote.bid_high >= self.price => Some((self.price, false)),
+            _ => None,
+        };
+
+        self.rested = true;
+        filled
+    }
+}
+
+#[derive(Debug, Default)]
+struct PaperState {
+    time: u64,
+    next_id: u64,
+    balances: BTreeMap<String, Decimal>,
+    locked: BTreeMap<String, Decimal>,
+    orders: Vec<PaperOrder>,
+    past_orders: Vec<Order>,
+    fills: Vec<Trade>,
+    prices: BTreeMap<String, Decimal>,
+    book_tickers: BTreeMap<String, BookTicker>,
+}
+
+impl PaperState {
+    fn free(&self, symbol: &str) -> Decimal {
+        self.balances.get(symbol).cloned().unwrap_or(Decimal::ZERO)
+            - self.locked.get(symbol).cloned().unwrap_or(Decimal::ZERO)
+    }
+
+    /// releases the balance held by an order taken off the book, keeping it with the past orders.
+    fn cancel(&mut self, order: &PaperOrder) {
+        match order.trade_type {
+            TradeType::Buy => PaperState::adjust(
+                &mut self.locked,
+                &order.pair.base,
+                -(order.qty * order.price),
+            ),
+            TradeType::Sell => PaperState::adjust(&mut self.locked, &order.pair.symbol, -order.qty),
+        }
+        self.past_orders.push(order.to_order(Decimal::ZERO));
+    }
+
+    fn adjust(map: &mut BTreeMap<String, Decimal>, symbol: &str, amount: Decimal) {
+        *map.entry(symbol.to_string()).or_insert(Decimal::ZERO) += amount;
+    }
+}
+
+/// A simulated exchange. Orders placed through `ExchangeAPI` rest on a private book and are
+/// filled against the quotes fed to `update`, from replayed history or a live feed.
+#[derive(Clone)]
+pub struct PaperAPI {
+    instruments: Arc<InstrumentMap>,
+    fill_model: FillModel,
+    state: Arc<Mutex<PaperState>>,
+}
+
+impl PaperAPI {
+    /// lists a market under its native symbol, eg. `BTCUSDT`.
+    pub fn market(mut self, symbol: &str, pair: &Pair) -> Self {
+        Arc::make_mut(&mut self.instruments).insert(symbol, &pair.symbol, &pair.base);
+        self
+    }
+
+    pub fn fill_model(mut self, fill_model: FillModel) -> Self {
+        self.fill_model = fill_model;
+        self
+    }
+
+    pub fn deposit(&self, symbol: &str, amount: Decimal) {
+        let mut state = self.state.lock().expect("paper exchange state");
+        PaperState::adjust(&mut state.balances, &symbol.to_uppercase(), amount);
+    }
+
+    /// the simulated clock, unix millis.
+    pub fn time(&self) -> u64 {
+        self.state.lock().expect("paper exchange state").time
+    }
+
+    pub fn set_time(&self, time: u64) {
+        let mut state = self.state.lock().expect("paper exchange state");
+        state.time = state.time.max(time);
+    }
+
+    /// every fill so far, oldest first.
+    pub fn fills(&self) -> Vec<Trade> {
+        self.state
+            .lock()
+            .expect("paper exchange state")
+            .fills
+            .clone()
+    }
+
+    pub fn balance(&self, symbol: &str) -> Decimal {
+        self.state
+            .lock()
+            .expect("paper exchange state")
+            .balances
+            .get(&symbol.to_uppercase())
+            .cloned()
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    /// Value of every balance in `currency` at the last prices seen, `None` if a held asset has
+    /// no route to it.
+    pub fn equity(&self, currency: &str) -> Option<Decimal> {
+        let graph = ConversionGraph::from_prices(&self.all_prices().ok()?);
+        let state = self.state.lock().expect("paper exchange state");
+
+        state
+            .balances
+            .iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .map(|(symbol, amount)| {
+                if symbol.eq_ignore_ascii_case(currency) {
+                    Some(*amount)
+                } else {
+                    graph.convert(*amount, symbol, currency)
+                }
+            })
+            .sum()
+    }
+
+    pub fn update_book_ticker(&self, symbol: &str, ticker: &BookTicker) {
+        let mut state = self.state.lock().expect("paper exchange state");
+        state
+            .book_tickers
+            .insert(symbol.to_string(), ticker.clone());
+    }
+
+    /// Moves the clock to the end of `quote` and matches the open orders for `symbol` against
+    /// it, returning the fills. Orders only see quotes starting once their latency has passed,
+    /// prices from earlier in a candle were gone before the order reached the book.
+    pub fn update(&self, symbol: &str, quote: &Quote) -> Vec<Trade> {
+        let mut state = self.state.lock().expect("paper exchange state");
+        state.time = state.time.max(quote.time);
+        state.prices.insert(symbol.to_string(), quote.last);
+
+        let model = &self.fill_model;
+        let mut fills = Vec::new();
+        let mut open = Vec::new();
+
+        for mut order in std::mem::replace(&mut state.orders, Vec::new()) {
+            if order.symbol != symbol || order.active > quote.start {
+                open.push(order);
+                continue;
+            }
+
+            let (price, taker) = match order.fill_price(quote, model.slippage) {
+                Some(filled) => filled,
+                None => {
+                    open.push(order);
+                    continue;
+                }
+            };
+
+            let fee_rate = if taker {
+                model.taker_fee
+            } else {
+                model.maker_fee
+            };
+            let notional = order.qty * price;
+            let fee = notional * fee_rate;
+            let (asset, currency) = (order.pair.symbol.clone(), order.pair.base.clone());
+
+            match order.trade_type {
+                TradeType::Buy => {
+                    PaperState::adjust(&mut state.locked, &currency, -(order.qty * order.price));
+                    PaperState::adjust(&mut state.balances, &currency, -(notional + fee));
+                    PaperState::adjust(&mut state.balances, &asset, order.qty);
+                }
+                TradeType::Sell => {
+                    PaperState::adjust(&mut state.locked, &asset, -order.qty);
+                    PaperState::adjust(&mut state.balances, &asset, -order.qty);
+                    PaperState::adjust(&mut state.balances, &currency, notional - fee);
+                }
+            }
+
+            let fill = Trade {
+                fee,
+                fee_symbol: Some(currency),
+                id: order.id.to_string(),
+                pair: order.pair.clone(),
+                sale_price: price,
+                qty: order.qty,
+                time: local_datetime_from_unix(quote.time),
+                trade_type: order.trade_type,
+            };
+
+            state.past_orders.push(order.to_order(order.qty));
+            state.fills.push(fill.clone());
+            fills.push(fill);
+        }
+
+        state.orders = open;
+        fills
+    }
+
+    /// Withdraws every open order, releasing the balances they held.
+    pub fn cancel_all(&self) {
+        let mut state = self.state.lock().expect("paper exchange state");
+        for order in std::mem::replace(&mut state.orders, Vec::new()) {
+            state.cancel(&order);
+        }
+    }
+
+    fn place(
+        &self,
+        symbol: &str,
+        trade_type: TradeType,
+        order_type: OrderType,
+        qty: Decimal,
+        price: Decimal,
+        stop_price: Option<Decimal>,
+    ) -> CoreResult<()> {
+        let pair = self
+            .instruments
+            .pair(symbol)
+            .ok_or(Box::new(TrailerError::PairNotFound(symbol.to_string())))?;
+
+        if qty <= Decimal::ZERO || price <= Decimal::ZERO {
+            return Err(Box::new(TrailerError::APIError(format!(
+                "invalid order: {} {} @ {}",
+                symbol, qty, price
+            ))));
+        }
+
+        let mut state = self.state.lock().expect("paper exchange state");
+        let (asset, required) = match trade_type {
+            TradeType::Buy => (
+                pair.base.clone(),
+                qty * price * (Decimal::ONE + self.fill_model.taker_fee),
+            ),
+            TradeType::Sell => (pair.symbol.clone(), qty),
+        };
+
+        if state.free(&asset) < required {
+            return Err(Box::new(TrailerError::APIError(format!(
+                "insufficient {} balance: {} required, {} free",
+                asset,
+                required,
+                state.free(&asset)
+            ))));
+        }
+
+        match trade_type {
+            TradeType::Buy => PaperState::adjust(&mut state.locked, &asset, qty * price),
+            TradeType::Sell => PaperState::adjust(&mut state.locked, &asset, qty),
+        }
+
+        state.next_id += 1;
+        let order = PaperOrder {
+            id: state.next_id,
+            symbol: symbol.to_string(),
+            pair,
+            trade_type,
+            order_type,
+            qty,
+            price,
+            stop_price,
+            placed: state.time,
+            active: state.time + self.fill_model.latency.as_millis() as u64,
+            rested: false,
+        };
+        state.orders.push(order);
+
+        Ok(())
+    }
+}
+
+impl ExchangeAPI for PaperAPI {
+    fn new() -> Self {
+        PaperAPI {
+            instruments: Arc::new(InstrumentMap::new(Exchange::Paper)),
+            fill_model: FillModel::default(),
+            state: Arc::new(Mutex::new(PaperState::default())),
+        }
+    }
+
+    fn authenticate(&self, key: &str, secret: &str) -> Self {
+        self.clone()
+    }
+
+    fn display(&self) -> String {
+        "paper".to_string()
+    }
+
+    fn btc_symbol(&self) -> String {
+        "BTC".to_string()
+    }
+
+    fn usd_symbol(&self) -> String {
+        "USDT".to_string()
+    }
+
+    fn base_pairs(&self) -> Vec<String> {
+        let mut bases: Vec<String> = self
+            .instruments
+            .pairs()
+            .into_iter()
+            .map(|pair| pair.base)
+            .collect();
+        bases.sort();
+        bases.dedup();
+        bases
+    }
+
+    fn balances(&self) -> CoreResult<Vec<Asset>> {
+        let state = self.state.lock().expect("paper exchange state");
+        Ok(state
+            .balances
+            .iter()
+            .map(|(symbol, amount)| Asset {
+                symbol: symbol.clone(),
+                amount: *amount,
+                locked: state.locked.get(symbol).cloned().unwrap_or(Decimal::ZERO),
+                exchange: Exchange::Paper,
+            })
+            .collect())
+    }
+
+    fn instruments(&self) -> CoreResult<Arc<InstrumentMap>> {
+        Ok(self.instruments.clone())
+    }
+
+    fn all_prices(&self) -> CoreResult<Vec<Price>> {
+        let state = self.state.lock().expect("paper exchange state");
+        Ok(state
+            .prices
+            .iter()
+            .filter_map(|(symbol, price)| {
+                self.instruments.pair(symbol).map(|pair| Price {
+                    pair,
+                    price: *price,
+                })
+            })
+            .collect())
+    }
+
+    fn limit_buy(&self, symbol: &str, amount: Decimal, price: Decimal) -> CoreResult<()> {
+        self.place(
+            symbol,
+            TradeType::Buy,
+            OrderType::Limit,
+            amount,
+            price,
+            None,
+        )
+    }
+
+    fn limit_sell(&self, symbol: &str, amount: Decimal, price: Decimal) -> CoreResult<()> {
+        self.place(
+            symbol,
+            TradeType::Sell,
+            OrderType::Limit,
+            amount,
+            price,
+            None,
+        )
+    }
+
+    fn cancel_order(&self, symbol: &str, order_id: &str) -> CoreResult<()> {
+        let mut state = self.state.lock().expect("paper exchange state");
+        let position = state
+            .orders
+            .iter()
+            .position(|order| order.symbol == symbol && order.id.to_string() == order_id)
+            .ok_or_else(|| {
+                Box::new(TrailerError::APIError(format!(
+                    "unknown order: {} {}",
+                    symbol, order_id
+                )))
+            })?;
+
+        let order = state.orders.remove(position);
+        state.cancel(&order);
+        Ok(())
+    }
+
+    fn open_orders(&self) -> CoreResult<Vec<Order>> {
+        let state = self.state.lock().expect("paper exchange state");
+        Ok(state
+            .orders
+            .iter()
+            .map(|order| order.to_order(Decimal::ZERO))
+            .collect())
+    }
+
+    fn past_orders(&self) -> CoreResult<Vec<Order>> {
+        Ok(self
+            .state
+            .lock()
+            .expect("paper exchange state")
+            .past_orders
+            .clone())
+    }
+
+    fn book_tickers(&self) -> CoreResult<Vec<BookTicker>> {
+        let state = self.state.lock().expect("paper exchange state");
+        Ok(state.book_tickers.values().cloned().collect())
+    }
+
+    fn trades_for_pair(&self, pair: Pair) -> CoreResult<Vec<Trade>> {
+        Ok(self
+            .fills()
+            .into_iter()
+            .filter(|trade| trade.pair == pair)
+            .collect())
+    }
+
+    fn intervals(&self) -> Vec<Interval> {
+        Vec::new()
+    }
+
+    fn chart_data(&self, pair: &str, interval: Interval) -> CoreResult<Vec<Candlestick>> {
+        Err(Box::new(TrailerError::Unsupported))
+    }
+
+    fn market_depth(&self, pair: &str) -> CoreResult<Depth> {
+        Err(Box::new(TrailerError::Unsupported))
+    }
+
+    fn stop_loss(
+        &self,
+        symbol: &str,
+        amount: Decimal,
+        stop_price: Decimal,
+        limit_price: Decimal,
+    ) -> CoreResult<()> {
+        self.place(
+            symbol,
+            TradeType::Sell,
+            OrderType::StopLossLimit,
+            amount,
+            limit_price,
+            Some(stop_price),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    /// 1000 USDT and 10 BTC, a 0.1% maker and 0.2% taker fee.
+    fn paper(latency: u64) -> PaperAPI {
+        let paper = PaperAPI::new()
+            .market("BTCUSDT", &Pair::new("BTC", "USDT"))
+            .fill_model(FillModel {
+                maker_fee: dec("0.001"),
+                taker_fee: dec("0.002"),
+                slippage: Decimal::ZERO,
+                latency: Duration::from_millis(latency),
+            });
+        paper.deposit("USDT", dec("1000"));
+        paper.deposit("BTC", dec("10"));
+        paper
+    }
+
+    /// a one minute candle starting at `start`.
+    fn candle(start: u64, open: &str, high: &str, low: &str, close: &str) -> Quote {
+        Quote::from_candle(&Candlestick {
+            open_time: start,
+            close_time: start + 59_999,
+            open_price: dec(open),
+            close_price: dec(close),
+            high_price: dec(high),
+            low_price: dec(low),
+            volume: Decimal::ONE,
+            quote_volume: None,
+            number_of_trades: 1,
+        })
+    }
+
+    fn locked(paper: &PaperAPI, symbol: &str) -> Decimal {
+        paper
+            .balances()
+            .unwrap()
+            .into_iter()
+            .find(|asset| asset.symbol == symbol)
+            .map_or(Decimal::ZERO, |asset| asset.locked)
+    }
+
+    #[test]
+    fn crossing_the_spread_takes_at_the_open() {
+        let paper = paper(0);
+        paper.limit_buy("BTCUSDT", dec("1"), dec("100")).unwrap();
+
+        let fills = paper.update("BTCUSDT", &candle(0, "90", "95", "85", "92"));
+
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].sale_price, dec("90"));
+        assert_eq!(fills[0].fee, dec("0.18"));
+        assert_eq!(paper.balance("USDT"), dec("909.82"));
+        assert_eq!(paper.balance("BTC"), dec("11"));
+        assert_eq!(locked(&paper, "USDT"), Decimal::ZERO);
+    }
+
+    #[test]
+    fn resting_orders_make_at_their_own_price() {
+        let paper = paper(0);
+        paper.limit_buy("BTCUSDT", dec("1"), dec("100")).unwrap();
+
+        assert!(paper
+            .update("BTCUSDT", &candle(0, "110", "115", "105", "108"))
+            .is_empty());
+
+        // a gap below the order still fills it at its price, as the maker.
+        let fills = paper.update("BTCUSDT", &candle(60_000, "95", "99", "90", "96"));
+        assert_eq!(fills[0].sale_price, dec("100"));
+        assert_eq!(fills[0].fee, dec("0.1"));
+        assert_eq!(paper.balance("USDT"), dec("899.9"));
+    }
+
+    #[test]
+    fn stop_sells_at_the_market_once_triggered() {
+        let paper = paper(0);
+        paper
+            .stop_loss("BTCUSDT", dec("1"), dec("95"), dec("90"))
+            .unwrap();
+
+        assert!(paper
+            .update("BTCUSDT", &candle(0, "100", "101", "96", "97"))
+            .is_empty());
+
+        let fills = paper.update("BTCUSDT", &candle(60_000, "97", "98", "94", "96"));
+        assert_eq!(fills[0].sale_price, dec("95"));
+        assert_eq!(fills[0].fee, dec("0.19"));
+        assert_eq!(paper.balance("BTC"), dec("9"));
+    }
+
+    #[test]
+    fn stop_gapping_past_its_limit_rests_at_the_limit() {
+        let paper = paper(0);
+        paper
+            .stop_loss("BTCUSDT", dec("1"), dec("95"), dec("90"))
+            .unwrap();
+
+        assert!(paper
+            .update("BTCUSDT", &candle(0, "85", "88", "80", "86"))
+            .is_empty());
+        assert_eq!(paper.open_orders().unwrap()[0].order_type, OrderType::Limit);
+
+        let fills = paper.update("BTCUSDT", &candle(60_000, "87", "91", "86", "89"));
+        assert_eq!(fills[0].sale_price, dec("90"));
+        assert_eq!(fills[0].fee, dec("0.09"));
+    }
+
+    #[test]
+    fn orders_lock_their_balance_until_cancelled() {
+        let paper = paper(0);
+        paper.limit_buy("BTCUSDT", dec("5"), dec("100")).unwrap();
+
+        assert_eq!(locked(&paper, "USDT"), dec("500"));
+        assert!(paper.limit_buy("BTCUSDT", dec("5"), dec("100")).is_err());
+
+        let id = paper.open_orders().unwrap()[0].id.clone();
+        paper.cancel_order("BTCUSDT", &id).unwrap();
+
+        assert_eq!(locked(&paper, "USDT"), Decimal::ZERO);
+        assert_eq!(paper.past_orders().unwrap()[0].executed_qty, Decimal::ZERO);
+        assert!(paper.limit_buy("BTCUSDT", dec("5"), dec("100")).is_ok());
+
+        paper.limit_sell("BTCUSDT", dec("10"), dec("200")).unwrap();
+        assert_eq!(locked(&paper, "BTC"), dec("10"));
+        assert!(paper.limit_sell("BTCUSDT", dec("1"), dec("200")).is_err());
+
+        paper.cancel_all();
+        assert_eq!(locked(&paper, "USDT"), Decimal::ZERO);
+        assert_eq!(locked(&paper, "BTC"), Decimal::ZERO);
+        assert!(paper.open_orders().unwrap().is_empty());
+    }
+
+    #[test]
+    fn latency_hides_the_candle_the_order_arrives_in() {
+        let paper = paper(30_000);
+        paper.limit_buy("BTCUSDT", dec("1"), dec("100")).unwrap();
+
+        // the open of 90 was gone before the order reached the book.
+        assert!(paper
+            .update("BTCUSDT", &candle(0, "90", "95", "85", "92"))
+            .is_empty());
+
+        let fills = paper.update("BTCUSDT", &candle(60_000, "101", "103", "99", "102"));
+        assert_eq!(fills[0].sale_price, dec("100"));
+        assert_eq!(fills[0].fee, dec("0.1"));
+    }
+
+    #[test]
+    fn latency_with_single_prices() {
+        let paper = paper(30_000);
+        paper.limit_buy("BTCUSDT", dec("1"), dec("100")).unwrap();
+
+        assert!(paper
+            .update("BTCUSDT", &Quote::from_price(20_000, dec("90")))
+            .is_empty());
+
+        let fills = paper.update("BTCUSDT", &Quote::from_price(30_000, dec("95")));
+        assert_eq!(fills[0].sale_price, dec("95"));
+        assert_eq!(fills[0].fee, dec("0.19"));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::models::*;
+use rust_decimal::Decimal;
+use std::time::Duration;
+
+/// How a parent order is cut into child orders.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionStyle {
+    /// Time weighted: the window is split into `slices` equal parts and an equal share of the
+    /// quantity is worked in each.
+    Twap { slices: u32 },
+    /// Volume weighted: keeps the quantity worked to `rate` of the volume the market has traded
+    /// since the start, eg. `0.1` for 10%.
+    Participation { rate: Decimal },
+    /// Works the whole quantity at once, showing at most `display` of it on the book.
+    Iceberg { display: Decimal },
+}
+
+/// An order too large to send as is, worked through child orders over a window of time.
+///
+/// ```rust,ignore
+/// let parent = ParentOrder::new("BTCUSDT", TradeType::Buy, qty, ExecutionStyle::Twap { slices: 12 })
+///     .duration(Duration::from_secs(60 * 60))
+///     .limit_price(Decimal::new(10_500, 0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParentOrder {
+    /// the exchange's native symbol.
+    pub symbol: String,
+    pub side: TradeType,
+    pub qty: Decimal,
+    /// unix millis, `None` starts with the first price seen.
+    pub start: Option<u64>,
+    pub duration: Duration,
+    /// no child order is priced worse than this.
+    pub limit_price: Option<Decimal>,
+    pub style: ExecutionStyle,
+}
+
+impl ParentOrder {
+    pub fn new(symbol: &str, side: TradeType, qty: Decimal, style: ExecutionStyle) -> Self {
+        ParentOrder {
+            symbol: symbol.to_string(),
+            side,
+            qty,
+            start: None,
+            duration: Duration::from_secs(60 * 60),
+            limit_price: None,
+            style,
+        }
+    }
+
+    pub fn start(mut self, start: u64) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn limit_price(mut self, limit_price: Decimal) -> Self {
+        self.limit_price = Some(limit_price);
+        self
+    }
+
+    /// How much should have been worked by `time`, given the market `volume` since `start`.
+    pub fn target_qty(&self, start: u64, time: u64, volume: Decimal) -> Decimal {
+        let elapsed = time.saturating_sub(start);
+        let duration = self.duration.as_millis() as u64;
+
+        match &self.style {
+            ExecutionStyle::Twap { slices } => {
+                let slices = u64::from((*slices).max(1));
+                let slice = match duration {
+                    0 => slices,
+                    _ => (elapsed * slices / duration + 1).min(slices),
+                };
+                self.qty * Decimal::from(slice) / Decimal::from(slices)
+            }
+            ExecutionStyle::Participation { rate } => (volume * *rate).min(self.qty),
+            ExecutionStyle::Iceberg { .. } => self.qty,
+        }
+    }
+
+    /// the largest child order the style allows on the book at a time.
+    pub fn display_qty(&self) -> Option<Decimal> {
+        match &self.style {
+            ExecutionStyle::Iceberg { display } => Some(*display),
+            _ => None,
+        }
+    }
+
+    /// Where a child order goes given the touch: icebergs rest on their own side of the book (or
+    /// at the limit price), other styles cross the spread, but never past the limit price.
+    pub fn child_price(&self, bid: Decimal, ask: Decimal) -> Decimal {
+        let price = match (&self.style, self.side, self.limit_price) {
+            (ExecutionStyle::Iceberg { .. }, _, Some(limit)) => return limit,
+            (ExecutionStyle::Iceberg { .. }, TradeType::Buy, None) => bid,
+            (ExecutionStyle::Iceberg { .. }, TradeType::Sell, None) => ask,
+            (_, TradeType::Buy, _) => ask,
+            (_, TradeType::Sell, _) => bid,
+        };
+
+        match (self.limit_price, self.side) {
+            (Some(limit), TradeType::Buy) => price.min(limit),
+            (Some(limit), TradeType::Sell) => price.max(limit),
+            (None, _) => price,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn parent(side: TradeType, style: ExecutionStyle) -> ParentOrder {
+        ParentOrder::new("BTCUSDT", side, dec("10"), style).duration(Duration::from_secs(100))
+    }
+
+    #[test]
+    fn twap_targets_one_slice_ahead() {
+        let order = parent(TradeType::Buy, ExecutionStyle::Twap { slices: 4 });
+
+        assert_eq!(order.target_qty(1000, 1000, Decimal::ZERO), dec("2.5"));
+        assert_eq!(order.target_qty(1000, 25_999, Decimal::ZERO), dec("2.5"));
+        assert_eq!(order.target_qty(1000, 26_000, Decimal::ZERO), dec("5"));
+        assert_eq!(order.target_qty(1000, 76_000, Decimal::ZERO), dec("10"));
+        assert_eq!(order.target_qty(1000, 500_000, Decimal::ZERO), dec("10"));
+        // before the start nothing has elapsed.
+        assert_eq!(order.target_qty(1000, 0, Decimal::ZERO), dec("2.5"));
+    }
+
+    #[test]
+    fn twap_without_a_window_works_everything() {
+        let order = parent(TradeType::Buy, ExecutionStyle::Twap { slices: 0 })
+            .duration(Duration::from_secs(0));
+        assert_eq!(order.target_qty(0, 0, Decimal::ZERO), dec("10"));
+    }
+
+    #[test]
+    fn participation_follows_volume_up_to_the_quantity() {
+        let order = parent(
+            TradeType::Sell,
+            ExecutionStyle::Participation { rate: dec("0.1") },
+        );
+
+        assert_eq!(order.target_qty(0, 50_000, dec("30")), dec("3"));
+        assert_eq!(order.target_qty(0, 50_000, dec("500")), dec("10"));
+    }
+
+    #[test]
+    fn iceberg_targets_everything_but_displays_a_part() {
+        let order = parent(
+            TradeType::Buy,
+            ExecutionStyle::Iceberg { display: dec("2") },
+        );
+
+        assert_eq!(order.target_qty(0, 0, Decimal::ZERO), dec("10"));
+        assert_eq!(order.display_qty(), Some(dec("2")));
+        assert_eq!(
+            parent(TradeType::Buy, ExecutionStyle::Twap { slices: 2 }).display_qty(),
+            None
+        );
+    }
+
+    #[test]
+    fn crossing_styles_take_the_other_side_within_the_limit() {
+        let buy = parent(TradeType::Buy, ExecutionStyle::Twap { slices: 2 });
+        let sell = parent(TradeType::Sell, ExecutionStyle::Twap { slices: 2 });
+
+        assert_eq!(buy.child_price(dec("99"), dec("101")), dec("101"));
+        assert_eq!(sell.child_price(dec("99"), dec("101")), dec("99"));
+        assert_eq!(
+            buy.limit_price(dec("100"))
+                .child_price(dec("99"), dec("101")),
+            dec("100")
+        );
+        assert_eq!(
+            sell.limit_price(dec("100"))
+                .child_price(dec("99"), dec("101")),
+            dec("100")
+        );
+    }
+
+    #[test]
+    fn icebergs_rest_on_their_own_side_or_at_the_limit() {
+        let style = ExecutionStyle::Iceberg { display: dec("1") };
+        let buy = parent(TradeType::Buy, style.clone());
+        let sell = parent(TradeType::Sell, style);
+
+        assert_eq!(buy.child_price(dec("99"), dec("101")), dec("99"));
+        assert_eq!(sell.child_price(dec("99"), dec("101")), dec("101"));
+        assert_eq!(
+            buy.limit_price(dec("95"))
+                .child_price(dec("99"), dec("101")),
+            dec("95")
+        );
+        assert_eq!(
+            sell.limit_price(dec("105"))
+                .child_price(dec("99"), dec("101")),
+            dec("105")
+        );
+    }
+}
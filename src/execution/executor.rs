@@ -0,0 +1,554 @@
+use crate::error::*;
+use crate::exchanges::ExchangeAPI;
+use crate::execution::*;
+use crate::models::*;
+use crate::strategies::{FeedEvent, MarketEvent};
+use crate::utils::*;
+use chrono::prelude::*;
+use log::{info, warn};
+use rust_decimal::Decimal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fmt, fmt::Display};
+
+/// A limit order sent on behalf of a parent order.
+#[derive(Debug, Clone)]
+pub struct ChildOrder {
+    /// the exchange's id, `None` when the order was never seen on the book.
+    pub id: Option<String>,
+    pub qty: Decimal,
+    pub executed_qty: Decimal,
+    pub price: Decimal,
+    /// unix millis.
+    pub placed: u64,
+    /// whether a cancel has been sent.
+    pub cancelled: bool,
+}
+
+/// How a parent order was worked.
+#[derive(Debug, Clone)]
+pub struct ExecutionReport {
+    pub symbol: String,
+    pub side: TradeType,
+    pub qty: Decimal,
+    pub filled_qty: Decimal,
+    pub notional: Decimal,
+    pub average_price: Option<Decimal>,
+    /// the mid price when the order started.
+    pub arrival_price: Option<Decimal>,
+    /// percent lost to the market against the arrival price, positive for a cost.
+    pub slippage: Option<Decimal>,
+    pub child_orders: u32,
+    /// child orders cancelled to be sent again at a better price.
+    pub replaced: u32,
+    pub started: Option<DateTime<Local>>,
+    pub finished: Option<DateTime<Local>>,
+    /// some child orders left the book without the exchange saying what they filled, and were
+    /// counted as filled at their own price.
+    pub estimated: bool,
+}
+
+impl ExecutionReport {
+    pub fn is_complete(&self) -> bool {
+        self.filled_qty >= self.qty
+    }
+}
+
+impl Display for ExecutionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} of {} {}",
+            self.side, self.filled_qty, self.qty, self.symbol
+        )?;
+
+        if let Some(average_price) = self.average_price {
+            write!(f, " at {}", average_price.round_dp(8))?;
+        }
+        if let (Some(arrival_price), Some(slippage)) = (self.arrival_price, self.slippage) {
+            write!(
+                f,
+                ", arrival {} ({}%)",
+                arrival_price.round_dp(8),
+                slippage.round_dp(4)
+            )?;
+        }
+
+        write!(
+            f,
+            ", {} child orders, {} replaced",
+            self.child_orders, self.replaced
+        )?;
+
+        if self.estimated {
+            write!(f, " (estimated)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Works a `ParentOrder` through an `ExchangeAPI` as limit orders. Child orders are followed
+/// through `open_orders`, and those gone from the book are looked up in `past_orders` or by id
+/// for what they filled. Where the exchange can do neither, an order that disappears counts as
+/// filled at its price, unless it was cancelled, and the report is marked `estimated`. Children
+/// left behind by the market for `stale_after` are cancelled and sent again at the new price;
+/// once the window ends whatever is still working is cancelled.
+///
+/// Child orders follow the market's `MarketRules`: quantities are rounded down to the step size,
+/// prices to the tick size, and a slice too small to place waits until it has grown. Once what
+/// is left of the parent is too small to place the order finishes.
+///
+/// ```rust,ignore
+/// let mut executor = Executor::new(parent);
+/// binance_feed(&binance, "BTCUSDT", Interval::OneMinute, sender)?;
+/// let report = executor.run(&binance, &receiver, &running)?;
+/// println!("{}", report);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Executor {
+    parent: ParentOrder,
+    pair: Option<Pair>,
+    rules: Option<MarketRules>,
+    stale_after: Duration,
+    start: Option<u64>,
+    time: u64,
+    /// best bid and ask.
+    touch: Option<(Decimal, Decimal)>,
+    arrival_price: Option<Decimal>,
+    volume: Decimal,
+    /// whether trades have been seen, candle volume is only used without them.
+    tape: bool,
+    working: Vec<ChildOrder>,
+    filled_qty: Decimal,
+    notional: Decimal,
+    child_orders: u32,
+    replaced: u32,
+    estimated: bool,
+    finished: Option<u64>,
+}
+
+impl Executor {
+    pub fn new(parent: ParentOrder) -> Self {
+        Executor {
+            parent,
+            pair: None,
+            rules: None,
+            stale_after: Duration::from_secs(30),
+            start: None,
+            time: 0,
+            touch: None,
+            arrival_price: None,
+            volume: Decimal::ZERO,
+            tape: false,
+            working: Vec::new(),
+            filled_qty: Decimal::ZERO,
+            notional: Decimal::ZERO,
+            child_orders: 0,
+            replaced: 0,
+            estimated: false,
+            finished: None,
+        }
+    }
+
+    pub fn stale_after(mut self, stale_after: Duration) -> Self {
+        self.stale_after = stale_after;
+        self
+    }
+
+    pub fn parent(&self) -> &ParentOrder {
+        &self.parent
+    }
+
+    /// child orders still on the book.
+    pub fn working(&self) -> &[ChildOrder] {
+        &self.working
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.is_some()
+    }
+
+    pub fn report(&self) -> ExecutionReport {
+        let average_price = self.notional.checked_div(self.filled_qty);
+        let slippage = match (self.arrival_price, average_price) {
+            (Some(arrival), Some(average)) => Some(match self.parent.side {
+                TradeType::Buy => price_percent(arrival, average),
+                TradeType::Sell => -price_percent(arrival, average),
+            }),
+            _ => None,
+        };
+
+        ExecutionReport {
+            symbol: self.parent.symbol.clone(),
+            side: self.parent.side,
+            qty: self.parent.qty,
+            filled_qty: self.filled_qty,
+            notional: self.notional,
+            average_price,
+            arrival_price: self.arrival_price,
+            slippage,
+            child_orders: self.child_orders,
+            replaced: self.replaced,
+            started: self.start.map(local_datetime_from_unix),
+            finished: self.finished.map(local_datetime_from_unix),
+            estimated: self.estimated,
+        }
+    }
+
+    /// Takes prices and volume from a market event for the parent's symbol and works the order.
+    pub fn on_event(&mut self, exchange: &dyn ExchangeAPI, event: &FeedEvent) -> CoreResult<()> {
+        if event.symbol != self.parent.symbol {
+            return Ok(());
+        }
+
+        let start = self.start;
+        let started = |time: u64| start.map_or(false, |start| time >= start);
+        match &event.event {
+            MarketEvent::Trade(trade) => {
+                self.tape = true;
+                if started(trade.time.timestamp_millis().max(0) as u64) {
+                    self.volume += trade.qty;
+                }
+            }
+            MarketEvent::Candle(candle) if !self.tape && started(candle.open_time) => {
+                self.volume += candle.volume;
+            }
+            _ => {}
+        }
+
+        let touch = match &event.event {
+            MarketEvent::Candle(candle) => Some((candle.close_price, candle.close_price)),
+            other => other.quote().map(|quote| (quote.bid_open, quote.ask_open)),
+        };
+        match touch {
+            Some((bid, ask)) => self.on_quote(exchange, event.event.time(), bid, ask),
+            None => Ok(()),
+        }
+    }
+
+    /// Works the order against the best bid and ask at `time`, unix millis.
+    pub fn on_quote(
+        &mut self,
+        exchange: &dyn ExchangeAPI,
+        time: u64,
+        bid: Decimal,
+        ask: Decimal,
+    ) -> CoreResult<()> {
+        self.time = self.time.max(time);
+        self.touch = Some((bid, ask));
+        self.step(exchange)
+    }
+
+    /// Works the order against the exchange's current book tickers, for exchanges without a feed.
+    pub fn poll(&mut self, exchange: &dyn ExchangeAPI) -> CoreResult<()> {
+        let pair = exchange.pair(&self.parent.symbol)?;
+        let ticker = exchange
+            .book_tickers()?
+            .into_iter()
+            .find(|ticker| ticker.pair == pair)
+            .ok_or(Box::new(TrailerError::PairNotFound(pair.to_string())))?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_millis() as u64)
+            .unwrap_or(0);
+        self.on_quote(exchange, now, ticker.bid_price, ticker.ask_price)
+    }
+
+    /// Works the order from a feed until it is finished, `running` is cleared or the feed hangs
+    /// up, then cancels what is left. Errors along the way are logged and retried on the next
+    /// event.
+    pub fn run(
+        &mut self,
+        exchange: &dyn ExchangeAPI,
+        events: &Receiver<FeedEvent>,
+        running: &AtomicBool,
+    ) -> CoreResult<ExecutionReport> {
+        Self::check_supported(exchange)?;
+
+        while running.load(Ordering::Relaxed) && !self.is_finished() {
+            let result = match events.recv_timeout(Duration::from_millis(250)) {
+                Ok(event) => self.on_event(exchange, &event),
+                Err(RecvTimeoutError::Timeout) => Ok(()),
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if let Err(e) = result {
+                warn!(
+                    "{}: executing {}: {}",
+                    exchange.display(),
+                    self.parent.symbol,
+                    e
+                );
+            }
+        }
+
+        if !self.is_finished() {
+            self.stop(exchange)?;
+        }
+        Ok(self.report())
+    }
+
+    /// Cancels the working child orders and finishes the order once they are off the book.
+    pub fn stop(&mut self, exchange: &dyn ExchangeAPI) -> CoreResult<()> {
+        for child in self.working.iter_mut().filter(|child| !child.cancelled) {
+            Self::cancel(exchange, &self.parent.symbol, child);
+        }
+        self.sync(exchange)?;
+
+        if self.working.is_empty() {
+            self.finished = Some(self.time);
+            info!("{}", self.report());
+        }
+        Ok(())
+    }
+
+    /// Children are followed through the exchange's open orders, without them every event would
+    /// place another. Fails with the exchange's `Unsupported` where they cannot be listed.
+    fn check_supported(exchange: &dyn ExchangeAPI) -> CoreResult<()> {
+        exchange.open_orders().map(|_| ())
+    }
+
+    fn step(&mut self, exchange: &dyn ExchangeAPI) -> CoreResult<()> {
+        let (bid, ask) = match (self.finished, self.touch) {
+            (None, Some(touch)) => touch,
+            _ => return Ok(()),
+        };
+
+        let start = *self
+            .start
+            .get_or_insert(self.parent.start.unwrap_or(self.time));
+        if self.time < start {
+            return Ok(());
+        }
+        if self.arrival_price.is_none() {
+            self.arrival_price = Some((bid + ask) / Decimal::new(2, 0));
+        }
+        if self.pair.is_none() {
+            Self::check_supported(exchange)?;
+            self.pair = Some(exchange.pair(&self.parent.symbol)?);
+            self.rules = exchange.instruments()?.rules(&self.parent.symbol).cloned();
+        }
+
+        self.sync(exchange)?;
+
+        let end = start + self.parent.duration.as_millis() as u64;
+        if self.filled_qty >= self.parent.qty || self.time >= end {
+            return self.stop(exchange);
+        }
+
+        let price = self.round_price(self.parent.child_price(bid, ask));
+        let stale_after = self.stale_after.as_millis() as u64;
+        let time = self.time;
+        let mut replaced = 0;
+
+        for child in self.working.iter_mut().filter(|child| {
+            !child.cancelled && child.price != price && time - child.placed >= stale_after
+        }) {
+            if Self::cancel(exchange, &self.parent.symbol, child) {
+                replaced += 1;
+            }
+        }
+        if replaced > 0 {
+            self.replaced += replaced;
+            self.sync(exchange)?;
+        }
+
+        let working_qty: Decimal = self
+            .working
+            .iter()
+            .map(|child| child.qty - child.executed_qty)
+            .sum();
+        let target = self.parent.target_qty(start, self.time, self.volume);
+
+        let remaining = self.round_qty(self.parent.qty - self.filled_qty - working_qty);
+        if let Some(reason) = self.rejected(remaining, price) {
+            if self.working.is_empty() {
+                info!(
+                    "{}: finishing, what is left cannot be placed: {}",
+                    self.parent.symbol, reason
+                );
+                return self.stop(exchange);
+            }
+            return Ok(());
+        }
+
+        let mut qty = (target - self.filled_qty - working_qty).min(remaining);
+        if let Some(display) = self.parent.display_qty() {
+            qty = qty.min(display - working_qty);
+        }
+        let qty = self.round_qty(qty);
+
+        // a slice below the rules is left to grow instead of being sent to fail.
+        if qty > Decimal::ZERO && self.rejected(qty, price).is_none() {
+            self.place(exchange, qty, price)?;
+        }
+        Ok(())
+    }
+
+    fn round_qty(&self, qty: Decimal) -> Decimal {
+        match &self.rules {
+            Some(rules) => rules.round_qty(qty),
+            None => {
+                let satoshi = Decimal::new(1, 8);
+                (qty / satoshi).floor() * satoshi
+            }
+        }
+    }
+
+    fn round_price(&self, price: Decimal) -> Decimal {
+        match &self.rules {
+            Some(rules) => rules.round_price(price),
+            None => price.round_dp(8),
+        }
+    }
+
+    /// why the market would reject an order, nothing is placeable below zero either way.
+    fn rejected(&self, qty: Decimal, price: Decimal) -> Option<String> {
+        match &self.rules {
+            Some(rules) => rules.check(qty, price),
+            None if qty <= Decimal::ZERO => Some("nothing left".to_string()),
+            None => None,
+        }
+    }
+
+    fn place(
+        &mut self,
+        exchange: &dyn ExchangeAPI,
+        qty: Decimal,
+        price: Decimal,
+    ) -> CoreResult<()> {
+        let symbol = self.parent.symbol.as_str();
+        match self.parent.side {
+            TradeType::Buy => exchange.limit_buy(symbol, qty, price)?,
+            TradeType::Sell => exchange.limit_sell(symbol, qty, price)?,
+        }
+        self.child_orders += 1;
+
+        info!(
+            "{} child {} {} {} at {}",
+            self.parent.symbol, self.parent.side, qty, symbol, price
+        );
+        // recorded before its id is known, so a failed lookup cannot send the same child again.
+        self.working.push(ChildOrder {
+            id: None,
+            qty,
+            executed_qty: Decimal::ZERO,
+            price,
+            placed: self.time,
+            cancelled: false,
+        });
+
+        match exchange.open_orders() {
+            Ok(open) => self.adopt_ids(&open),
+            Err(e) => warn!("{}: finding child order id: {}", symbol, e),
+        }
+        Ok(())
+    }
+
+    /// Gives children placed without an id the id of a matching open order not yet claimed.
+    fn adopt_ids(&mut self, open: &[Order]) {
+        for index in 0..self.working.len() {
+            if self.working[index].id.is_some() {
+                continue;
+            }
+
+            let (qty, price) = (self.working[index].qty, self.working[index].price);
+            let id = open
+                .iter()
+                .find(|order| {
+                    Some(&order.pair) == self.pair.as_ref()
+                        && order.trade_type == self.parent.side
+                        && order.qty == qty
+                        && order.purchase_price == price
+                        && !self
+                            .working
+                            .iter()
+                            .any(|child| child.id.as_ref() == Some(&order.id))
+                })
+                .map(|order| order.id.clone());
+            self.working[index].id = id;
+        }
+    }
+
+    /// sends a cancel, returning whether it went through. Failures are left to the next sync,
+    /// usually the order has just filled.
+    fn cancel(exchange: &dyn ExchangeAPI, symbol: &str, child: &mut ChildOrder) -> bool {
+        let id = match &child.id {
+            Some(id) => id,
+            None => return false,
+        };
+
+        match exchange.cancel_order(symbol, id) {
+            Ok(()) => {
+                child.cancelled = true;
+                true
+            }
+            Err(e) => {
+                warn!("cancelling {} {}: {}", symbol, id, e);
+                false
+            }
+        }
+    }
+
+    /// Brings the child orders up to date with the exchange, counting what they filled.
+    fn sync(&mut self, exchange: &dyn ExchangeAPI) -> CoreResult<()> {
+        if self.working.is_empty() {
+            return Ok(());
+        }
+
+        let open = exchange.open_orders()?;
+        self.adopt_ids(&open);
+        let mut past: Option<Vec<Order>> = None;
+        let mut working = Vec::new();
+
+        for mut child in std::mem::replace(&mut self.working, Vec::new()) {
+            let on_book = child
+                .id
+                .as_ref()
+                .and_then(|id| open.iter().find(|order| &order.id == id));
+
+            let (executed_qty, price) = match on_book {
+                Some(order) => (order.executed_qty, order.purchase_price),
+                None => {
+                    let past =
+                        past.get_or_insert_with(|| exchange.past_orders().unwrap_or_default());
+                    let closed = child.id.as_ref().and_then(|id| {
+                        past.iter()
+                            .find(|order| &order.id == id)
+                            .cloned()
+                            .or_else(|| exchange.order(&self.parent.symbol, id).ok())
+                    });
+
+                    match closed {
+                        Some(order) => (order.executed_qty, order.purchase_price),
+                        None if child.cancelled => (child.executed_qty, child.price),
+                        None => {
+                            self.estimated = true;
+                            (child.qty, child.price)
+                        }
+                    }
+                }
+            };
+
+            if executed_qty > child.executed_qty {
+                let filled = executed_qty - child.executed_qty;
+                let price = if price > Decimal::ZERO {
+                    price
+                } else {
+                    child.price
+                };
+                self.filled_qty += filled;
+                self.notional += filled * price;
+                child.executed_qty = executed_qty;
+            }
+
+            if on_book.is_some() {
+                working.push(child);
+            }
+        }
+
+        self.working = working;
+        Ok(())
+    }
+}
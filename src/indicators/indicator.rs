@@ -0,0 +1,98 @@
+use crate::models::Candlestick;
+use rust_decimal::Decimal;
+
+/// A streaming indicator, updated one candle at a time.
+pub trait Indicator {
+    type Output;
+
+    /// feeds the next candle, returning a value once enough candles have been seen.
+    fn next(&mut self, candle: &Candlestick) -> Option<Self::Output>;
+
+    /// forgets every candle seen so far.
+    fn reset(&mut self);
+
+    /// Batch computation over a whole series, one entry per candle. The entries are `None` while
+    /// the indicator is warming up.
+    fn calculate(&mut self, candles: &[Candlestick]) -> Vec<Option<Self::Output>> {
+        self.reset();
+        candles.iter().map(|candle| self.next(candle)).collect()
+    }
+
+    /// the value after the last candle of the series.
+    fn last(&mut self, candles: &[Candlestick]) -> Option<Self::Output> {
+        self.calculate(candles).pop().and_then(|value| value)
+    }
+}
+
+/// Square root by newton's method, `Decimal` has none without the `maths` feature.
+pub(crate) fn sqrt(value: Decimal) -> Decimal {
+    if value <= Decimal::ZERO {
+        return Decimal::ZERO;
+    }
+
+    let two = Decimal::new(2, 0);
+    let mut guess = if value > Decimal::ONE {
+        value / two
+    } else {
+        value
+    };
+
+    for _ in 0..64 {
+        let next = (guess + value / guess) / two;
+        if next == guess {
+            break;
+        }
+        guess = next;
+    }
+
+    guess
+}
+
+pub(crate) fn hundred() -> Decimal {
+    Decimal::new(100, 0)
+}
+
+/// a candle with the given high, low, close and volume, opening at the close.
+#[cfg(test)]
+pub(crate) fn candle(high: i64, low: i64, close: i64, volume: i64) -> Candlestick {
+    Candlestick {
+        open_time: 0,
+        close_time: 59_999,
+        open_price: Decimal::from(close),
+        close_price: Decimal::from(close),
+        high_price: Decimal::from(high),
+        low_price: Decimal::from(low),
+        volume: Decimal::from(volume),
+        quote_volume: None,
+        number_of_trades: 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::Sma;
+
+    fn closes(closes: &[i64]) -> Vec<Candlestick> {
+        closes.iter().map(|c| candle(*c, *c, *c, 1)).collect()
+    }
+
+    #[test]
+    fn calculate_starts_from_scratch() {
+        let mut sma = Sma::new(2);
+        let expected = vec![None, Some(Decimal::new(15, 1)), Some(Decimal::new(25, 1))];
+
+        assert_eq!(sma.calculate(&closes(&[1, 2, 3])), expected);
+        assert_eq!(sma.calculate(&closes(&[1, 2, 3])), expected);
+        assert_eq!(sma.last(&closes(&[1, 2, 3])), Some(Decimal::new(25, 1)));
+        assert_eq!(sma.last(&closes(&[1])), None);
+    }
+
+    #[test]
+    fn square_roots() {
+        assert_eq!(sqrt(Decimal::new(16, 0)), Decimal::new(4, 0));
+        assert_eq!(sqrt(Decimal::ONE), Decimal::ONE);
+        assert_eq!(sqrt(Decimal::new(25, 2)), Decimal::new(5, 1));
+        assert_eq!(sqrt(Decimal::new(-4, 0)), Decimal::ZERO);
+    }
+}
@@ -0,0 +1,279 @@
+use crate::indicators::{hundred, Ema, Indicator, Sma};
+use crate::models::Candlestick;
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+
+/// Relative strength index of the close price, with Wilder's smoothing.
+#[derive(Debug, Clone)]
+pub struct Rsi {
+    period: usize,
+    previous: Option<Decimal>,
+    seen: usize,
+    avg_gain: Decimal,
+    avg_loss: Decimal,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        Rsi {
+            period: period.max(1),
+            previous: None,
+            seen: 0,
+            avg_gain: Decimal::ZERO,
+            avg_loss: Decimal::ZERO,
+        }
+    }
+
+    pub fn next_price(&mut self, price: Decimal) -> Option<Decimal> {
+        let previous = match self.previous.replace(price) {
+            Some(previous) => previous,
+            None => return None,
+        };
+
+        let change = price - previous;
+        let gain = change.max(Decimal::ZERO);
+        let loss = (-change).max(Decimal::ZERO);
+        let period = Decimal::from(self.period);
+
+        self.seen += 1;
+        if self.seen <= self.period {
+            // the first averages are plain means of the first `period` changes.
+            self.avg_gain += gain / period;
+            self.avg_loss += loss / period;
+            if self.seen < self.period {
+                return None;
+            }
+        } else {
+            self.avg_gain = (self.avg_gain * (period - Decimal::ONE) + gain) / period;
+            self.avg_loss = (self.avg_loss * (period - Decimal::ONE) + loss) / period;
+        }
+
+        if self.avg_loss.is_zero() {
+            // no losses at all is as strong as it gets, no movement at all is neutral.
+            return Some(if self.avg_gain.is_zero() {
+                Decimal::new(50, 0)
+            } else {
+                hundred()
+            });
+        }
+
+        let strength = self.avg_gain / self.avg_loss;
+        Some(hundred() - hundred() / (Decimal::ONE + strength))
+    }
+}
+
+impl Indicator for Rsi {
+    type Output = Decimal;
+
+    fn next(&mut self, candle: &Candlestick) -> Option<Decimal> {
+        self.next_price(candle.close_price)
+    }
+
+    fn reset(&mut self) {
+        *self = Rsi::new(self.period);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdOutput {
+    pub macd: Decimal,
+    pub signal: Decimal,
+    pub histogram: Decimal,
+}
+
+/// Moving average convergence divergence, commonly `Macd::new(12, 26, 9)`.
+#[derive(Debug, Clone)]
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+}
+
+impl Macd {
+    pub fn new(fast: usize, slow: usize, signal: usize) -> Self {
+        Macd {
+            fast: Ema::new(fast),
+            slow: Ema::new(slow),
+            signal: Ema::new(signal),
+        }
+    }
+
+    pub fn next_price(&mut self, price: Decimal) -> Option<MacdOutput> {
+        let fast = self.fast.next_price(price);
+        let slow = self.slow.next_price(price);
+
+        let macd = match (fast, slow) {
+            (Some(fast), Some(slow)) => fast - slow,
+            _ => return None,
+        };
+
+        self.signal.next_price(macd).map(|signal| MacdOutput {
+            macd,
+            signal,
+            histogram: macd - signal,
+        })
+    }
+}
+
+impl Default for Macd {
+    fn default() -> Self {
+        Macd::new(12, 26, 9)
+    }
+}
+
+impl Indicator for Macd {
+    type Output = MacdOutput;
+
+    fn next(&mut self, candle: &Candlestick) -> Option<MacdOutput> {
+        self.next_price(candle.close_price)
+    }
+
+    fn reset(&mut self) {
+        self.fast.reset();
+        self.slow.reset();
+        self.signal.reset();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StochasticOutput {
+    /// where the close sits in the recent high-low range, 0 to 100.
+    pub k: Decimal,
+    /// simple average of `k`.
+    pub d: Decimal,
+}
+
+/// Stochastic oscillator, commonly `Stochastic::new(14, 3)`.
+#[derive(Debug, Clone)]
+pub struct Stochastic {
+    k_period: usize,
+    window: VecDeque<(Decimal, Decimal)>,
+    d: Sma,
+}
+
+impl Stochastic {
+    pub fn new(k_period: usize, d_period: usize) -> Self {
+        Stochastic {
+            k_period: k_period.max(1),
+            window: VecDeque::new(),
+            d: Sma::new(d_period),
+        }
+    }
+}
+
+impl Default for Stochastic {
+    fn default() -> Self {
+        Stochastic::new(14, 3)
+    }
+}
+
+impl Indicator for Stochastic {
+    type Output = StochasticOutput;
+
+    fn next(&mut self, candle: &Candlestick) -> Option<StochasticOutput> {
+        self.window.push_back((candle.high_price, candle.low_price));
+        if self.window.len() > self.k_period {
+            self.window.pop_front();
+        }
+        if self.window.len() < self.k_period {
+            return None;
+        }
+
+        let highest = self.window.iter().map(|(high, _)| *high).max()?;
+        let lowest = self.window.iter().map(|(_, low)| *low).min()?;
+
+        // a flat range has no position within it, call it the middle.
+        let k = ((candle.close_price - lowest) * hundred())
+            .checked_div(highest - lowest)
+            .unwrap_or_else(|| Decimal::new(50, 0));
+
+        self.d.next_price(k).map(|d| StochasticOutput { k, d })
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.d.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::candle;
+
+    #[test]
+    fn rsi_with_wilders_smoothing() {
+        let mut rsi = Rsi::new(2);
+
+        assert_eq!(rsi.next_price(Decimal::new(1, 0)), None);
+        assert_eq!(rsi.next_price(Decimal::new(2, 0)), None);
+        // two gains and no losses.
+        assert_eq!(rsi.next_price(Decimal::new(3, 0)), Some(hundred()));
+        // gains and losses both average 0.5.
+        assert_eq!(
+            rsi.next_price(Decimal::new(2, 0)),
+            Some(Decimal::new(50, 0))
+        );
+    }
+
+    #[test]
+    fn rsi_of_a_flat_series_is_neutral() {
+        let mut rsi = Rsi::new(2);
+        let values: Vec<Option<Decimal>> = (0..3).map(|_| rsi.next_price(Decimal::ONE)).collect();
+
+        assert_eq!(values, vec![None, None, Some(Decimal::new(50, 0))]);
+    }
+
+    #[test]
+    fn macd_waits_for_the_slow_and_signal_averages() {
+        let mut macd = Macd::new(2, 3, 2);
+        let values: Vec<Option<MacdOutput>> =
+            (1..=5).map(|p| macd.next_price(Decimal::from(p))).collect();
+
+        assert_eq!(values[..3], [None, None, None]);
+
+        // on a steady rise the fast average leads the slow one by a constant half.
+        let half = Decimal::new(5, 1);
+        for output in values[3..].iter() {
+            let output = output.expect("warmed up");
+            assert_eq!(output.macd.round_dp(8), half);
+            assert_eq!(output.signal.round_dp(8), half);
+            assert_eq!(output.histogram.round_dp(8), Decimal::ZERO);
+        }
+    }
+
+    #[test]
+    fn stochastic_places_the_close_in_the_range() {
+        let mut stochastic = Stochastic::new(2, 2);
+        let candles = vec![
+            candle(10, 0, 5, 1),
+            candle(10, 0, 10, 1),
+            candle(20, 0, 0, 1),
+        ];
+
+        assert_eq!(
+            stochastic.calculate(&candles),
+            vec![
+                None,
+                None,
+                Some(StochasticOutput {
+                    k: Decimal::ZERO,
+                    d: Decimal::new(50, 0),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn stochastic_of_a_flat_range_is_the_middle() {
+        let mut stochastic = Stochastic::new(1, 1);
+
+        assert_eq!(
+            stochastic.next(&candle(5, 5, 5, 1)),
+            Some(StochasticOutput {
+                k: Decimal::new(50, 0),
+                d: Decimal::new(50, 0),
+            })
+        );
+    }
+}
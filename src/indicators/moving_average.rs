@@ -0,0 +1,214 @@
+use crate::indicators::Indicator;
+use crate::models::Candlestick;
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+
+/// Simple moving average of the close price.
+#[derive(Debug, Clone)]
+pub struct Sma {
+    period: usize,
+    window: VecDeque<Decimal>,
+    sum: Decimal,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        Sma {
+            period: period.max(1),
+            window: VecDeque::new(),
+            sum: Decimal::ZERO,
+        }
+    }
+
+    pub fn next_price(&mut self, price: Decimal) -> Option<Decimal> {
+        self.window.push_back(price);
+        self.sum += price;
+
+        if self.window.len() > self.period {
+            self.sum -= self.window.pop_front().unwrap_or(Decimal::ZERO);
+        }
+
+        if self.window.len() == self.period {
+            Some(self.sum / Decimal::from(self.period))
+        } else {
+            None
+        }
+    }
+}
+
+impl Indicator for Sma {
+    type Output = Decimal;
+
+    fn next(&mut self, candle: &Candlestick) -> Option<Decimal> {
+        self.next_price(candle.close_price)
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.sum = Decimal::ZERO;
+    }
+}
+
+/// Exponential moving average of the close price, seeded with the simple average of the first
+/// `period` prices.
+#[derive(Debug, Clone)]
+pub struct Ema {
+    period: usize,
+    alpha: Decimal,
+    seed: Sma,
+    value: Option<Decimal>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        let period = period.max(1);
+        Ema {
+            period,
+            alpha: Decimal::new(2, 0) / Decimal::from(period + 1),
+            seed: Sma::new(period),
+            value: None,
+        }
+    }
+
+    pub fn period(&self) -> usize {
+        self.period
+    }
+
+    pub fn next_price(&mut self, price: Decimal) -> Option<Decimal> {
+        self.value = match self.value {
+            Some(previous) => Some(previous + self.alpha * (price - previous)),
+            None => self.seed.next_price(price),
+        };
+        self.value
+    }
+}
+
+impl Indicator for Ema {
+    type Output = Decimal;
+
+    fn next(&mut self, candle: &Candlestick) -> Option<Decimal> {
+        self.next_price(candle.close_price)
+    }
+
+    fn reset(&mut self) {
+        self.seed.reset();
+        self.value = None;
+    }
+}
+
+/// Linearly weighted moving average of the close price, the newest price has the most weight.
+#[derive(Debug, Clone)]
+pub struct Wma {
+    period: usize,
+    window: VecDeque<Decimal>,
+}
+
+impl Wma {
+    pub fn new(period: usize) -> Self {
+        Wma {
+            period: period.max(1),
+            window: VecDeque::new(),
+        }
+    }
+
+    pub fn next_price(&mut self, price: Decimal) -> Option<Decimal> {
+        self.window.push_back(price);
+        if self.window.len() > self.period {
+            self.window.pop_front();
+        }
+
+        if self.window.len() < self.period {
+            return None;
+        }
+
+        let weighted: Decimal = self
+            .window
+            .iter()
+            .enumerate()
+            .map(|(i, price)| *price * Decimal::from(i + 1))
+            .sum();
+        let weights = Decimal::from(self.period * (self.period + 1) / 2);
+
+        Some(weighted / weights)
+    }
+}
+
+impl Indicator for Wma {
+    type Output = Decimal;
+
+    fn next(&mut self, candle: &Candlestick) -> Option<Decimal> {
+        self.next_price(candle.close_price)
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(next: impl FnMut(Decimal) -> Option<Decimal>, prices: &[i64]) -> Vec<Option<Decimal>> {
+        prices.iter().map(|p| Decimal::from(*p)).map(next).collect()
+    }
+
+    #[test]
+    fn sma_averages_the_window() {
+        let mut sma = Sma::new(3);
+
+        assert_eq!(
+            feed(|p| sma.next_price(p), &[1, 2, 3, 4]),
+            vec![
+                None,
+                None,
+                Some(Decimal::new(2, 0)),
+                Some(Decimal::new(3, 0))
+            ]
+        );
+    }
+
+    #[test]
+    fn ema_is_seeded_with_the_sma() {
+        // a period of 3 weighs every new price by a half.
+        let mut ema = Ema::new(3);
+
+        assert_eq!(
+            feed(|p| ema.next_price(p), &[1, 2, 3, 5, 1]),
+            vec![
+                None,
+                None,
+                Some(Decimal::new(2, 0)),
+                Some(Decimal::new(35, 1)),
+                Some(Decimal::new(225, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn wma_weighs_the_newest_most() {
+        let mut wma = Wma::new(3);
+        let six = Decimal::new(6, 0);
+
+        assert_eq!(
+            feed(|p| wma.next_price(p), &[1, 2, 3, 4]),
+            vec![
+                None,
+                None,
+                Some(Decimal::new(14, 0) / six),
+                Some(Decimal::new(20, 0) / six),
+            ]
+        );
+    }
+
+    #[test]
+    fn reset_forgets_the_window() {
+        let mut ema = Ema::new(2);
+        ema.next_price(Decimal::new(100, 0));
+        ema.next_price(Decimal::new(100, 0));
+        ema.reset();
+
+        assert_eq!(ema.next_price(Decimal::ONE), None);
+        assert_eq!(ema.next_price(Decimal::new(3, 0)), Some(Decimal::new(2, 0)));
+    }
+}
@@ -0,0 +1,191 @@
+use crate::indicators::{sqrt, Indicator};
+use crate::models::Candlestick;
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BollingerOutput {
+    pub upper: Decimal,
+    pub middle: Decimal,
+    pub lower: Decimal,
+}
+
+impl BollingerOutput {
+    /// distance between the bands relative to the middle band.
+    pub fn bandwidth(&self) -> Decimal {
+        (self.upper - self.lower)
+            .checked_div(self.middle)
+            .unwrap_or(Decimal::ZERO)
+    }
+}
+
+/// Bollinger bands: a simple moving average of the close price, with bands `multiplier`
+/// standard deviations above and below. Commonly `BollingerBands::new(20, Decimal::new(2, 0))`.
+#[derive(Debug, Clone)]
+pub struct BollingerBands {
+    period: usize,
+    multiplier: Decimal,
+    window: VecDeque<Decimal>,
+}
+
+impl BollingerBands {
+    pub fn new(period: usize, multiplier: Decimal) -> Self {
+        BollingerBands {
+            period: period.max(1),
+            multiplier,
+            window: VecDeque::new(),
+        }
+    }
+
+    pub fn next_price(&mut self, price: Decimal) -> Option<BollingerOutput> {
+        self.window.push_back(price);
+        if self.window.len() > self.period {
+            self.window.pop_front();
+        }
+        if self.window.len() < self.period {
+            return None;
+        }
+
+        let count = Decimal::from(self.period);
+        let middle = self.window.iter().sum::<Decimal>() / count;
+        let variance = self
+            .window
+            .iter()
+            .map(|price| (*price - middle) * (*price - middle))
+            .sum::<Decimal>()
+            / count;
+        let width = sqrt(variance) * self.multiplier;
+
+        Some(BollingerOutput {
+            upper: middle + width,
+            middle,
+            lower: middle - width,
+        })
+    }
+}
+
+impl Default for BollingerBands {
+    fn default() -> Self {
+        BollingerBands::new(20, Decimal::new(2, 0))
+    }
+}
+
+impl Indicator for BollingerBands {
+    type Output = BollingerOutput;
+
+    fn next(&mut self, candle: &Candlestick) -> Option<BollingerOutput> {
+        self.next_price(candle.close_price)
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// Average true range with Wilder's smoothing, commonly `Atr::new(14)`.
+#[derive(Debug, Clone)]
+pub struct Atr {
+    period: usize,
+    previous_close: Option<Decimal>,
+    seen: usize,
+    value: Decimal,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        Atr {
+            period: period.max(1),
+            previous_close: None,
+            seen: 0,
+            value: Decimal::ZERO,
+        }
+    }
+}
+
+/// the largest of the candle's range and its gaps from the previous close.
+pub fn true_range(candle: &Candlestick, previous_close: Option<Decimal>) -> Decimal {
+    let range = candle.high_price - candle.low_price;
+
+    match previous_close {
+        Some(close) => range
+            .max((candle.high_price - close).abs())
+            .max((candle.low_price - close).abs()),
+        None => range,
+    }
+}
+
+impl Indicator for Atr {
+    type Output = Decimal;
+
+    fn next(&mut self, candle: &Candlestick) -> Option<Decimal> {
+        let range = true_range(candle, self.previous_close);
+        self.previous_close = Some(candle.close_price);
+
+        let period = Decimal::from(self.period);
+        self.seen += 1;
+
+        if self.seen <= self.period {
+            self.value += range / period;
+            if self.seen < self.period {
+                return None;
+            }
+        } else {
+            self.value = (self.value * (period - Decimal::ONE) + range) / period;
+        }
+
+        Some(self.value)
+    }
+
+    fn reset(&mut self) {
+        *self = Atr::new(self.period);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::candle;
+
+    #[test]
+    fn bollinger_bands_are_standard_deviations_apart() {
+        let mut bands = BollingerBands::new(2, Decimal::new(2, 0));
+
+        assert_eq!(bands.next_price(Decimal::ONE), None);
+
+        let output = bands.next_price(Decimal::new(3, 0)).expect("warmed up");
+        assert_eq!(output.middle, Decimal::new(2, 0));
+        assert_eq!(output.upper, Decimal::new(4, 0));
+        assert_eq!(output.lower, Decimal::ZERO);
+        assert_eq!(output.bandwidth(), Decimal::new(2, 0));
+    }
+
+    #[test]
+    fn true_range_counts_gaps() {
+        let gap_up = candle(15, 14, 15, 1);
+
+        assert_eq!(true_range(&gap_up, None), Decimal::ONE);
+        assert_eq!(
+            true_range(&gap_up, Some(Decimal::new(10, 0))),
+            Decimal::new(5, 0)
+        );
+        assert_eq!(
+            true_range(&gap_up, Some(Decimal::new(20, 0))),
+            Decimal::new(6, 0)
+        );
+    }
+
+    #[test]
+    fn atr_with_wilders_smoothing() {
+        let mut atr = Atr::new(2);
+        let candles = vec![
+            candle(10, 8, 9, 1),
+            candle(12, 9, 11, 1),
+            candle(11, 10, 10, 1),
+        ];
+
+        assert_eq!(
+            atr.calculate(&candles),
+            vec![None, Some(Decimal::new(25, 1)), Some(Decimal::new(175, 2))]
+        );
+    }
+}
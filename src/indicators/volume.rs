@@ -0,0 +1,139 @@
+use crate::indicators::Indicator;
+use crate::models::Candlestick;
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+
+/// On balance volume: volume added on up closes and subtracted on down closes.
+#[derive(Debug, Clone, Default)]
+pub struct Obv {
+    previous_close: Option<Decimal>,
+    value: Decimal,
+}
+
+impl Obv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Indicator for Obv {
+    type Output = Decimal;
+
+    fn next(&mut self, candle: &Candlestick) -> Option<Decimal> {
+        if let Some(previous) = self.previous_close {
+            if candle.close_price > previous {
+                self.value += candle.volume;
+            } else if candle.close_price < previous {
+                self.value -= candle.volume;
+            }
+        }
+
+        self.previous_close = Some(candle.close_price);
+        Some(self.value)
+    }
+
+    fn reset(&mut self) {
+        *self = Obv::default();
+    }
+}
+
+/// Volume weighted average of the typical price `(high + low + close) / 3`. Cumulative since the
+/// last reset (eg. per session) by default, or over the last `period` candles with `rolling`.
+#[derive(Debug, Clone, Default)]
+pub struct Vwap {
+    period: Option<usize>,
+    window: VecDeque<(Decimal, Decimal)>,
+    price_volume: Decimal,
+    volume: Decimal,
+}
+
+impl Vwap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn rolling(period: usize) -> Self {
+        Vwap {
+            period: Some(period.max(1)),
+            ..Self::default()
+        }
+    }
+}
+
+impl Indicator for Vwap {
+    type Output = Decimal;
+
+    fn next(&mut self, candle: &Candlestick) -> Option<Decimal> {
+        let typical =
+            (candle.high_price + candle.low_price + candle.close_price) / Decimal::new(3, 0);
+        let price_volume = typical * candle.volume;
+
+        self.price_volume += price_volume;
+        self.volume += candle.volume;
+
+        if let Some(period) = self.period {
+            self.window.push_back((price_volume, candle.volume));
+            if self.window.len() > period {
+                if let Some((old_price_volume, old_volume)) = self.window.pop_front() {
+                    self.price_volume -= old_price_volume;
+                    self.volume -= old_volume;
+                }
+            }
+        }
+
+        self.price_volume.checked_div(self.volume)
+    }
+
+    fn reset(&mut self) {
+        *self = Vwap {
+            period: self.period,
+            ..Self::default()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::candle;
+
+    #[test]
+    fn obv_follows_the_close() {
+        let mut obv = Obv::new();
+        let candles = vec![
+            candle(10, 10, 10, 1),
+            candle(11, 11, 11, 2),
+            candle(9, 9, 9, 3),
+            candle(9, 9, 9, 4),
+        ];
+
+        assert_eq!(
+            obv.calculate(&candles),
+            vec![
+                Some(Decimal::ZERO),
+                Some(Decimal::new(2, 0)),
+                Some(Decimal::new(-1, 0)),
+                Some(Decimal::new(-1, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn vwap_weighs_by_volume() {
+        let candles = vec![candle(10, 10, 10, 1), candle(20, 20, 20, 3)];
+
+        assert_eq!(
+            Vwap::new().calculate(&candles),
+            vec![Some(Decimal::new(10, 0)), Some(Decimal::new(175, 1))]
+        );
+        assert_eq!(
+            Vwap::rolling(1).calculate(&candles),
+            vec![Some(Decimal::new(10, 0)), Some(Decimal::new(20, 0))]
+        );
+    }
+
+    #[test]
+    fn vwap_without_volume_has_no_value() {
+        assert_eq!(Vwap::new().next(&candle(10, 10, 10, 0)), None);
+    }
+}
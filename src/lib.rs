@@ -1,13 +1,18 @@
 pub mod config;
 pub mod error;
 pub mod exchanges;
-pub(crate) mod utils;
+pub mod store;
+pub mod utils;
 
 pub mod models {
     mod asset;
     pub use self::asset::*;
     mod candlestick;
     pub use self::candlestick::*;
+    mod interval;
+    pub use self::interval::*;
+    mod resample;
+    pub use self::resample::*;
     mod order;
     pub use self::order::*;
     mod trade;
@@ -16,10 +21,20 @@ pub mod models {
     pub use self::trade_type::*;
     mod position;
     pub use self::position::*;
+    mod ledger;
+    pub use self::ledger::*;
     mod pair;
     pub use self::pair::*;
+    mod instrument;
+    pub use self::instrument::*;
     mod price;
     pub use self::price::*;
+    mod conversion;
+    pub use self::conversion::*;
+    mod price_history;
+    pub use self::price_history::*;
+    mod snapshot;
+    pub use self::snapshot::*;
     mod book_ticker;
     pub use self::book_ticker::*;
     mod depth;
@@ -30,10 +45,93 @@ pub mod presenters {
     mod asset;
     mod balance;
     mod order;
+    mod portfolio;
     mod position;
     mod trade_presenter;
 
-    pub use self::{asset::*, balance::*, order::*, position::*, trade_presenter::*};
+    pub use self::{asset::*, balance::*, order::*, portfolio::*, position::*, trade_presenter::*};
+}
+
+pub mod indicators {
+    mod indicator;
+    pub use self::indicator::*;
+    mod moving_average;
+    pub use self::moving_average::*;
+    mod momentum;
+    pub use self::momentum::*;
+    mod volatility;
+    pub use self::volatility::*;
+    mod volume;
+    pub use self::volume::*;
+}
+
+pub mod strategies {
+    mod strategy;
+    pub use self::strategy::*;
+    mod feed;
+    pub use self::feed::*;
+    mod runner;
+    pub use self::runner::*;
+}
+
+pub mod triggers {
+    mod trigger;
+    pub use self::trigger::*;
+    mod engine;
+    pub use self::engine::*;
+}
+
+pub mod execution {
+    mod algo;
+    pub use self::algo::*;
+    mod executor;
+    pub use self::executor::*;
+}
+
+pub mod arbitrage {
+    mod scanner;
+    pub use self::scanner::*;
+    mod triangular;
+    pub use self::triangular::*;
+}
+
+pub mod rebalance {
+    mod allocation;
+    pub use self::allocation::*;
+    mod rebalancer;
+    pub use self::rebalancer::*;
+}
+
+pub mod risk {
+    mod limits;
+    pub use self::limits::*;
+    mod guard;
+    pub use self::guard::*;
+}
+
+pub mod alerts {
+    mod rule;
+    pub use self::rule::*;
+    mod sink;
+    pub use self::sink::*;
+    mod engine;
+    pub use self::engine::*;
+    mod watchlist;
+    pub use self::watchlist::*;
+}
+
+pub mod backtest {
+    mod backtester;
+    pub use self::backtester::*;
+    mod statistics;
+    pub use self::statistics::*;
+}
+
+pub mod reports {
+    mod tax;
+    pub use self::tax::*;
+    mod positions;
+    pub use self::positions::*;
 }
 
 pub static KNOWN_STABLECOIN_SYMBOLS: [&str; 3] = ["USDT", "USD", "TUSD"];
@@ -1,9 +1,450 @@
+use cryptotrader::config;
+use cryptotrader::error::*;
+use cryptotrader::exchanges::{exchange_api, ExchangeAPI};
+use cryptotrader::models::*;
+use cryptotrader::presenters::{BalancePresenter, PositionPresenter};
+use cryptotrader::reports::PositionReport;
+use rust_decimal::Decimal;
+use std::io::{self, Write};
+use std::process;
+use std::str::FromStr;
+use structopt::StructOpt;
 
-use okex::client::Client;
+/// Balances, prices and orders on the exchanges in the config file: ./.config.toml,
+/// ~/.config.toml or ~/.crypto/.config.toml, whichever is found first.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "cryptotrader")]
+struct Opt {
+    /// Only use this exchange from the config, eg. binance
+    #[structopt(short, long, global = true)]
+    exchange: Option<String>,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Balances on each exchange, valued in USDT and BTC, and the total
+    Balances,
+    /// Last price and best bid and ask of a pair, eg. BTC/USDT or the exchange's BTCUSDT
+    Prices { pair: String },
+    /// Order book of a pair
+    Depth {
+        pair: String,
+        /// Price levels shown on each side
+        #[structopt(short, long, default_value = "10")]
+        levels: usize,
+    },
+    /// Open orders, and past orders where the exchange lists them
+    Orders {
+        /// Also list past orders
+        #[structopt(long)]
+        past: bool,
+    },
+    /// Your trades in a pair
+    Trades { pair: String },
+    /// Held assets rebuilt from their trades, with entry price and profit and loss
+    Positions {
+        /// table, json or csv
+        #[structopt(short, long, default_value = "table")]
+        format: Format,
+        /// How sells are matched against buys: fifo, lifo, hifo or average
+        #[structopt(short, long, default_value = "fifo")]
+        method: CostBasisMethod,
+    },
+    /// Places a limit buy order
+    Buy(OrderArgs),
+    /// Places a limit sell order
+    Sell(OrderArgs),
+    /// Cancels an open order
+    Cancel { pair: String, order_id: String },
+}
+
+#[derive(Debug)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = TrailerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(TrailerError::MissingArgumentError(format!(
+                "unknown format: {}, expected table, json or csv",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct OrderArgs {
+    /// eg. BTC/USDT or the exchange's BTCUSDT
+    pair: String,
+    /// Quantity of the pair's base asset, eg. BTC
+    qty: Decimal,
+    /// Limit price in the pair's quote asset
+    price: Decimal,
+    /// Place the order without asking first
+    #[structopt(short, long)]
+    yes: bool,
+}
 
 fn main() {
-  
+    let opt = Opt::from_args();
+
+    if let Err(e) = run(opt) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
 }
 
+fn run(opt: Opt) -> CoreResult<()> {
+    let exchanges = exchanges(opt.exchange.as_ref().map(String::as_str))?;
+
+    match opt.command {
+        Command::Balances => balances(&exchanges),
+        Command::Prices { pair } => prices(&exchanges, &pair),
+        Command::Depth { pair, levels } => depth(&exchanges, &pair, levels),
+        Command::Orders { past } => orders(&exchanges, past),
+        Command::Trades { pair } => trades(&exchanges, &pair),
+        Command::Positions { format, method } => positions(&exchanges, format, method),
+        Command::Buy(args) => place(single(&exchanges)?, TradeType::Buy, &args),
+        Command::Sell(args) => place(single(&exchanges)?, TradeType::Sell, &args),
+        Command::Cancel { pair, order_id } => {
+            let exchange = single(&exchanges)?;
+            let (symbol, _) = market(exchange, &pair)?;
+            exchange.cancel_order(&symbol, &order_id)?;
+            println!("cancelled {} on {}", order_id, exchange.display());
+            Ok(())
+        }
+    }
+}
 
+/// every exchange in the config, or just `only`.
+fn exchanges(only: Option<&str>) -> CoreResult<Vec<Box<dyn ExchangeAPI>>> {
+    let config = config::read()?;
 
+    let exchanges: Vec<Box<dyn ExchangeAPI>> = config
+        .exchange
+        .iter()
+        .filter(|(name, _)| only.map_or(true, |only| only.eq_ignore_ascii_case(name)))
+        .map(|(name, api_config)| exchange_api(name, api_config))
+        .collect::<CoreResult<_>>()?;
+
+    if exchanges.is_empty() {
+        return Err(Box::new(TrailerError::ConfigError(match only {
+            Some(name) => format!("no exchange named {} in the config", name),
+            None => "no exchanges in the config".to_string(),
+        })));
+    }
+    Ok(exchanges)
+}
+
+/// the one exchange orders go to, `--exchange` picks it when there are several.
+fn single(exchanges: &[Box<dyn ExchangeAPI>]) -> CoreResult<&dyn ExchangeAPI> {
+    match exchanges {
+        [exchange] => Ok(exchange.as_ref()),
+        _ => Err(Box::new(TrailerError::MissingArgumentError(
+            "several exchanges are configured, pick one with --exchange".to_string(),
+        ))),
+    }
+}
+
+/// A pair written `BTC/USDT`, `BTC-USDT` or `BTC_USDT`, or the exchange's own symbol, eg.
+/// `BTCUSDT`. Returns the native symbol and the pair.
+fn market(exchange: &dyn ExchangeAPI, pair: &str) -> CoreResult<(String, Pair)> {
+    let pair = pair.to_uppercase();
+
+    match pair.find(|c| c == '/' || c == '-' || c == '_') {
+        Some(at) => {
+            let pair = Pair::new(&pair[..at], &pair[at + 1..]);
+            Ok((exchange.native_symbol(&pair)?, pair))
+        }
+        None => Ok((pair.clone(), exchange.pair(&pair)?)),
+    }
+}
+
+fn balances(exchanges: &[Box<dyn ExchangeAPI>]) -> CoreResult<()> {
+    let mut total_usd = Decimal::ZERO;
+    let mut total_btc = Decimal::ZERO;
+
+    for exchange in exchanges {
+        let presenter = match exchange
+            .balances()
+            .and_then(|assets| Ok((assets, exchange.all_prices()?)))
+        {
+            Ok((assets, prices)) => BalancePresenter {
+                assets: assets.filter_zero_balances(),
+                prices,
+            },
+            Err(e) => {
+                eprintln!("{}: {}", exchange.display(), e);
+                continue;
+            }
+        };
+
+        println!("{}", exchange.display());
+        println!(
+            "  {:<10} {:>18} {:>18} {:>14}",
+            "asset", "amount", "locked", "USDT"
+        );
+        for (asset, conversion) in presenter.valuations_in("USDT") {
+            let value = match asset.asset_type() {
+                AssetType::Stablecoin => Some(asset.amount),
+                _ => conversion.map(|c| c.convert(asset.amount)),
+            };
+            println!(
+                "  {:<10} {:>18} {:>18} {:>14}",
+                asset.symbol,
+                asset.amount,
+                asset.locked,
+                value.map_or("-".to_string(), |v| v.round_dp(2).to_string())
+            );
+        }
+
+        let (usd, btc) = (
+            presenter.total_value_in_usd(),
+            presenter.total_value_in_btc(),
+        );
+        println!(
+            "  total {} USDT, {} BTC\n",
+            usd.round_dp(2),
+            btc.round_dp(8)
+        );
+        total_usd += usd;
+        total_btc += btc;
+    }
+
+    if exchanges.len() > 1 {
+        println!(
+            "total {} USDT, {} BTC",
+            total_usd.round_dp(2),
+            total_btc.round_dp(8)
+        );
+    }
+    Ok(())
+}
+
+fn prices(exchanges: &[Box<dyn ExchangeAPI>], pair: &str) -> CoreResult<()> {
+    for exchange in exchanges {
+        let (symbol, pair) = match market(exchange.as_ref(), pair) {
+            Ok(market) => market,
+            Err(e) => {
+                eprintln!("{}: {}", exchange.display(), e);
+                continue;
+            }
+        };
+
+        let last = match exchange.all_prices() {
+            Ok(prices) => prices
+                .into_iter()
+                .find(|price| price.pair == pair)
+                .map(|price| price.price),
+            Err(e) => {
+                eprintln!("{}: {}", exchange.display(), e);
+                continue;
+            }
+        };
+        let book = exchange
+            .book_tickers()
+            .ok()
+            .and_then(|tickers| tickers.into_iter().find(|ticker| ticker.pair == pair));
+
+        println!(
+            "{:<10} {:<12} last {:>14} bid {:>14} ask {:>14}",
+            exchange.display(),
+            symbol,
+            last.map_or("-".to_string(), |p| p.to_string()),
+            book.as_ref()
+                .map_or("-".to_string(), |b| b.bid_price.to_string()),
+            book.as_ref()
+                .map_or("-".to_string(), |b| b.ask_price.to_string())
+        );
+    }
+    Ok(())
+}
+
+fn depth(exchanges: &[Box<dyn ExchangeAPI>], pair: &str, levels: usize) -> CoreResult<()> {
+    for exchange in exchanges {
+        let (symbol, _) = match market(exchange.as_ref(), pair) {
+            Ok(market) => market,
+            Err(e) => {
+                eprintln!("{}: {}", exchange.display(), e);
+                continue;
+            }
+        };
+        let depth = match exchange.market_depth(&symbol) {
+            Ok(depth) => depth,
+            Err(e) => {
+                eprintln!("{}: {}", exchange.display(), e);
+                continue;
+            }
+        };
+
+        println!("{} {}", exchange.display(), symbol);
+        println!("  {:>18} {:>18}", "price", "qty");
+        for ask in depth.asks.iter().take(levels).rev() {
+            println!("  {:>18} {:>18}  ask", ask.price, ask.qty);
+        }
+        match (depth.spread(), depth.spread_bps()) {
+            (Some(spread), Some(bps)) => {
+                println!("  spread {} ({}bps)", spread, bps.round_dp(2))
+            }
+            _ => println!("  one sided book"),
+        }
+        for bid in depth.bids.iter().take(levels) {
+            println!("  {:>18} {:>18}  bid", bid.price, bid.qty);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+fn orders(exchanges: &[Box<dyn ExchangeAPI>], past: bool) -> CoreResult<()> {
+    fn print(orders: &[Order]) {
+        for order in orders {
+            println!(
+                "  {} {:<14} {:<10} {:<4} {:<10} {:>14} {:>14} {:>14}",
+                order.time.format("%Y-%m-%d %H:%M"),
+                order.id,
+                order.pair,
+                order.trade_type,
+                order.order_type,
+                order.qty,
+                order.executed_qty,
+                order.purchase_price
+            );
+        }
+    }
+
+    for exchange in exchanges {
+        println!("{} open orders", exchange.display());
+        match exchange.open_orders() {
+            Ok(open) => print(&open),
+            Err(e) => eprintln!("  {}", e),
+        }
+
+        if past {
+            println!("{} past orders", exchange.display());
+            match exchange.past_orders() {
+                Ok(past) => print(&past),
+                Err(e) => eprintln!("  {}", e),
+            }
+        }
+        println!();
+    }
+    Ok(())
+}
+
+fn trades(exchanges: &[Box<dyn ExchangeAPI>], pair: &str) -> CoreResult<()> {
+    for exchange in exchanges {
+        let (_, pair) = match market(exchange.as_ref(), pair) {
+            Ok(market) => market,
+            Err(e) => {
+                eprintln!("{}: {}", exchange.display(), e);
+                continue;
+            }
+        };
+
+        let trades = match exchange.trades_for_pair(pair.clone()) {
+            Ok(trades) => trades,
+            Err(e) => {
+                eprintln!("{}: {}", exchange.display(), e);
+                continue;
+            }
+        };
+
+        println!("{} {}", exchange.display(), pair);
+        for trade in trades {
+            println!(
+                "  {} {:<4} {:>14} at {:>14} fee {} {}",
+                trade.time.format("%Y-%m-%d %H:%M"),
+                trade.trade_type,
+                trade.qty,
+                trade.sale_price,
+                trade.fee,
+                trade.fee_symbol.unwrap_or_default()
+            );
+        }
+        println!();
+    }
+    Ok(())
+}
+
+fn positions(
+    exchanges: &[Box<dyn ExchangeAPI>],
+    format: Format,
+    method: CostBasisMethod,
+) -> CoreResult<()> {
+    let mut positions = Vec::new();
+    for exchange in exchanges {
+        match PositionPresenter::for_exchange(exchange.as_ref(), method) {
+            Ok(mut found) => positions.append(&mut found),
+            Err(e) => eprintln!("{}: {}", exchange.display(), e),
+        }
+    }
+
+    let report = PositionReport::new(&positions);
+    match format {
+        Format::Table => println!("{}", report),
+        Format::Json => println!("{}", report.to_json()?),
+        Format::Csv => report.write_csv(io::stdout())?,
+    }
+    Ok(())
+}
+
+/// Checks the order against the exchange's lot and notional rules, asks for confirmation unless
+/// `--yes` was given, and places it.
+fn place(exchange: &dyn ExchangeAPI, side: TradeType, args: &OrderArgs) -> CoreResult<()> {
+    let (symbol, pair) = market(exchange, &args.pair)?;
+
+    if let Some(problem) = exchange
+        .instruments()?
+        .rules(&symbol)
+        .and_then(|rules| rules.check(args.qty, args.price))
+    {
+        return Err(Box::new(TrailerError::MissingArgumentError(problem)));
+    }
+
+    let summary = format!(
+        "{} {} {} at {} on {} ({} {})",
+        side,
+        args.qty,
+        pair.symbol,
+        args.price,
+        exchange.display(),
+        (args.qty * args.price).round_dp(8),
+        pair.base
+    );
+    if !args.yes && !confirm(&format!("{}?", summary))? {
+        println!("not placed");
+        return Ok(());
+    }
+
+    match side {
+        TradeType::Buy => exchange.limit_buy(&symbol, args.qty, args.price)?,
+        TradeType::Sell => exchange.limit_sell(&symbol, args.qty, args.price)?,
+    }
+    println!("placed {}", summary);
+    Ok(())
+}
+
+fn confirm(question: &str) -> CoreResult<bool> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => true,
+        _ => false,
+    })
+}
@@ -1,11 +1,12 @@
 use crate::exchanges::*;
 use crate::models::pair::AssetType;
+use rust_decimal::Decimal;
 
 #[derive(Debug, Clone)]
 pub struct Asset {
     pub symbol: String,
-    pub amount: f64,
-    pub locked: f64,
+    pub amount: Decimal,
+    pub locked: Decimal,
     pub exchange: Exchange,
 }
 
@@ -24,15 +25,15 @@ impl Display for Asset {
 
 pub trait AssetExtensions {
     fn filter_zero_balances(&self) -> Self;
-    fn filter_small_balances(&self, min: f64) -> Self;
+    fn filter_small_balances(&self, min: Decimal) -> Self;
 }
 
 impl AssetExtensions for Vec<Asset> {
     fn filter_zero_balances(&self) -> Self {
-        self.filter_small_balances(0.0)
+        self.filter_small_balances(Decimal::ZERO)
     }
 
-    fn filter_small_balances(&self, min: f64) -> Self {
+    fn filter_small_balances(&self, min: Decimal) -> Self {
         self.into_iter()
             .filter(|asset| asset.amount > min)
             .map(|a| a.clone())
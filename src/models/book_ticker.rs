@@ -1,11 +1,13 @@
 use crate::models::pair::Pair;
+use rust_decimal::Decimal;
+
 #[derive(Debug, Clone)]
 pub struct BookTicker {
     pub pair: Pair,
-    pub bid_price: f64,
-    pub bid_qty: f64,
-    pub ask_price: f64,
-    pub ask_qty: f64,
+    pub bid_price: Decimal,
+    pub bid_qty: Decimal,
+    pub ask_price: Decimal,
+    pub ask_qty: Decimal,
 }
 
 impl BookTicker {
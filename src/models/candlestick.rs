@@ -1,10 +1,35 @@
+use crate::utils::*;
+use chrono::prelude::*;
+use rust_decimal::Decimal;
+
 #[derive(Debug, Clone)]
 pub struct Candlestick {
-    pub open_time: i32,
-    pub open_price: f64,
-    pub close_price: f64,
-    pub high_price: f64,
-    pub low_price: f64,
-    pub volume: f64,
+    /// unix time in milliseconds.
+    pub open_time: u64,
+    /// unix time in milliseconds of the last moment covered, eg. `open_time + 59_999` for `1m`.
+    pub close_time: u64,
+    pub open_price: Decimal,
+    pub close_price: Decimal,
+    pub high_price: Decimal,
+    pub low_price: Decimal,
+    /// volume in the base asset.
+    pub volume: Decimal,
+    /// volume in the quote asset, when the exchange reports it.
+    pub quote_volume: Option<Decimal>,
     pub number_of_trades: u64,
 }
+
+impl Candlestick {
+    pub fn open_datetime(&self) -> DateTime<Local> {
+        local_datetime_from_unix(self.open_time)
+    }
+
+    pub fn close_datetime(&self) -> DateTime<Local> {
+        local_datetime_from_unix(self.close_time)
+    }
+
+    /// whether `time` (unix millis) falls within the candle.
+    pub fn contains(&self, time: u64) -> bool {
+        time >= self.open_time && time <= self.close_time
+    }
+}
@@ -0,0 +1,292 @@
+use crate::models::*;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+
+/// Longest chain of markets a conversion is allowed to go through by default.
+pub static MAX_CONVERSION_HOPS: usize = 3;
+
+/// The result of converting between two assets, with the route taken, eg. `XYZ -> ETH -> USDT`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conversion {
+    pub from: String,
+    pub to: String,
+    /// units of `to` received for one unit of `from`.
+    pub rate: Decimal,
+    /// every asset visited, starting with `from` and ending with `to`.
+    pub path: Vec<String>,
+}
+
+impl Conversion {
+    pub fn convert(&self, amount: Decimal) -> Decimal {
+        amount * self.rate
+    }
+
+    pub fn hops(&self) -> usize {
+        self.path.len().saturating_sub(1)
+    }
+
+    pub fn is_direct(&self) -> bool {
+        self.hops() <= 1
+    }
+
+    pub fn path_display(&self) -> String {
+        self.path.join(" -> ")
+    }
+}
+
+/// Assets connected by the markets between them. Every market `SYMBOL_BASE` at price `p` adds an
+/// edge `SYMBOL -> BASE` at rate `p` and the inverse `BASE -> SYMBOL` at rate `1 / p`.
+#[derive(Debug, Clone)]
+pub struct ConversionGraph {
+    edges: BTreeMap<String, BTreeMap<String, Decimal>>,
+    max_hops: usize,
+}
+
+impl Default for ConversionGraph {
+    fn default() -> Self {
+        Self {
+            edges: BTreeMap::new(),
+            max_hops: MAX_CONVERSION_HOPS,
+        }
+    }
+}
+
+impl ConversionGraph {
+    pub fn from_prices(prices: &[Price]) -> Self {
+        let mut graph = Self::default();
+        for price in prices {
+            graph.add_market(&price.pair, price.price);
+        }
+        graph
+    }
+
+    /// builds the graph from the mid price of every book ticker, skipping one sided books.
+    pub fn from_book_tickers(tickers: &[BookTicker]) -> Self {
+        let mut graph = Self::default();
+        for ticker in tickers {
+            if ticker.bid_price <= Decimal::ZERO || ticker.ask_price <= Decimal::ZERO {
+                continue;
+            }
+            let mid = (ticker.bid_price + ticker.ask_price) / Decimal::new(2, 0);
+            graph.add_market(&ticker.pair, mid);
+        }
+        graph
+    }
+
+    pub fn max_hops(mut self, max_hops: usize) -> Self {
+        self.max_hops = max_hops;
+        self
+    }
+
+    /// markets without a usable price are skipped.
+    pub fn add_market(&mut self, pair: &Pair, price: Decimal) {
+        if price <= Decimal::ZERO {
+            return;
+        }
+
+        let symbol = pair.symbol.to_uppercase();
+        let base = pair.base.to_uppercase();
+
+        if let Some(inverse) = Decimal::ONE.checked_div(price) {
+            self.edges
+                .entry(base.clone())
+                .or_insert_with(BTreeMap::new)
+                .insert(symbol.clone(), inverse);
+        }
+
+        self.edges
+            .entry(symbol)
+            .or_insert_with(BTreeMap::new)
+            .insert(base, price);
+    }
+
+    pub fn contains(&self, asset: &str) -> bool {
+        self.edges.contains_key(&asset.to_uppercase())
+    }
+
+    /// Finds the route with the fewest markets between two assets. Every extra market costs a
+    /// spread and a fee, so among routes of the same length the one with the best rate wins.
+    pub fn conversion(&self, from: &str, to: &str) -> Option<Conversion> {
+        let from = from.to_uppercase();
+        let to = to.to_uppercase();
+
+        if from == to {
+            return Some(Conversion {
+                from: from.clone(),
+                to,
+                rate: Decimal::ONE,
+                path: vec![from],
+            });
+        }
+
+        // best (rate, path) for every asset reached so far, expanded one hop at a time.
+        let mut visited: HashMap<String, (Decimal, Vec<String>)> = HashMap::new();
+        visited.insert(from.clone(), (Decimal::ONE, vec![from.clone()]));
+        let mut frontier = vec![from.clone()];
+
+        for _ in 0..self.max_hops {
+            let mut next: BTreeMap<String, (Decimal, Vec<String>)> = BTreeMap::new();
+
+            for asset in &frontier {
+                let (rate, path) = visited[asset].clone();
+
+                for (neighbour, edge_rate) in self.edges.get(asset).into_iter().flatten() {
+                    if visited.contains_key(neighbour) {
+                        continue;
+                    }
+
+                    let candidate = rate * *edge_rate;
+                    let better = next
+                        .get(neighbour)
+                        .map_or(true, |(best, _)| candidate > *best);
+
+                    if better {
+                        let mut path = path.clone();
+                        path.push(neighbour.clone());
+                        next.insert(neighbour.clone(), (candidate, path));
+                    }
+                }
+            }
+
+            if next.is_empty() {
+                return None;
+            }
+
+            if let Some((rate, path)) = next.get(&to) {
+                return Some(Conversion {
+                    from,
+                    to: to.clone(),
+                    rate: *rate,
+                    path: path.clone(),
+                });
+            }
+
+            frontier = next.keys().cloned().collect();
+            visited.extend(next);
+        }
+
+        None
+    }
+
+    pub fn convert(&self, amount: Decimal, from: &str, to: &str) -> Option<Decimal> {
+        self.conversion(from, to).map(|c| c.convert(amount))
+    }
+
+    /// the price of `symbol` in `base`, going through other markets when there is no direct one.
+    pub fn price_of(&self, symbol: &str, base: &str) -> Option<Decimal> {
+        self.conversion(symbol, base).map(|c| c.rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn graph(markets: &[(&str, &str, &str)]) -> ConversionGraph {
+        let mut graph = ConversionGraph::default();
+        for (symbol, base, price) in markets {
+            graph.add_market(&Pair::new(symbol, base), dec(price));
+        }
+        graph
+    }
+
+    #[test]
+    fn direct_market_and_its_inverse() {
+        let graph = graph(&[("ETH", "USDT", "200")]);
+
+        let conversion = graph.conversion("eth", "usdt").unwrap();
+        assert_eq!(conversion.rate, dec("200"));
+        assert_eq!(conversion.path, vec!["ETH", "USDT"]);
+        assert!(conversion.is_direct());
+
+        let inverse = graph.conversion("USDT", "ETH").unwrap();
+        assert_eq!(inverse.rate, dec("0.005"));
+        assert_eq!(inverse.path, vec!["USDT", "ETH"]);
+        assert_eq!(graph.convert(dec("400"), "USDT", "ETH"), Some(dec("2")));
+    }
+
+    #[test]
+    fn same_asset_converts_at_par() {
+        let conversion = graph(&[]).conversion("BTC", "btc").unwrap();
+        assert_eq!(conversion.rate, Decimal::ONE);
+        assert_eq!(conversion.hops(), 0);
+    }
+
+    #[test]
+    fn two_hops_through_a_shared_market() {
+        let graph = graph(&[("XYZ", "ETH", "0.01"), ("ETH", "USDT", "200")]);
+
+        let conversion = graph.conversion("XYZ", "USDT").unwrap();
+        assert_eq!(conversion.rate, dec("2"));
+        assert_eq!(conversion.path, vec!["XYZ", "ETH", "USDT"]);
+        assert_eq!(conversion.path_display(), "XYZ -> ETH -> USDT");
+        assert_eq!(conversion.hops(), 2);
+        assert!(!conversion.is_direct());
+        assert_eq!(graph.price_of("USDT", "XYZ"), Some(dec("0.5")));
+    }
+
+    #[test]
+    fn fewest_hops_win_then_the_best_rate() {
+        let graph = graph(&[
+            ("XYZ", "ETH", "0.01"),
+            ("ETH", "USDT", "200"),
+            ("XYZ", "BTC", "0.0001"),
+            ("BTC", "USDT", "25000"),
+            ("XYZ", "BNB", "0.1"),
+            ("BNB", "USDT", "10"),
+            ("BNB", "ETH", "0.5"),
+        ]);
+
+        // ETH pays 2, BTC 2.5 and BNB 1 for the same two hops.
+        let conversion = graph.conversion("XYZ", "USDT").unwrap();
+        assert_eq!(conversion.path, vec!["XYZ", "BTC", "USDT"]);
+        assert_eq!(conversion.rate, dec("2.5"));
+
+        // a direct market beats any route through others, whatever its rate.
+        let mut direct = graph.clone();
+        direct.add_market(&Pair::new("XYZ", "USDT"), dec("1"));
+        let conversion = direct.conversion("XYZ", "USDT").unwrap();
+        assert_eq!(conversion.path, vec!["XYZ", "USDT"]);
+        assert_eq!(conversion.rate, dec("1"));
+    }
+
+    #[test]
+    fn unreachable_assets_have_no_conversion() {
+        let graph = graph(&[
+            ("XYZ", "ETH", "0.01"),
+            ("ETH", "BTC", "0.05"),
+            ("BTC", "USDT", "25000"),
+            ("ABC", "DEF", "3"),
+        ]);
+
+        assert_eq!(graph.conversion("XYZ", "DEF"), None);
+        assert_eq!(graph.conversion("NOPE", "USDT"), None);
+        assert_eq!(graph.convert(Decimal::ONE, "USDT", "ABC"), None);
+        // three hops are within the default limit, not within a tighter one.
+        assert!(graph.conversion("XYZ", "USDT").is_some());
+        assert_eq!(graph.max_hops(2).conversion("XYZ", "USDT"), None);
+    }
+
+    #[test]
+    fn books_and_markets_without_a_price_are_skipped() {
+        let ticker = |symbol: &str, bid: &str, ask: &str| BookTicker {
+            pair: Pair::new(symbol, "USDT"),
+            bid_price: dec(bid),
+            bid_qty: Decimal::ONE,
+            ask_price: dec(ask),
+            ask_qty: Decimal::ONE,
+        };
+        let books = ConversionGraph::from_book_tickers(&[
+            ticker("ETH", "199", "201"),
+            ticker("XYZ", "0", "2"),
+        ]);
+
+        assert_eq!(books.price_of("ETH", "USDT"), Some(dec("200")));
+        assert!(!books.contains("XYZ"));
+        assert!(!graph(&[("ABC", "USDT", "0")]).contains("ABC"));
+    }
+}
@@ -1,3 +1,8 @@
+use crate::models::*;
+use crate::utils::*;
+use rust_decimal::Decimal;
+
+/// An order book, best prices first: bids high to low and asks low to high.
 #[derive(Debug, Clone)]
 pub struct Depth {
     pub bids: Vec<Offer>,
@@ -6,6 +11,287 @@ pub struct Depth {
 
 #[derive(Debug, Clone)]
 pub struct Offer {
-    pub price: f64,
-    pub qty: f64,
+    pub price: Decimal,
+    pub qty: Decimal,
+}
+
+/// What a market order would get from the book.
+#[derive(Debug, Clone)]
+pub struct FillEstimate {
+    pub side: TradeType,
+    /// quantity filled, in the base asset.
+    pub qty: Decimal,
+    /// spent on a buy or received on a sell, in the quote asset.
+    pub notional: Decimal,
+    pub average_price: Decimal,
+    /// the last level reached.
+    pub worst_price: Decimal,
+    /// percent lost to walking the book compared to the best price, positive for a cost.
+    pub slippage: Decimal,
+    pub levels: usize,
+    /// false when the book ran out before the order was filled.
+    pub complete: bool,
+}
+
+/// Resting quantity near the mid price.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthBand {
+    pub bid_qty: Decimal,
+    pub bid_notional: Decimal,
+    pub ask_qty: Decimal,
+    pub ask_notional: Decimal,
+}
+
+impl Depth {
+    pub fn best_bid(&self) -> Option<&Offer> {
+        self.bids.first()
+    }
+
+    pub fn best_ask(&self) -> Option<&Offer> {
+        self.asks.first()
+    }
+
+    pub fn mid_price(&self) -> Option<Decimal> {
+        Some((self.best_bid()?.price + self.best_ask()?.price) / Decimal::new(2, 0))
+    }
+
+    pub fn spread(&self) -> Option<Decimal> {
+        Some(self.best_ask()?.price - self.best_bid()?.price)
+    }
+
+    /// spread relative to the mid price, in basis points.
+    pub fn spread_bps(&self) -> Option<Decimal> {
+        self.spread()?
+            .checked_div(self.mid_price()?)
+            .map(|spread| spread * Decimal::new(10_000, 0))
+    }
+
+    /// the levels a market order on `side` takes from: asks for a buy, bids for a sell.
+    pub fn side(&self, side: TradeType) -> &[Offer] {
+        match side {
+            TradeType::Buy => &self.asks,
+            TradeType::Sell => &self.bids,
+        }
+    }
+
+    /// Walks the book for a market order of `qty` in the base asset, `None` on an empty side.
+    pub fn fill_qty(&self, side: TradeType, qty: Decimal) -> Option<FillEstimate> {
+        self.walk(side, |offer, filled, _| (qty - filled).min(offer.qty))
+    }
+
+    /// Walks the book for a market order spending (or, selling, raising) `notional` in the quote
+    /// asset, `None` on an empty side.
+    pub fn fill_notional(&self, side: TradeType, notional: Decimal) -> Option<FillEstimate> {
+        self.walk(side, |offer, _, spent| {
+            (notional - spent)
+                .checked_div(offer.price)
+                .unwrap_or(Decimal::ZERO)
+                .min(offer.qty)
+        })
+    }
+
+    /// Quantity and notional resting within `percent` of the mid price on each side.
+    pub fn depth_within(&self, percent: Decimal) -> Option<DepthBand> {
+        let mid = self.mid_price()?;
+        let distance = mid * percent / Decimal::new(100, 0);
+
+        let sum = |offers: &[Offer], within: &dyn Fn(Decimal) -> bool| {
+            offers
+                .iter()
+                .filter(|offer| within(offer.price))
+                .fold((Decimal::ZERO, Decimal::ZERO), |(qty, notional), offer| {
+                    (qty + offer.qty, notional + offer.qty * offer.price)
+                })
+        };
+        let (bid_qty, bid_notional) = sum(&self.bids, &|price| price >= mid - distance);
+        let (ask_qty, ask_notional) = sum(&self.asks, &|price| price <= mid + distance);
+
+        Some(DepthBand {
+            bid_qty,
+            bid_notional,
+            ask_qty,
+            ask_notional,
+        })
+    }
+
+    /// `(bids - asks) / (bids + asks)` of the notional within `percent` of the mid price, from -1
+    /// (only asks) to 1 (only bids).
+    pub fn imbalance(&self, percent: Decimal) -> Option<Decimal> {
+        let band = self.depth_within(percent)?;
+        (band.bid_notional - band.ask_notional).checked_div(band.bid_notional + band.ask_notional)
+    }
+
+    /// `take` gives the quantity to take from a level, given the quantity and notional so far.
+    fn walk<F>(&self, side: TradeType, take: F) -> Option<FillEstimate>
+    where
+        F: Fn(&Offer, Decimal, Decimal) -> Decimal,
+    {
+        let offers = self.side(side);
+        let best = offers.first()?.price;
+
+        let mut qty = Decimal::ZERO;
+        let mut notional = Decimal::ZERO;
+        let mut worst_price = best;
+        let mut levels = 0;
+        let mut complete = false;
+
+        for offer in offers {
+            let taken = take(offer, qty, notional);
+            if taken <= Decimal::ZERO {
+                complete = true;
+                break;
+            }
+
+            qty += taken;
+            notional += taken * offer.price;
+            worst_price = offer.price;
+            levels += 1;
+
+            if taken < offer.qty {
+                complete = true;
+                break;
+            }
+        }
+        if !complete {
+            complete = take(&offers[offers.len() - 1], qty, notional) <= Decimal::ZERO;
+        }
+
+        let average_price = notional.checked_div(qty).unwrap_or(best);
+        let slippage = match side {
+            TradeType::Buy => price_percent(best, average_price),
+            TradeType::Sell => -price_percent(best, average_price),
+        };
+
+        Some(FillEstimate {
+            side,
+            qty,
+            notional,
+            average_price,
+            worst_price,
+            slippage,
+            levels,
+            complete,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn offers(levels: &[(&str, &str)]) -> Vec<Offer> {
+        levels
+            .iter()
+            .map(|(price, qty)| Offer {
+                price: dec(price),
+                qty: dec(qty),
+            })
+            .collect()
+    }
+
+    fn book() -> Depth {
+        Depth {
+            bids: offers(&[("99", "1"), ("98", "2"), ("95", "1")]),
+            asks: offers(&[("100", "1"), ("101", "2"), ("105", "1")]),
+        }
+    }
+
+    #[test]
+    fn buying_walks_the_asks() {
+        let fill = book().fill_qty(TradeType::Buy, dec("2")).unwrap();
+
+        assert_eq!(fill.qty, dec("2"));
+        assert_eq!(fill.notional, dec("201"));
+        assert_eq!(fill.average_price, dec("100.5"));
+        assert_eq!(fill.worst_price, dec("101"));
+        assert_eq!(fill.slippage, dec("0.5"));
+        assert_eq!(fill.levels, 2);
+        assert!(fill.complete);
+    }
+
+    #[test]
+    fn selling_walks_the_bids() {
+        let fill = book().fill_qty(TradeType::Sell, dec("2")).unwrap();
+
+        assert_eq!(fill.notional, dec("197"));
+        assert_eq!(fill.worst_price, dec("98"));
+        assert!(fill.slippage > Decimal::ZERO);
+        assert_eq!(fill.slippage.round_dp(4), dec("0.5051"));
+    }
+
+    #[test]
+    fn filling_a_level_exactly_is_complete() {
+        let fill = book().fill_qty(TradeType::Buy, dec("3")).unwrap();
+
+        assert_eq!(fill.levels, 2);
+        assert_eq!(fill.worst_price, dec("101"));
+        assert!(fill.complete);
+
+        let fill = book().fill_qty(TradeType::Buy, dec("4")).unwrap();
+        assert_eq!(fill.levels, 3);
+        assert!(fill.complete);
+    }
+
+    #[test]
+    fn running_out_of_book_is_incomplete() {
+        let fill = book().fill_qty(TradeType::Buy, dec("5")).unwrap();
+
+        assert_eq!(fill.qty, dec("4"));
+        assert_eq!(fill.notional, dec("407"));
+        assert_eq!(fill.worst_price, dec("105"));
+        assert!(!fill.complete);
+    }
+
+    #[test]
+    fn notional_is_spent_across_levels() {
+        let fill = book().fill_notional(TradeType::Buy, dec("201")).unwrap();
+
+        assert_eq!(fill.qty, dec("2"));
+        assert_eq!(fill.notional, dec("201"));
+        assert!(fill.complete);
+
+        let fill = book().fill_notional(TradeType::Buy, dec("100")).unwrap();
+        assert_eq!(fill.qty, dec("1"));
+        assert_eq!(fill.levels, 1);
+        assert!(fill.complete);
+
+        let fill = book().fill_notional(TradeType::Buy, dec("1000")).unwrap();
+        assert_eq!(fill.notional, dec("407"));
+        assert!(!fill.complete);
+    }
+
+    #[test]
+    fn an_empty_side_has_no_fill() {
+        let depth = Depth {
+            bids: Vec::new(),
+            asks: offers(&[("100", "1")]),
+        };
+
+        assert!(depth.fill_qty(TradeType::Sell, dec("1")).is_none());
+        assert!(depth.fill_notional(TradeType::Sell, dec("1")).is_none());
+        assert!(depth.mid_price().is_none());
+    }
+
+    #[test]
+    fn spread_and_depth_near_the_mid() {
+        let depth = book();
+
+        assert_eq!(depth.mid_price(), Some(dec("99.5")));
+        assert_eq!(depth.spread(), Some(dec("1")));
+        assert_eq!(depth.spread_bps().unwrap().round_dp(2), dec("100.50"));
+        assert_eq!(
+            depth.depth_within(dec("1")),
+            Some(DepthBand {
+                bid_qty: dec("1"),
+                bid_notional: dec("99"),
+                ask_qty: dec("1"),
+                ask_notional: dec("100"),
+            })
+        );
+        assert_eq!(depth.imbalance(dec("1")), Some(dec("-1") / dec("199")));
+    }
 }
@@ -0,0 +1,199 @@
+use crate::error::*;
+use crate::exchanges::Exchange;
+use crate::models::Pair;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::{fmt, fmt::Display};
+
+/// A market identified independently of how the exchange spells it, eg. `BTC`/`USDT` on binance
+/// is `BTCUSDT` there, `btcusdt` on huobi and `BTC-USDT` on okex.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Instrument {
+    pub base: String,
+    pub quote: String,
+    pub exchange: Exchange,
+}
+
+impl Instrument {
+    pub fn new(base: &str, quote: &str, exchange: Exchange) -> Self {
+        Self {
+            base: base.to_uppercase(),
+            quote: quote.to_uppercase(),
+            exchange,
+        }
+    }
+
+    pub fn from_pair(pair: &Pair, exchange: Exchange) -> Self {
+        Self::new(&pair.symbol, &pair.base, exchange)
+    }
+
+    pub fn pair(&self) -> Pair {
+        Pair::new(&self.base, &self.quote)
+    }
+}
+
+impl Display for Instrument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}_{}",
+            self.exchange.to_string(),
+            self.base,
+            self.quote
+        )
+    }
+}
+
+/// The limits an exchange puts on orders in one market, zero where it has none.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarketRules {
+    pub min_qty: Decimal,
+    /// quantities must be a multiple of this.
+    pub step_size: Decimal,
+    /// prices must be a multiple of this.
+    pub tick_size: Decimal,
+    /// the smallest order value, in the quote asset.
+    pub min_notional: Decimal,
+}
+
+impl MarketRules {
+    /// rounds down to the step size.
+    pub fn round_qty(&self, qty: Decimal) -> Decimal {
+        match qty.checked_div(self.step_size) {
+            Some(steps) => steps.floor() * self.step_size,
+            None => qty,
+        }
+    }
+
+    /// rounds to the nearest tick.
+    pub fn round_price(&self, price: Decimal) -> Decimal {
+        match price.checked_div(self.tick_size) {
+            Some(ticks) => ticks.round() * self.tick_size,
+            None => price,
+        }
+    }
+
+    /// why an order would be rejected, `None` when it is within the rules.
+    pub fn check(&self, qty: Decimal, price: Decimal) -> Option<String> {
+        if qty <= Decimal::ZERO || qty < self.min_qty {
+            Some(format!("quantity {} below the minimum {}", qty, self.min_qty))
+        } else if qty * price < self.min_notional {
+            Some(format!(
+                "value {} below the minimum {}",
+                qty * price,
+                self.min_notional
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// Bidirectional mapping between canonical instruments and the native symbols of one exchange,
+/// built from the exchange's own symbol listing.
+#[derive(Debug, Clone)]
+pub struct InstrumentMap {
+    exchange: Exchange,
+    by_symbol: HashMap<String, Instrument>,
+    by_instrument: HashMap<Instrument, String>,
+    rules: HashMap<String, MarketRules>,
+}
+
+impl InstrumentMap {
+    pub fn new(exchange: Exchange) -> Self {
+        Self {
+            exchange,
+            by_symbol: HashMap::new(),
+            by_instrument: HashMap::new(),
+            rules: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, native_symbol: &str, base: &str, quote: &str) {
+        let instrument = Instrument::new(base, quote, self.exchange);
+        self.by_symbol
+            .insert(native_symbol.to_string(), instrument.clone());
+        self.by_instrument
+            .insert(instrument, native_symbol.to_string());
+    }
+
+    pub fn set_rules(&mut self, native_symbol: &str, rules: MarketRules) {
+        self.rules.insert(native_symbol.to_string(), rules);
+    }
+
+    /// the order limits of a market, `None` when the exchange doesn't list them.
+    pub fn rules(&self, native_symbol: &str) -> Option<&MarketRules> {
+        self.rules.get(native_symbol)
+    }
+
+    pub fn exchange(&self) -> Exchange {
+        self.exchange
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_symbol.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_symbol.is_empty()
+    }
+
+    /// look up a native symbol, eg. `BTCUSDT`.
+    pub fn instrument(&self, native_symbol: &str) -> Option<&Instrument> {
+        self.by_symbol.get(native_symbol)
+    }
+
+    pub fn native_symbol(&self, instrument: &Instrument) -> Option<&str> {
+        self.by_instrument.get(instrument).map(|s| s.as_str())
+    }
+
+    pub fn pair(&self, native_symbol: &str) -> Option<Pair> {
+        self.instrument(native_symbol).map(|i| i.pair())
+    }
+
+    pub fn pair_to_native_symbol(&self, pair: &Pair) -> Option<&str> {
+        self.native_symbol(&Instrument::from_pair(pair, self.exchange))
+    }
+
+    pub fn instruments(&self) -> Vec<Instrument> {
+        self.by_instrument.keys().cloned().collect()
+    }
+
+    pub fn pairs(&self) -> Vec<Pair> {
+        self.by_instrument.keys().map(|i| i.pair()).collect()
+    }
+}
+
+/// Holds the instrument listing of an exchange once it has been fetched, shared between clones
+/// of the same api.
+#[derive(Debug, Clone, Default)]
+pub struct InstrumentCache(Arc<Mutex<Option<Arc<InstrumentMap>>>>);
+
+impl InstrumentCache {
+    pub fn get_or_load<F>(&self, load: F) -> CoreResult<Arc<InstrumentMap>>
+    where
+        F: FnOnce() -> CoreResult<InstrumentMap>,
+    {
+        let mut cached = self.0.lock().map_err(|_| {
+            Box::new(TrailerError::Generic(
+                "instrument cache poisoned".to_string(),
+            ))
+        })?;
+
+        if let Some(ref map) = *cached {
+            return Ok(map.clone());
+        }
+
+        let map = Arc::new(load()?);
+        *cached = Some(map.clone());
+        Ok(map)
+    }
+
+    /// forget the listing, the next lookup fetches it again (eg. after a new listing).
+    pub fn clear(&self) {
+        if let Ok(mut cached) = self.0.lock() {
+            *cached = None;
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::error::*;
+use std::{fmt, fmt::Display};
+
+static MINUTE: u64 = 60 * 1000;
+static HOUR: u64 = 60 * MINUTE;
+static DAY: u64 = 24 * HOUR;
+
+/// Candle width. Written in the binance notation, eg. `1m`, `4h`, `1w`, `1M`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Interval {
+    OneMinute,
+    ThreeMinutes,
+    FiveMinutes,
+    FifteenMinutes,
+    ThirtyMinutes,
+    OneHour,
+    TwoHours,
+    FourHours,
+    SixHours,
+    EightHours,
+    TwelveHours,
+    OneDay,
+    ThreeDays,
+    OneWeek,
+    OneMonth,
+}
+
+impl Interval {
+    pub fn all() -> Vec<Interval> {
+        vec![
+            Interval::OneMinute,
+            Interval::ThreeMinutes,
+            Interval::FiveMinutes,
+            Interval::FifteenMinutes,
+            Interval::ThirtyMinutes,
+            Interval::OneHour,
+            Interval::TwoHours,
+            Interval::FourHours,
+            Interval::SixHours,
+            Interval::EightHours,
+            Interval::TwelveHours,
+            Interval::OneDay,
+            Interval::ThreeDays,
+            Interval::OneWeek,
+            Interval::OneMonth,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Interval::OneMinute => "1m",
+            Interval::ThreeMinutes => "3m",
+            Interval::FiveMinutes => "5m",
+            Interval::FifteenMinutes => "15m",
+            Interval::ThirtyMinutes => "30m",
+            Interval::OneHour => "1h",
+            Interval::TwoHours => "2h",
+            Interval::FourHours => "4h",
+            Interval::SixHours => "6h",
+            Interval::EightHours => "8h",
+            Interval::TwelveHours => "12h",
+            Interval::OneDay => "1d",
+            Interval::ThreeDays => "3d",
+            Interval::OneWeek => "1w",
+            Interval::OneMonth => "1M",
+        }
+    }
+
+    /// Width in milliseconds. Months differ in length, `None` for `OneMonth`.
+    pub fn millis(&self) -> Option<u64> {
+        match self {
+            Interval::OneMinute => Some(MINUTE),
+            Interval::ThreeMinutes => Some(3 * MINUTE),
+            Interval::FiveMinutes => Some(5 * MINUTE),
+            Interval::FifteenMinutes => Some(15 * MINUTE),
+            Interval::ThirtyMinutes => Some(30 * MINUTE),
+            Interval::OneHour => Some(HOUR),
+            Interval::TwoHours => Some(2 * HOUR),
+            Interval::FourHours => Some(4 * HOUR),
+            Interval::SixHours => Some(6 * HOUR),
+            Interval::EightHours => Some(8 * HOUR),
+            Interval::TwelveHours => Some(12 * HOUR),
+            Interval::OneDay => Some(DAY),
+            Interval::ThreeDays => Some(3 * DAY),
+            Interval::OneWeek => Some(7 * DAY),
+            Interval::OneMonth => None,
+        }
+    }
+
+    /// whether candles of this interval can be built from candles of `lower`.
+    pub fn is_multiple_of(&self, lower: Interval) -> bool {
+        match (self.millis(), lower.millis()) {
+            (Some(width), Some(lower)) => width >= lower && width % lower == 0,
+            // months are made of whole days, but not whole weeks.
+            (None, Some(lower)) => DAY % lower == 0,
+            (None, None) => true,
+            (Some(_), None) => false,
+        }
+    }
+
+    /// the width of the candle in seconds as okex names it, eg. `3600` for `1h`.
+    pub fn okex_granularity(&self) -> Option<u32> {
+        match self {
+            Interval::OneMinute
+            | Interval::ThreeMinutes
+            | Interval::FiveMinutes
+            | Interval::FifteenMinutes
+            | Interval::ThirtyMinutes
+            | Interval::OneHour
+            | Interval::TwoHours
+            | Interval::FourHours
+            | Interval::SixHours
+            | Interval::TwelveHours
+            | Interval::OneDay
+            | Interval::OneWeek => self.millis().map(|millis| (millis / 1000) as u32),
+            _ => None,
+        }
+    }
+
+    /// the `period` huobi names the interval with, eg. `60min` for `1h`.
+    pub fn huobi_period(&self) -> Option<&'static str> {
+        match self {
+            Interval::OneMinute => Some("1min"),
+            Interval::FiveMinutes => Some("5min"),
+            Interval::FifteenMinutes => Some("15min"),
+            Interval::ThirtyMinutes => Some("30min"),
+            Interval::OneHour => Some("60min"),
+            Interval::FourHours => Some("4hour"),
+            Interval::OneDay => Some("1day"),
+            Interval::OneWeek => Some("1week"),
+            Interval::OneMonth => Some("1mon"),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Interval {
+    type Err = TrailerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Interval::all()
+            .into_iter()
+            .find(|interval| interval.as_str() == s)
+            .ok_or_else(|| TrailerError::MissingArgumentError(format!("unknown interval: {}", s)))
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
@@ -0,0 +1,481 @@
+use crate::error::*;
+use crate::models::*;
+use chrono::{offset::Local, prelude::DateTime};
+use rust_decimal::Decimal;
+use std::{fmt, fmt::Display};
+
+/// Decides which acquisition lots a disposal is matched against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CostBasisMethod {
+    /// first in, first out: the oldest lots are sold first.
+    Fifo,
+    /// last in, first out: the newest lots are sold first.
+    Lifo,
+    /// highest in, first out: the most expensive lots are sold first.
+    Hifo,
+    /// every open lot is pooled at the average cost of the pool.
+    AverageCost,
+}
+
+impl std::str::FromStr for CostBasisMethod {
+    type Err = TrailerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fifo" => Ok(CostBasisMethod::Fifo),
+            "lifo" => Ok(CostBasisMethod::Lifo),
+            "hifo" => Ok(CostBasisMethod::Hifo),
+            "average" | "avg" | "acb" => Ok(CostBasisMethod::AverageCost),
+            _ => Err(TrailerError::MissingArgumentError(format!(
+                "unknown cost basis method: {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for CostBasisMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CostBasisMethod::Fifo => write!(f, "FIFO"),
+            CostBasisMethod::Lifo => write!(f, "LIFO"),
+            CostBasisMethod::Hifo => write!(f, "HIFO"),
+            CostBasisMethod::AverageCost => write!(f, "AVERAGE"),
+        }
+    }
+}
+
+/// Units acquired by a single buy, priced in the quote currency of the pair.
+#[derive(Debug, Clone)]
+pub struct Lot {
+    pub trade_id: String,
+    pub acquired: DateTime<Local>,
+    /// units still held.
+    pub qty: Decimal,
+    /// units originally bought, after fees paid in the asset itself.
+    pub original_qty: Decimal,
+    /// cost per unit, including fees paid in the quote currency.
+    pub cost_price: Decimal,
+}
+
+impl Lot {
+    pub fn cost_basis(&self) -> Decimal {
+        self.qty * self.cost_price
+    }
+
+    pub fn value(&self, current_price: Decimal) -> Decimal {
+        self.qty * current_price
+    }
+
+    pub fn unrealized_pnl(&self, current_price: Decimal) -> Decimal {
+        self.value(current_price) - self.cost_basis()
+    }
+}
+
+/// The part of a lot consumed by a disposal.
+#[derive(Debug, Clone)]
+pub struct LotMatch {
+    pub trade_id: String,
+    pub acquired: DateTime<Local>,
+    pub qty: Decimal,
+    pub cost_price: Decimal,
+}
+
+impl LotMatch {
+    pub fn cost_basis(&self) -> Decimal {
+        self.qty * self.cost_price
+    }
+}
+
+/// A single sell, with the lots it was matched against.
+#[derive(Debug, Clone)]
+pub struct Disposal {
+    pub trade_id: String,
+    pub time: DateTime<Local>,
+    pub qty: Decimal,
+    /// proceeds per unit, net of fees paid in the quote currency.
+    pub sale_price: Decimal,
+    pub lots: Vec<LotMatch>,
+    /// units sold that no recorded buy covers, eg. holdings older than the trade history. They
+    /// carry a zero cost basis.
+    pub unmatched_qty: Decimal,
+}
+
+impl Disposal {
+    pub fn proceeds(&self) -> Decimal {
+        self.qty * self.sale_price
+    }
+
+    pub fn cost_basis(&self) -> Decimal {
+        self.lots.iter().map(|lot| lot.cost_basis()).sum()
+    }
+
+    pub fn realized_pnl(&self) -> Decimal {
+        self.proceeds() - self.cost_basis()
+    }
+}
+
+/// Consumes the trades of one pair in order, keeping open lots for buys and matching sells
+/// against them with the chosen `CostBasisMethod`.
+#[derive(Debug, Clone)]
+pub struct LotLedger {
+    pub method: CostBasisMethod,
+    pair: Option<Pair>,
+    lots: Vec<Lot>,
+    disposals: Vec<Disposal>,
+}
+
+impl LotLedger {
+    pub fn new(method: CostBasisMethod) -> Self {
+        Self {
+            method,
+            pair: None,
+            lots: Vec::new(),
+            disposals: Vec::new(),
+        }
+    }
+
+    /// replays the trades oldest first.
+    pub fn from_trades(method: CostBasisMethod, trades: &[Trade]) -> CoreResult<Self> {
+        let mut trades = trades.to_vec();
+        trades.sort_by(|a, b| a.time.cmp(&b.time));
+
+        let mut ledger = Self::new(method);
+        for trade in &trades {
+            ledger.add_trade(trade)?;
+        }
+
+        Ok(ledger)
+    }
+
+    /// Trades have to be added oldest first and all belong to the same pair, so that costs and
+    /// proceeds are in one quote currency.
+    pub fn add_trade(&mut self, trade: &Trade) -> CoreResult<()> {
+        match self.pair {
+            Some(ref pair) if *pair != trade.pair => {
+                return Err(Box::new(TrailerError::Generic(format!(
+                    "trade {} is for {}, the ledger holds {}",
+                    trade.id, trade.pair, pair
+                ))));
+            }
+            None => self.pair = Some(trade.pair.clone()),
+            _ => {}
+        };
+
+        match trade.trade_type {
+            TradeType::Buy => self.acquire(trade),
+            TradeType::Sell => self.dispose(trade),
+        };
+
+        Ok(())
+    }
+
+    fn acquire(&mut self, trade: &Trade) {
+        let mut qty = trade.qty;
+        let mut cost = trade.sale_cost();
+
+        match self.fee_side(trade) {
+            Some(FeeSide::Asset) => qty -= trade.fee,
+            Some(FeeSide::Quote) => cost += trade.fee,
+            None => {}
+        };
+
+        if qty <= Decimal::ZERO {
+            return;
+        }
+
+        self.lots.push(Lot {
+            trade_id: trade.id.clone(),
+            acquired: trade.time,
+            qty,
+            original_qty: qty,
+            cost_price: cost.checked_div(qty).unwrap_or(Decimal::ZERO),
+        });
+    }
+
+    fn dispose(&mut self, trade: &Trade) {
+        let mut proceeds = trade.sale_cost();
+        if let Some(FeeSide::Quote) = self.fee_side(trade) {
+            proceeds -= trade.fee;
+        }
+
+        let pooled_price = self.average_cost();
+        self.sort_lots_for_disposal();
+
+        let mut remaining = trade.qty;
+        let mut matches = Vec::new();
+
+        for lot in self.lots.iter_mut() {
+            if remaining <= Decimal::ZERO {
+                break;
+            }
+
+            let qty = remaining.min(lot.qty);
+            lot.qty -= qty;
+            remaining -= qty;
+
+            matches.push(LotMatch {
+                trade_id: lot.trade_id.clone(),
+                acquired: lot.acquired,
+                qty,
+                cost_price: match self.method {
+                    CostBasisMethod::AverageCost => pooled_price,
+                    _ => lot.cost_price,
+                },
+            });
+        }
+
+        self.lots.retain(|lot| lot.qty > Decimal::ZERO);
+
+        if self.method == CostBasisMethod::AverageCost {
+            for lot in self.lots.iter_mut() {
+                lot.cost_price = pooled_price;
+            }
+        }
+
+        // open lots are kept in the order they were bought.
+        self.lots.sort_by(|a, b| a.acquired.cmp(&b.acquired));
+
+        self.disposals.push(Disposal {
+            trade_id: trade.id.clone(),
+            time: trade.time,
+            qty: trade.qty,
+            sale_price: proceeds.checked_div(trade.qty).unwrap_or(Decimal::ZERO),
+            lots: matches,
+            unmatched_qty: remaining,
+        });
+    }
+
+    fn sort_lots_for_disposal(&mut self) {
+        match self.method {
+            CostBasisMethod::Fifo | CostBasisMethod::AverageCost => {
+                self.lots.sort_by(|a, b| a.acquired.cmp(&b.acquired))
+            }
+            CostBasisMethod::Lifo => self.lots.sort_by(|a, b| b.acquired.cmp(&a.acquired)),
+            CostBasisMethod::Hifo => self.lots.sort_by(|a, b| b.cost_price.cmp(&a.cost_price)),
+        }
+    }
+
+    /// fees paid in the traded asset change the quantity, fees paid in the quote currency change
+    /// the cost. Fees paid in anything else (eg. BNB) are not part of the cost basis.
+    fn fee_side(&self, trade: &Trade) -> Option<FeeSide> {
+        match trade.fee_symbol {
+            Some(ref symbol) if *symbol == trade.pair.symbol => Some(FeeSide::Asset),
+            Some(ref symbol) if *symbol == trade.pair.base => Some(FeeSide::Quote),
+            _ => None,
+        }
+    }
+
+    pub fn pair(&self) -> Option<&Pair> {
+        self.pair.as_ref()
+    }
+
+    pub fn open_lots(&self) -> &[Lot] {
+        &self.lots
+    }
+
+    pub fn disposals(&self) -> &[Disposal] {
+        &self.disposals
+    }
+
+    pub fn qty(&self) -> Decimal {
+        self.lots.iter().map(|lot| lot.qty).sum()
+    }
+
+    pub fn cost_basis(&self) -> Decimal {
+        self.lots.iter().map(|lot| lot.cost_basis()).sum()
+    }
+
+    /// cost per unit of everything still held.
+    pub fn average_cost(&self) -> Decimal {
+        self.cost_basis()
+            .checked_div(self.qty())
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    pub fn realized_pnl(&self) -> Decimal {
+        self.disposals.iter().map(|d| d.realized_pnl()).sum()
+    }
+
+    pub fn unrealized_pnl(&self, current_price: Decimal) -> Decimal {
+        self.lots
+            .iter()
+            .map(|lot| lot.unrealized_pnl(current_price))
+            .sum()
+    }
+}
+
+enum FeeSide {
+    Asset,
+    Quote,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn trade(id: &str, side: TradeType, qty: &str, price: &str, minute: i64) -> Trade {
+        Trade {
+            fee: Decimal::ZERO,
+            fee_symbol: None,
+            id: id.to_string(),
+            pair: Pair::new("BTC", "USDT"),
+            sale_price: dec(price),
+            qty: dec(qty),
+            time: Local.timestamp_opt(1_560_000_000 + minute * 60, 0).unwrap(),
+            trade_type: side,
+        }
+    }
+
+    fn with_fee(mut trade: Trade, fee: &str, symbol: &str) -> Trade {
+        trade.fee = dec(fee);
+        trade.fee_symbol = Some(symbol.to_string());
+        trade
+    }
+
+    /// buys at 100, 200 and 150, then sells 1.5 at 300.
+    fn ledger(method: CostBasisMethod) -> LotLedger {
+        LotLedger::from_trades(
+            method,
+            &[
+                trade("4", TradeType::Sell, "1.5", "300", 4),
+                trade("1", TradeType::Buy, "1", "100", 1),
+                trade("2", TradeType::Buy, "1", "200", 2),
+                trade("3", TradeType::Buy, "1", "150", 3),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn matched(ledger: &LotLedger) -> Vec<(String, Decimal)> {
+        ledger.disposals()[0]
+            .lots
+            .iter()
+            .map(|lot| (lot.trade_id.clone(), lot.qty))
+            .collect()
+    }
+
+    #[test]
+    fn fifo_sells_the_oldest_lots() {
+        let ledger = ledger(CostBasisMethod::Fifo);
+
+        assert_eq!(
+            matched(&ledger),
+            vec![("1".to_string(), dec("1")), ("2".to_string(), dec("0.5"))]
+        );
+        assert_eq!(ledger.realized_pnl(), dec("250"));
+        assert_eq!(ledger.qty(), dec("1.5"));
+        assert_eq!(ledger.cost_basis(), dec("250"));
+        assert_eq!(ledger.unrealized_pnl(dec("300")), dec("200"));
+    }
+
+    #[test]
+    fn lifo_sells_the_newest_lots() {
+        let ledger = ledger(CostBasisMethod::Lifo);
+
+        assert_eq!(
+            matched(&ledger),
+            vec![("3".to_string(), dec("1")), ("2".to_string(), dec("0.5"))]
+        );
+        assert_eq!(ledger.realized_pnl(), dec("200"));
+        assert_eq!(ledger.cost_basis(), dec("200"));
+    }
+
+    #[test]
+    fn hifo_sells_the_most_expensive_lots() {
+        let ledger = ledger(CostBasisMethod::Hifo);
+
+        assert_eq!(
+            matched(&ledger),
+            vec![("2".to_string(), dec("1")), ("3".to_string(), dec("0.5"))]
+        );
+        assert_eq!(ledger.realized_pnl(), dec("175"));
+        assert_eq!(ledger.cost_basis(), dec("175"));
+        // open lots stay in the order they were bought.
+        let open: Vec<&str> = ledger
+            .open_lots()
+            .iter()
+            .map(|l| l.trade_id.as_str())
+            .collect();
+        assert_eq!(open, vec!["1", "3"]);
+    }
+
+    #[test]
+    fn average_cost_pools_the_lots() {
+        let ledger = ledger(CostBasisMethod::AverageCost);
+
+        assert_eq!(ledger.realized_pnl(), dec("225"));
+        assert_eq!(ledger.average_cost(), dec("150"));
+        assert_eq!(ledger.cost_basis(), dec("225"));
+    }
+
+    #[test]
+    fn fee_in_the_asset_shrinks_the_lot() {
+        let buy = with_fee(trade("1", TradeType::Buy, "1", "100", 1), "0.01", "BTC");
+        let ledger = LotLedger::from_trades(CostBasisMethod::Fifo, &[buy]).unwrap();
+
+        assert_eq!(ledger.qty(), dec("0.99"));
+        assert_eq!(ledger.cost_basis().round_dp(8), dec("100"));
+    }
+
+    #[test]
+    fn fee_in_the_quote_adds_to_cost_and_reduces_proceeds() {
+        let ledger = LotLedger::from_trades(
+            CostBasisMethod::Fifo,
+            &[
+                with_fee(trade("1", TradeType::Buy, "1", "100", 1), "1", "USDT"),
+                with_fee(trade("2", TradeType::Sell, "1", "300", 2), "3", "USDT"),
+            ],
+        )
+        .unwrap();
+
+        let disposal = &ledger.disposals()[0];
+        assert_eq!(disposal.proceeds(), dec("297"));
+        assert_eq!(disposal.cost_basis(), dec("101"));
+        assert_eq!(ledger.realized_pnl(), dec("196"));
+    }
+
+    #[test]
+    fn fee_in_another_asset_is_not_part_of_the_cost() {
+        let buy = with_fee(trade("1", TradeType::Buy, "1", "100", 1), "0.5", "BNB");
+        let ledger = LotLedger::from_trades(CostBasisMethod::Fifo, &[buy]).unwrap();
+
+        assert_eq!(ledger.qty(), dec("1"));
+        assert_eq!(ledger.cost_basis(), dec("100"));
+    }
+
+    #[test]
+    fn selling_more_than_held_leaves_an_unmatched_qty() {
+        let ledger = LotLedger::from_trades(
+            CostBasisMethod::Fifo,
+            &[
+                trade("1", TradeType::Buy, "1", "100", 1),
+                trade("2", TradeType::Sell, "3", "150", 2),
+            ],
+        )
+        .unwrap();
+
+        let disposal = &ledger.disposals()[0];
+        assert_eq!(disposal.unmatched_qty, dec("2"));
+        assert_eq!(disposal.cost_basis(), dec("100"));
+        assert_eq!(ledger.realized_pnl(), dec("350"));
+        assert!(ledger.open_lots().is_empty());
+    }
+
+    #[test]
+    fn trades_of_another_pair_are_refused() {
+        let mut ledger = LotLedger::new(CostBasisMethod::Fifo);
+        ledger
+            .add_trade(&trade("1", TradeType::Buy, "1", "100", 1))
+            .unwrap();
+
+        let mut other = trade("2", TradeType::Buy, "1", "100", 2);
+        other.pair = Pair::new("ETH", "USDT");
+        assert!(ledger.add_trade(&other).is_err());
+    }
+}
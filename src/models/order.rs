@@ -3,6 +3,7 @@ use crate::models::*;
 use chrono::DateTime;
 use chrono::Local;
 use core::fmt;
+use rust_decimal::Decimal;
 
 #[derive(Debug, Clone)]
 pub struct Order {
@@ -10,10 +11,10 @@ pub struct Order {
     pub pair: Pair,
     pub order_type: OrderType, // limit, stop loss, etc.
     pub trade_type: TradeType, // buy or sell
-    pub qty: f64,
-    pub executed_qty: f64,
-    pub purchase_price: f64,
-    // pub current_price: f64,
+    pub qty: Decimal,
+    pub executed_qty: Decimal,
+    pub purchase_price: Decimal,
+    // pub current_price: Decimal,
     pub time: DateTime<Local>,
 }
 
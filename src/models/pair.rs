@@ -125,11 +125,14 @@ pub fn find_pair_by_symbol_and_base(symbol: &str, base: &str, pairs: Vec<Pair>)
 }
 
 type PairMap = HashMap<String, Vec<Pair>>;
+use rust_decimal::Decimal;
 use std::collections::HashMap;
 
-pub fn average_pair_price(prices: Vec<Price>) -> f64 {
-    let total_price: f64 = prices.iter().map(|p| p.price).sum();
-    total_price / prices.len() as f64
+pub fn average_pair_price(prices: Vec<Price>) -> Decimal {
+    let total_price: Decimal = prices.iter().map(|p| p.price).sum();
+    total_price
+        .checked_div(Decimal::from(prices.len()))
+        .unwrap_or(Decimal::ZERO)
 }
 
 // todo: remove
@@ -147,6 +150,6 @@ pub fn filter_pairmap_by_symbols(pairs: PairMap, symbols: Vec<&str>) -> PairMap
         .collect()
 }
 
-pub fn convert_currency(amount: f64, from: Price, to: Price) -> Option<f64> {
-    Some((from.price / to.price) * amount)
+pub fn convert_currency(amount: Decimal, from: Price, to: Price) -> Option<Decimal> {
+    from.price.checked_div(to.price).map(|rate| rate * amount)
 }
@@ -1,6 +1,7 @@
 use crate::error::*;
 use crate::models::*;
 use crate::utils::*;
+use rust_decimal::Decimal;
 
 #[derive(Debug, Clone)]
 pub struct Position {
@@ -27,33 +28,85 @@ impl Position {
             .expect("position does not contain trades.")
     }
 
-    pub fn entry_price(&self) -> f64 {
-        let entry_prices: f64 = self
+    pub fn entry_price(&self) -> Decimal {
+        let entry_prices: Decimal = self
             .buy_trades()
             .into_iter()
             .map(|o| o.sale_price * o.qty)
             .sum();
-        let total_qty: f64 = self.buy_trades().into_iter().map(|o| o.qty).sum();
+        let total_qty: Decimal = self.buy_trades().into_iter().map(|o| o.qty).sum();
 
-        entry_prices / total_qty
+        entry_prices.checked_div(total_qty).unwrap_or(Decimal::ZERO)
     }
 
-    pub fn exit_price(&self) -> Option<f64> {
+    /// sell prices weighted by the quantity sold.
+    pub fn exit_price(&self) -> Option<Decimal> {
         if self.sell_trades().len() > 0 {
-            Some(
-                self.sell_trades()
-                    .into_iter()
-                    .map(|t| t.sale_price)
-                    .sum::<f64>()
-                    / self.sell_trades().len() as f64,
-            )
+            let exit_prices: Decimal = self
+                .sell_trades()
+                .into_iter()
+                .map(|o| o.sale_price * o.qty)
+                .sum();
+
+            exit_prices.checked_div(self.sell_qty())
         } else {
             None
         }
     }
 
-    pub fn current_price(&self) -> f64 {
-        0.0 // todo: fix to include most recent price
+    /// replays the trades into lots, see `LotLedger` for realized and unrealized profit.
+    pub fn ledger(&self, method: CostBasisMethod) -> CoreResult<LotLedger> {
+        LotLedger::from_trades(method, &self.trades)
+    }
+
+    /// like `ledger`, with fees paid in other assets (eg. BNB) counted in the cost basis.
+    pub fn net_ledger(
+        &self,
+        method: CostBasisMethod,
+        prices: &dyn HistoricalPrices,
+    ) -> CoreResult<LotLedger> {
+        LotLedger::from_trades(method, &self.trades.normalize_fees(prices)?)
+    }
+
+    /// every fee of the position in the quote currency.
+    pub fn fees(&self, prices: &dyn HistoricalPrices) -> CoreResult<Decimal> {
+        self.trades
+            .iter()
+            .map(|trade| {
+                trade
+                    .fee_in_quote(prices)
+                    .ok_or_else(|| missing_fee_price(trade))
+            })
+            .sum()
+    }
+
+    /// what the buys cost including their fees.
+    pub fn net_buy_cost(&self, prices: &dyn HistoricalPrices) -> CoreResult<Decimal> {
+        net_sale_costs(&self.buy_trades(), prices)
+    }
+
+    /// what the sells returned after their fees.
+    pub fn net_sell_proceeds(&self, prices: &dyn HistoricalPrices) -> CoreResult<Decimal> {
+        net_sale_costs(&self.sell_trades(), prices)
+    }
+
+    pub fn net_entry_price(&self, prices: &dyn HistoricalPrices) -> CoreResult<Decimal> {
+        Ok(self
+            .net_buy_cost(prices)?
+            .checked_div(self.buy_qty())
+            .unwrap_or(Decimal::ZERO))
+    }
+
+    pub fn net_realized_pnl(
+        &self,
+        method: CostBasisMethod,
+        prices: &dyn HistoricalPrices,
+    ) -> CoreResult<Decimal> {
+        Ok(self.net_ledger(method, prices)?.realized_pnl())
+    }
+
+    pub fn current_price(&self) -> Decimal {
+        Decimal::ZERO // todo: fix to include most recent price
             //        self.buy_trades()
             //            .into_iter()
             //            .map(|o| o.pair.price)
@@ -61,23 +114,23 @@ impl Position {
             //            / self.buy_trades().len() as f64
     }
 
-    pub fn qty(&self) -> f64 {
+    pub fn qty(&self) -> Decimal {
         self.buy_qty() - self.sell_qty()
     }
 
-    pub fn buy_qty(&self) -> f64 {
+    pub fn buy_qty(&self) -> Decimal {
         self.buy_trades().into_iter().map(|o| o.qty).sum()
     }
-    pub fn sell_qty(&self) -> f64 {
+    pub fn sell_qty(&self) -> Decimal {
         self.sell_trades().into_iter().map(|o| o.qty).sum()
     }
 
-    pub fn buy_cost(&self) -> f64 {
+    pub fn buy_cost(&self) -> Decimal {
         self.entry_price() * self.buy_qty()
     }
 
-    pub fn sell_cost(&self) -> f64 {
-        self.exit_price().unwrap_or(0.0) * self.sell_qty()
+    pub fn sell_cost(&self) -> Decimal {
+        self.exit_price().unwrap_or(Decimal::ZERO) * self.sell_qty()
     }
 
     // todo: memoize
@@ -108,13 +161,13 @@ impl Position {
         average_trades(self.sell_trades())
     }
 
-    pub fn remaining_qty(&self) -> f64 {
+    pub fn remaining_qty(&self) -> Decimal {
         // println!("remaining_qty: {}", self.asset.amount);
         self.asset.amount
         // self.buy_qty() - self.sell_qty()
     }
 
-    pub fn current_profit_as_percent(&self) -> f64 {
+    pub fn current_profit_as_percent(&self) -> Decimal {
         price_percent(self.entry_price(), self.current_price())
     }
 
@@ -122,3 +175,14 @@ impl Position {
         self.trades.first().map(|t| t.pair.base_type())
     }
 }
+
+fn net_sale_costs(trades: &[Trade], prices: &dyn HistoricalPrices) -> CoreResult<Decimal> {
+    trades
+        .iter()
+        .map(|trade| {
+            trade
+                .net_sale_cost(prices)
+                .ok_or_else(|| missing_fee_price(trade))
+        })
+        .sum()
+}
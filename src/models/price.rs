@@ -1,9 +1,10 @@
 use crate::models::*;
+use rust_decimal::Decimal;
 
 #[derive(Debug, Clone)]
 pub struct Price {
     pub pair: Pair,
-    pub price: f64,
+    pub price: Decimal,
 }
 
 impl Price {
@@ -29,10 +30,12 @@ impl Price {
 pub trait PriceUtils {
     /// strips price information from Vec returning simple Pairs.
     fn to_pairs(&self) -> Vec<Pair>;
-    fn price_for(&self, pair: Pair) -> Option<f64>;
+    fn price_for(&self, pair: Pair) -> Option<Decimal>;
     fn filter_by(&self, symbol: &str) -> Vec<Price>;
     fn first_btc_price_for(&self, symbol: &str) -> Option<Price>;
-    fn price_of(&self, symbol: &str, base: &str) -> Option<f64>;
+    /// Connects every asset through the markets, build it once and look up as many prices and
+    /// conversions as needed.
+    fn conversions(&self) -> ConversionGraph;
 }
 
 impl PriceUtils for Vec<Price> {
@@ -40,7 +43,7 @@ impl PriceUtils for Vec<Price> {
         self.iter().map(|price| price.pair.clone()).collect()
     }
 
-    fn price_for(&self, pair: Pair) -> Option<f64> {
+    fn price_for(&self, pair: Pair) -> Option<Decimal> {
         self.into_iter().find(|p| p.pair == pair).map(|p| p.price)
     }
 
@@ -60,13 +63,7 @@ impl PriceUtils for Vec<Price> {
             .map(|p| p.clone())
     }
 
-    fn price_of(&self, symbol: &str, base: &str) -> Option<f64> {
-        if symbol == base {
-            return Some(1.0);
-        };
-
-        self.price_for(Pair::new(symbol, base)).or(self
-            .price_for(Pair::new(base, symbol))
-            .map(|price| 1.0 / price))
+    fn conversions(&self) -> ConversionGraph {
+        ConversionGraph::from_prices(self)
     }
 }
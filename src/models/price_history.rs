@@ -0,0 +1,111 @@
+use crate::error::*;
+use crate::exchanges::ExchangeAPI;
+use crate::models::*;
+use crate::utils::local_datetime_from_unix;
+use chrono::{offset::Local, prelude::DateTime};
+use log::warn;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// Prices of an asset in another currency at a point in time, used to value past trades.
+pub trait HistoricalPrices {
+    fn price_at(&self, symbol: &str, base: &str, time: DateTime<Local>) -> Option<Decimal>;
+}
+
+/// Samples of prices over time, eg. daily closes, keyed by pair.
+#[derive(Debug, Clone, Default)]
+pub struct PriceHistory {
+    series: HashMap<String, Vec<(DateTime<Local>, Decimal)>>,
+}
+
+impl PriceHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The close of every candle the exchange has for `pairs`, at the candle's close time. Pairs
+    /// whose candles cannot be fetched are skipped with a warning.
+    pub fn from_exchange(
+        api: &dyn ExchangeAPI,
+        pairs: &[Pair],
+        interval: Interval,
+    ) -> CoreResult<Self> {
+        let mut history = Self::new();
+
+        for pair in pairs {
+            let symbol = api.native_symbol(pair)?;
+            let candles = match api.candles(&symbol, interval) {
+                Ok(candles) => candles,
+                Err(e) => {
+                    warn!("{}: candles for {}: {}", api.display(), symbol, e);
+                    continue;
+                }
+            };
+
+            for candle in candles {
+                history.insert(
+                    pair,
+                    local_datetime_from_unix(candle.close_time),
+                    candle.close_price,
+                );
+            }
+        }
+
+        Ok(history)
+    }
+
+    pub fn insert(&mut self, pair: &Pair, time: DateTime<Local>, price: Decimal) {
+        let series = self
+            .series
+            .entry(key(&pair.symbol, &pair.base))
+            .or_default();
+
+        match series.binary_search_by(|(t, _)| t.cmp(&time)) {
+            Ok(index) => series[index] = (time, price),
+            Err(index) => series.insert(index, (time, price)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.series.values().map(|s| s.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn latest_before(&self, symbol: &str, base: &str, time: DateTime<Local>) -> Option<Decimal> {
+        let series = self.series.get(&key(symbol, base))?;
+
+        match series.binary_search_by(|(t, _)| t.cmp(&time)) {
+            Ok(index) => Some(series[index].1),
+            Err(0) => None,
+            Err(index) => Some(series[index - 1].1),
+        }
+    }
+}
+
+impl HistoricalPrices for PriceHistory {
+    /// the last sample at or before `time`, using the inverse pair when only that one is known.
+    fn price_at(&self, symbol: &str, base: &str, time: DateTime<Local>) -> Option<Decimal> {
+        if symbol.to_uppercase() == base.to_uppercase() {
+            return Some(Decimal::ONE);
+        }
+
+        self.latest_before(symbol, base, time).or_else(|| {
+            self.latest_before(base, symbol, time)
+                .and_then(|price| Decimal::ONE.checked_div(price))
+        })
+    }
+}
+
+/// Current prices, for estimates when no history is available. The time is ignored.
+impl HistoricalPrices for ConversionGraph {
+    fn price_at(&self, symbol: &str, base: &str, _time: DateTime<Local>) -> Option<Decimal> {
+        self.price_of(symbol, base)
+    }
+}
+
+fn key(symbol: &str, base: &str) -> String {
+    format!("{}_{}", symbol.to_uppercase(), base.to_uppercase())
+}
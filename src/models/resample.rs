@@ -0,0 +1,237 @@
+use crate::models::*;
+use chrono::prelude::*;
+use std::time::{Duration, UNIX_EPOCH};
+
+static DAY: u64 = 24 * 60 * 60 * 1000;
+/// the unix epoch was a thursday, weeks start on monday.
+static WEEK_OFFSET: u64 = 4 * DAY;
+
+/// Start and end (inclusive, unix millis) of the `interval` bucket holding `time`. Buckets are
+/// aligned to UTC: days start at midnight, weeks on monday and months on the first.
+pub fn interval_bounds(time: u64, interval: Interval) -> (u64, u64) {
+    match interval.millis() {
+        Some(width) if interval == Interval::OneWeek => {
+            // counted from the monday before the epoch, its start is clamped to the epoch.
+            let end = (time + width - WEEK_OFFSET) / width * width + WEEK_OFFSET - 1;
+            (end.saturating_sub(width - 1), end)
+        }
+        Some(width) => {
+            let start = time / width * width;
+            (start, start + width - 1)
+        }
+        None => {
+            let date = DateTime::<Utc>::from(UNIX_EPOCH + Duration::from_millis(time))
+                .naive_utc()
+                .date();
+            let (year, month) = (date.year(), date.month());
+            let (next_year, next_month) = if month == 12 {
+                (year + 1, 1)
+            } else {
+                (year, month + 1)
+            };
+            (
+                month_start(year, month),
+                month_start(next_year, next_month) - 1,
+            )
+        }
+    }
+}
+
+fn month_start(year: i32, month: u32) -> u64 {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid date");
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .map(|date| date.signed_duration_since(epoch).num_days().max(0) as u64 * DAY)
+        .unwrap_or(0)
+}
+
+/// Builds `interval` candles out of lower interval candles, eg. `3d` from `1d` for an exchange
+/// which doesn't offer it. Candles must be oldest first, empty buckets are left out and the last
+/// candle may be incomplete, as it is on the exchanges.
+pub fn resample(candles: &[Candlestick], interval: Interval) -> Vec<Candlestick> {
+    let mut resampled: Vec<Candlestick> = Vec::new();
+
+    for candle in candles {
+        let (open_time, close_time) = interval_bounds(candle.open_time, interval);
+
+        match resampled.last_mut() {
+            Some(current) if current.open_time == open_time => {
+                current.close_price = candle.close_price;
+                current.high_price = current.high_price.max(candle.high_price);
+                current.low_price = current.low_price.min(candle.low_price);
+                current.volume += candle.volume;
+                current.quote_volume = match (current.quote_volume, candle.quote_volume) {
+                    (Some(total), Some(volume)) => Some(total + volume),
+                    _ => None,
+                };
+                current.number_of_trades += candle.number_of_trades;
+            }
+            _ => resampled.push(Candlestick {
+                open_time,
+                close_time,
+                ..candle.clone()
+            }),
+        }
+    }
+
+    resampled
+}
+
+/// Builds `interval` candles out of individual trades, oldest first. Empty buckets are left out.
+pub fn candles_from_trades(trades: &[Trade], interval: Interval) -> Vec<Candlestick> {
+    let mut trades: Vec<&Trade> = trades.iter().collect();
+    trades.sort_by(|a, b| a.time.cmp(&b.time));
+
+    let candles: Vec<Candlestick> = trades
+        .into_iter()
+        .map(|trade| {
+            let time = trade.time.timestamp_millis().max(0) as u64;
+            Candlestick {
+                open_time: time,
+                close_time: time,
+                open_price: trade.sale_price,
+                close_price: trade.sale_price,
+                high_price: trade.sale_price,
+                low_price: trade.sale_price,
+                volume: trade.qty,
+                quote_volume: Some(trade.qty * trade.sale_price),
+                number_of_trades: 1,
+            }
+        })
+        .collect();
+
+    resample(&candles, interval)
+}
+
+/// The largest of `available` that `interval` can be built from.
+pub fn resample_source(interval: Interval, available: &[Interval]) -> Option<Interval> {
+    available
+        .iter()
+        .filter(|lower| interval.is_multiple_of(**lower))
+        .max()
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    static HOUR: u64 = 60 * 60 * 1000;
+
+    fn candle(hour: u64, open: i64, high: i64, low: i64, close: i64) -> Candlestick {
+        Candlestick {
+            open_time: hour * HOUR,
+            close_time: (hour + 1) * HOUR - 1,
+            open_price: Decimal::from(open),
+            close_price: Decimal::from(close),
+            high_price: Decimal::from(high),
+            low_price: Decimal::from(low),
+            volume: Decimal::ONE,
+            quote_volume: Some(Decimal::from(close)),
+            number_of_trades: 2,
+        }
+    }
+
+    #[test]
+    fn fixed_width_bounds() {
+        assert_eq!(
+            interval_bounds(HOUR + 5 * 60 * 1000, Interval::OneHour),
+            (HOUR, 2 * HOUR - 1)
+        );
+        assert_eq!(
+            interval_bounds(2 * DAY + HOUR, Interval::ThreeDays),
+            (0, 3 * DAY - 1)
+        );
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        // 1970-01-05 was the first monday.
+        assert_eq!(
+            interval_bounds(4 * DAY + HOUR, Interval::OneWeek),
+            (4 * DAY, 11 * DAY - 1)
+        );
+        assert_eq!(interval_bounds(HOUR, Interval::OneWeek), (0, 4 * DAY - 1));
+    }
+
+    #[test]
+    fn months_follow_the_calendar() {
+        let february = 1_548_979_200_000; // 2019-02-01
+        let march = 1_551_398_400_000; // 2019-03-01
+
+        assert_eq!(
+            interval_bounds(february + 14 * DAY, Interval::OneMonth),
+            (february, march - 1)
+        );
+        assert_eq!(interval_bounds(march, Interval::OneMonth).0, march);
+    }
+
+    #[test]
+    fn resample_merges_each_bucket() {
+        let candles = vec![
+            candle(0, 10, 12, 9, 11),
+            candle(1, 11, 15, 10, 14),
+            candle(2, 14, 14, 8, 9),
+            candle(3, 9, 10, 9, 10),
+            candle(4, 10, 11, 10, 11),
+        ];
+        let resampled = resample(&candles, Interval::FourHours);
+
+        assert_eq!(resampled.len(), 2);
+
+        let first = &resampled[0];
+        assert_eq!((first.open_time, first.close_time), (0, 4 * HOUR - 1));
+        assert_eq!(first.open_price, Decimal::new(10, 0));
+        assert_eq!(first.close_price, Decimal::new(10, 0));
+        assert_eq!(first.high_price, Decimal::new(15, 0));
+        assert_eq!(first.low_price, Decimal::new(8, 0));
+        assert_eq!(first.volume, Decimal::new(4, 0));
+        assert_eq!(first.quote_volume, Some(Decimal::new(44, 0)));
+        assert_eq!(first.number_of_trades, 8);
+
+        // the last bucket is incomplete, as on the exchanges.
+        let last = &resampled[1];
+        assert_eq!((last.open_time, last.close_time), (4 * HOUR, 8 * HOUR - 1));
+        assert_eq!(last.close_price, Decimal::new(11, 0));
+    }
+
+    #[test]
+    fn resample_leaves_out_empty_buckets() {
+        let mut partial = candle(1, 1, 1, 1, 1);
+        partial.quote_volume = None;
+        let candles = vec![candle(0, 1, 1, 1, 1), partial, candle(9, 1, 1, 1, 1)];
+        let resampled = resample(&candles, Interval::FourHours);
+
+        let opens: Vec<u64> = resampled.iter().map(|c| c.open_time).collect();
+        assert_eq!(opens, vec![0, 8 * HOUR]);
+        // a candle without quote volume leaves the bucket without it.
+        assert_eq!(resampled[0].quote_volume, None);
+    }
+
+    #[test]
+    fn source_is_the_largest_divisor() {
+        let available = vec![
+            Interval::OneMinute,
+            Interval::OneHour,
+            Interval::OneDay,
+            Interval::ThreeDays,
+        ];
+
+        assert_eq!(
+            resample_source(Interval::OneWeek, &available),
+            Some(Interval::OneDay)
+        );
+        assert_eq!(
+            resample_source(Interval::TwelveHours, &available),
+            Some(Interval::OneHour)
+        );
+        assert_eq!(
+            resample_source(Interval::OneMonth, &available),
+            Some(Interval::OneDay)
+        );
+        assert_eq!(
+            resample_source(Interval::OneMinute, &[Interval::OneHour]),
+            None
+        );
+    }
+}
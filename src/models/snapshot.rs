@@ -0,0 +1,92 @@
+use crate::error::*;
+use crate::exchanges::ExchangeAPI;
+use crate::models::*;
+use crate::utils::local_datetime_from_unix;
+use chrono::{offset::Local, prelude::DateTime};
+use rust_decimal::Decimal;
+use serde_derive::{Deserialize, Serialize};
+
+/// The balance of one asset on one exchange when the snapshot was taken.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetSnapshot {
+    pub exchange: String,
+    pub symbol: String,
+    pub amount: Decimal,
+    /// `None` when no market connected the asset to BTC at the time.
+    pub value_in_btc: Option<Decimal>,
+    pub value_in_usd: Option<Decimal>,
+}
+
+/// Balances across exchanges at a moment in time, valued in BTC and USD.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// unix time in milliseconds.
+    pub time: u64,
+    pub assets: Vec<AssetSnapshot>,
+}
+
+impl Snapshot {
+    pub fn new(time: DateTime<Local>) -> Self {
+        Snapshot {
+            time: time.timestamp_millis() as u64,
+            assets: Vec::new(),
+        }
+    }
+
+    pub fn now() -> Self {
+        Self::new(Local::now())
+    }
+
+    pub fn datetime(&self) -> DateTime<Local> {
+        local_datetime_from_unix(self.time)
+    }
+
+    /// values every balance with the prices of the exchange it is held on.
+    pub fn add_assets(&mut self, assets: &[Asset], prices: &[Price]) {
+        let graph = ConversionGraph::from_prices(prices);
+
+        for asset in assets {
+            self.assets.push(AssetSnapshot {
+                exchange: asset.exchange.to_string(),
+                symbol: asset.symbol.clone(),
+                amount: asset.amount,
+                value_in_btc: graph.convert(asset.amount, &asset.symbol, BTC_SYMBOL),
+                value_in_usd: match asset.asset_type() {
+                    AssetType::Stablecoin => Some(asset.amount),
+                    _ => graph.convert(asset.amount, &asset.symbol, USD_SYMBOL),
+                },
+            });
+        }
+    }
+
+    /// fetches balances and prices from an exchange and adds them to the snapshot.
+    pub fn add_exchange<E: ExchangeAPI + ?Sized>(&mut self, api: &E) -> CoreResult<()> {
+        let assets = api.balances()?;
+        let prices = api.all_prices()?;
+        self.add_assets(&assets, &prices);
+        Ok(())
+    }
+
+    pub fn total_btc(&self) -> Decimal {
+        self.assets.iter().filter_map(|a| a.value_in_btc).sum()
+    }
+
+    pub fn total_usd(&self) -> Decimal {
+        self.assets.iter().filter_map(|a| a.value_in_usd).sum()
+    }
+
+    pub fn exchange(&self, exchange: &str) -> Snapshot {
+        Snapshot {
+            time: self.time,
+            assets: self
+                .assets
+                .iter()
+                .filter(|a| a.exchange == exchange)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+static BTC_SYMBOL: &str = "BTC";
+static USD_SYMBOL: &str = "USDT";
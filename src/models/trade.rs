@@ -1,16 +1,18 @@
+use crate::error::*;
 use crate::models::*;
 use crate::utils::average;
 // use crate::utils::*;
 use chrono::{offset::Local, prelude::DateTime};
+use rust_decimal::Decimal;
 
 #[derive(Debug, Clone)]
 pub struct Trade {
-    pub fee: f64,
+    pub fee: Decimal,
     pub fee_symbol: Option<String>,
     pub id: String,
     pub pair: Pair,
-    pub sale_price: f64,
-    pub qty: f64,
+    pub sale_price: Decimal,
+    pub qty: Decimal,
     pub time: DateTime<Local>,
     pub trade_type: TradeType,
 }
@@ -37,10 +39,72 @@ impl Trade {
     // }
 
     /// what the trade cost when bought/sold
-    pub fn sale_cost(&self) -> f64 {
+    pub fn sale_cost(&self) -> Decimal {
         self.qty * self.sale_price
     }
 
+    /// The fee in the quote currency of the pair. Fees paid in another asset (eg. BNB) are
+    /// converted with its price at the time of the trade, `None` when that price is unknown.
+    pub fn fee_in_quote(&self, prices: &dyn HistoricalPrices) -> Option<Decimal> {
+        if self.fee.is_zero() {
+            return Some(Decimal::ZERO);
+        }
+
+        match self.fee_symbol {
+            None => Some(self.fee),
+            Some(ref symbol) if symbol.to_uppercase() == self.pair.base.to_uppercase() => {
+                Some(self.fee)
+            }
+            Some(ref symbol) if symbol.to_uppercase() == self.pair.symbol.to_uppercase() => {
+                Some(self.fee * self.sale_price)
+            }
+            Some(ref symbol) => prices
+                .price_at(symbol, &self.pair.base, self.time)
+                .map(|price| self.fee * price),
+        }
+    }
+
+    /// the fee in a reporting currency, eg. USD, at the time of the trade.
+    pub fn fee_in(&self, currency: &str, prices: &dyn HistoricalPrices) -> Option<Decimal> {
+        let fee = self.fee_in_quote(prices)?;
+        prices
+            .price_at(&self.pair.base, currency, self.time)
+            .map(|price| fee * price)
+    }
+
+    /// what a buy cost including fees, or what a sell returned after fees, in the quote currency.
+    pub fn net_sale_cost(&self, prices: &dyn HistoricalPrices) -> Option<Decimal> {
+        let fee = self.fee_in_quote(prices)?;
+        match self.trade_type {
+            TradeType::Buy => Some(self.sale_cost() + fee),
+            TradeType::Sell => Some(self.sale_cost() - fee),
+        }
+    }
+
+    /// the price per unit after fees.
+    pub fn net_sale_price(&self, prices: &dyn HistoricalPrices) -> Option<Decimal> {
+        self.net_sale_cost(prices)?.checked_div(self.qty)
+    }
+
+    /// A copy of the trade with its fee moved into the quote currency. Fees on a buy paid in the
+    /// bought asset are kept as they are, they reduce the quantity received rather than the cost.
+    pub fn normalize_fee(&self, prices: &dyn HistoricalPrices) -> Option<Trade> {
+        let fee_in_bought_asset = self.trade_type == TradeType::Buy
+            && self.fee_symbol.as_ref().map_or(false, |s| {
+                s.to_uppercase() == self.pair.symbol.to_uppercase()
+            });
+
+        if fee_in_bought_asset || self.fee.is_zero() {
+            return Some(self.clone());
+        }
+
+        Some(Trade {
+            fee: self.fee_in_quote(prices)?,
+            fee_symbol: Some(self.pair.base.clone()),
+            ..self.clone()
+        })
+    }
+
     /// the current value of the trade
     // pub fn current_cost(&self) -> f64 {
     //     // self.qty * self.current_price
@@ -85,14 +149,14 @@ impl Trade {
     // }
 
     // grouping strategy that attempts to group until an asset reaches zero. buggy at the moment.
-    pub fn group_by_qty(trades: &Vec<Trade>, qty: f64) -> Vec<Trade> {
+    pub fn group_by_qty(trades: &Vec<Trade>, qty: Decimal) -> Vec<Trade> {
         let trades: Vec<Trade> = trades.iter().cloned().rev().collect();
         let mut position_trades: Vec<Trade> = Vec::new();
         let mut remaining_qty = qty;
         // let trade_type = trades.first().expect("need a trade type").
 
         for trade in trades.clone() {
-            if remaining_qty.round() <= 0.0 {
+            if remaining_qty.round() <= Decimal::ZERO {
                 break;
             }
 
@@ -163,7 +227,7 @@ impl Trade {
     }
 }
 
-pub fn sum_qty(trades: Vec<Trade>) -> f64 {
+pub fn sum_qty(trades: Vec<Trade>) -> Decimal {
     trades
         .into_iter()
         .map(|trade| match trade.trade_type {
@@ -173,7 +237,7 @@ pub fn sum_qty(trades: Vec<Trade>) -> f64 {
         .sum()
 }
 
-pub fn sum_cost(trades: Vec<Trade>) -> f64 {
+pub fn sum_cost(trades: Vec<Trade>) -> Decimal {
     trades
         .into_iter()
         .map(|trade| match trade.trade_type {
@@ -183,13 +247,15 @@ pub fn sum_cost(trades: Vec<Trade>) -> f64 {
         .sum()
 }
 
-pub fn average_cost(trades: Vec<Trade>) -> f64 {
-    let average: f64 = trades
+pub fn average_cost(trades: Vec<Trade>) -> Decimal {
+    let average: Decimal = trades
         .clone()
         .into_iter()
         .map(|trade| trade.qty * trade.sale_price)
         .sum();
-    average / sum_qty(trades)
+    average
+        .checked_div(sum_qty(trades))
+        .unwrap_or(Decimal::ZERO)
 }
 
 /// average together buys and sells
@@ -295,7 +361,7 @@ pub fn _group_trades_by_trade_type_pair(trades: Vec<Trade>) -> Vec<Vec<Trade>> {
 pub fn group_trades_by_price(trades: Vec<Trade>) -> Vec<Trade> {
     let mut grouped_trades = Vec::new();
     let mut current_trade: Trade = trades.first().cloned().unwrap();
-    current_trade.qty = 0.0;
+    current_trade.qty = Decimal::ZERO;
 
     for trade in trades.clone() {
         if trade.sale_price == current_trade.sale_price
@@ -315,6 +381,8 @@ pub fn group_trades_by_price(trades: Vec<Trade>) -> Vec<Trade> {
 pub trait TradeUtils {
     fn buys_only(&self) -> Vec<Trade>;
     fn sells_only(&self) -> Vec<Trade>;
+    fn normalize_fees(&self, prices: &dyn HistoricalPrices) -> CoreResult<Vec<Trade>>;
+    fn fees_in(&self, currency: &str, prices: &dyn HistoricalPrices) -> CoreResult<Decimal>;
 }
 
 impl TradeUtils for Vec<Trade> {
@@ -331,4 +399,34 @@ impl TradeUtils for Vec<Trade> {
             .cloned()
             .collect()
     }
+
+    /// moves every fee into the quote currency of its trade, see `Trade::normalize_fee`.
+    fn normalize_fees(&self, prices: &dyn HistoricalPrices) -> CoreResult<Vec<Trade>> {
+        self.iter()
+            .map(|trade| {
+                trade
+                    .normalize_fee(prices)
+                    .ok_or_else(|| missing_fee_price(trade))
+            })
+            .collect()
+    }
+
+    fn fees_in(&self, currency: &str, prices: &dyn HistoricalPrices) -> CoreResult<Decimal> {
+        self.iter()
+            .map(|trade| {
+                trade
+                    .fee_in(currency, prices)
+                    .ok_or_else(|| missing_fee_price(trade))
+            })
+            .sum()
+    }
+}
+
+pub(crate) fn missing_fee_price(trade: &Trade) -> Box<dyn std::error::Error> {
+    Box::new(TrailerError::Generic(format!(
+        "no price for the {} fee of trade {} at {}",
+        trade.fee_symbol.clone().unwrap_or_default(),
+        trade.id,
+        trade.time
+    )))
 }
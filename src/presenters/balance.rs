@@ -1,4 +1,5 @@
 use crate::models::*;
+use rust_decimal::Decimal;
 
 #[derive(Debug, Clone)]
 pub struct BalancePresenter {
@@ -7,90 +8,67 @@ pub struct BalancePresenter {
 }
 
 impl BalancePresenter {
-    pub fn _total_value_in(&self, symbol: &str) -> f64 {
-        self.assets
-            .clone()
-            .into_iter()
-            .map(|asset| self.prices.price_of(&asset.symbol, symbol).unwrap_or(0.0))
-            .sum()
+    pub fn conversions(&self) -> ConversionGraph {
+        ConversionGraph::from_prices(&self.prices)
     }
 
-    // todo: this doesn't need to be an option does it?
-    pub fn total_value_in_btc(&self) -> f64 {
-        let btc_price = Price::find_first_btc_usd_price(&self.prices)
-            .map(|p| p.price)
-            .unwrap_or(0.0);
+    /// every asset with the route used to value it in `symbol`, `None` when there is no route.
+    pub fn valuations_in(&self, symbol: &str) -> Vec<(Asset, Option<Conversion>)> {
+        let graph = self.conversions();
 
         self.assets
             .iter()
-            .map({
-                |asset| match asset.asset_type() {
-                    AssetType::Bitcoin => asset.amount,
-                    AssetType::Stablecoin => asset.amount / btc_price,
-                    AssetType::Altcoin => {
-                        Price::find_first_btc_price_for_symbol(&asset.symbol, self.prices.clone())
-                            .map(|p| p.price)
-                            .unwrap_or(0.0)
-                            * asset.amount
-                    }
-                }
-            })
-            .sum()
+            .map(|asset| (asset.clone(), graph.conversion(&asset.symbol, symbol)))
+            .collect()
     }
 
-    pub fn alts_value_in_btc(&self) -> f64 {
-        self.assets
-            .iter()
-            .map({
-                |asset| match asset.asset_type() {
-                    AssetType::Altcoin => {
-                        Price::find_first_btc_price_for_symbol(&asset.symbol, self.prices.clone())
-                            .map(|p| p.price)
-                            .unwrap_or(0.0)
-                            * asset.amount
-                    }
-                    _ => 0.0,
-                }
+    /// assets left out of the totals because no market connects them to `symbol`.
+    pub fn unpriced_assets(&self, symbol: &str) -> Vec<Asset> {
+        self.valuations_in(symbol)
+            .into_iter()
+            .filter(|(_, conversion)| conversion.is_none())
+            .map(|(asset, _)| asset)
+            .collect()
+    }
+
+    pub fn _total_value_in(&self, symbol: &str) -> Decimal {
+        self.valuations_in(symbol)
+            .into_iter()
+            .map(|(asset, conversion)| {
+                conversion.map_or(Decimal::ZERO, |c| c.convert(asset.amount))
             })
             .sum()
     }
 
-    pub fn total_value_in_usd(&self) -> f64 {
-        let btc_price = Price::find_first_btc_usd_price(&self.prices).map_or(0.0, |p| p.price);
+    pub fn total_value_in_btc(&self) -> Decimal {
+        self._total_value_in(BTC_SYMBOL)
+    }
 
-        self.assets
-            .iter()
-            .map({
-                |asset| match asset.asset_type() {
-                    AssetType::Bitcoin => asset.amount * btc_price,
-                    AssetType::Stablecoin => asset.amount,
-                    AssetType::Altcoin => {
-                        Price::find_first_btc_price_for_symbol(&asset.symbol, self.prices.clone())
-                            .map(|p| p.price)
-                            .unwrap_or(
-                                Price::find_first_btc_price_for_symbol(
-                                    &asset.symbol,
-                                    self.prices.clone(),
-                                )
-                                .map_or(0.0, |p| p.price * btc_price),
-                            )
-                            * asset.amount
-                    }
-                }
+    pub fn alts_value_in_btc(&self) -> Decimal {
+        self.valuations_in(BTC_SYMBOL)
+            .into_iter()
+            .filter(|(asset, _)| asset.asset_type() == AssetType::Altcoin)
+            .map(|(asset, conversion)| {
+                conversion.map_or(Decimal::ZERO, |c| c.convert(asset.amount))
             })
             .sum()
-
-        // self.total_value_in_btc()
-        //     * Pair::find_first_btc_usd_pair(&self.pairs)
-        //         .map(|p| p.price)
-        //         .unwrap_or(0.0)
     }
 
-    // pub fn asset_presenters(&self) -> Vec<AsssetPresener> {
-
-    // }
+    /// stablecoins count at par, everything else goes through the conversion graph.
+    pub fn total_value_in_usd(&self) -> Decimal {
+        self.valuations_in(USD_SYMBOL)
+            .into_iter()
+            .map(|(asset, conversion)| match asset.asset_type() {
+                AssetType::Stablecoin => asset.amount,
+                _ => conversion.map_or(Decimal::ZERO, |c| c.convert(asset.amount)),
+            })
+            .sum()
+    }
 }
 
+static BTC_SYMBOL: &str = "BTC";
+static USD_SYMBOL: &str = "USDT";
+
 // #[test]
 // fn test_asset_presenter_new() {
 //     use exchanges::*;
@@ -1,4 +1,5 @@
 use crate::models::*;
+use rust_decimal::Decimal;
 
 #[derive(Debug, Clone)]
 pub struct OrderPresenter {
@@ -7,10 +8,10 @@ pub struct OrderPresenter {
 }
 
 impl OrderPresenter {
-    pub fn current_value_in_btc(&self) -> f64 {
+    pub fn current_value_in_btc(&self) -> Decimal {
         self.order.qty
             * Price::find_first_btc_price_for_symbol(&self.order.pair.symbol, self.prices.clone())
-                .map_or(0.0, |p| p.price)
+                .map_or(Decimal::ZERO, |p| p.price)
     }
 
     //    pub fn current_value_in_usd(&self) -> f64 {
@@ -0,0 +1,253 @@
+use crate::exchanges::*;
+use crate::models::*;
+use log::warn;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+/// Where the price of an asset came from, eg. `binance: XYZ -> ETH -> BTC`.
+#[derive(Debug, Clone)]
+pub struct PriceSource {
+    pub exchange: Exchange,
+    pub conversion: Conversion,
+}
+
+/// The part of an asset held on one exchange.
+#[derive(Debug, Clone)]
+pub struct ExchangeHolding {
+    pub exchange: Exchange,
+    pub amount: Decimal,
+    pub locked: Decimal,
+}
+
+impl ExchangeHolding {
+    pub fn free(&self) -> Decimal {
+        self.amount - self.locked
+    }
+}
+
+/// One asset merged across every exchange it is held on.
+#[derive(Debug, Clone)]
+pub struct PortfolioAsset {
+    pub symbol: String,
+    pub holdings: Vec<ExchangeHolding>,
+    pub btc_source: Option<PriceSource>,
+    pub usd_source: Option<PriceSource>,
+}
+
+impl PortfolioAsset {
+    pub fn amount(&self) -> Decimal {
+        self.holdings.iter().map(|h| h.amount).sum()
+    }
+
+    pub fn locked(&self) -> Decimal {
+        self.holdings.iter().map(|h| h.locked).sum()
+    }
+
+    pub fn free(&self) -> Decimal {
+        self.amount() - self.locked()
+    }
+
+    pub fn price_in_btc(&self) -> Option<Decimal> {
+        self.btc_source.as_ref().map(|s| s.conversion.rate)
+    }
+
+    /// stablecoins count at par.
+    pub fn price_in_usd(&self) -> Option<Decimal> {
+        match AssetType::from_symbol(&self.symbol) {
+            AssetType::Stablecoin => Some(Decimal::ONE),
+            _ => self.usd_source.as_ref().map(|s| s.conversion.rate),
+        }
+    }
+
+    pub fn value_in_btc(&self) -> Decimal {
+        self.price_in_btc()
+            .map_or(Decimal::ZERO, |price| price * self.amount())
+    }
+
+    pub fn value_in_usd(&self) -> Decimal {
+        self.price_in_usd()
+            .map_or(Decimal::ZERO, |price| price * self.amount())
+    }
+
+    pub fn holding(&self, exchange: Exchange) -> Option<&ExchangeHolding> {
+        self.holdings.iter().find(|h| h.exchange == exchange)
+    }
+}
+
+/// Balances from every exchange merged per asset and valued with the best price available on
+/// any of them.
+#[derive(Debug, Clone)]
+pub struct PortfolioPresenter {
+    pub assets: Vec<PortfolioAsset>,
+    /// exchanges that could not be fetched, left out of the totals.
+    pub errors: Vec<(Exchange, String)>,
+}
+
+impl PortfolioPresenter {
+    /// `balances` holds the assets and prices fetched from each exchange.
+    pub fn new(balances: Vec<(Exchange, Vec<Asset>, Vec<Price>)>) -> Self {
+        let graphs: Vec<(Exchange, ConversionGraph)> = balances
+            .iter()
+            .map(|(exchange, _, prices)| (*exchange, ConversionGraph::from_prices(prices)))
+            .collect();
+
+        let mut holdings: BTreeMap<String, Vec<ExchangeHolding>> = BTreeMap::new();
+        for (exchange, assets, _) in &balances {
+            for asset in assets {
+                holdings
+                    .entry(asset.symbol.to_uppercase())
+                    .or_insert_with(Vec::new)
+                    .push(ExchangeHolding {
+                        exchange: *exchange,
+                        amount: asset.amount,
+                        locked: asset.locked,
+                    });
+            }
+        }
+
+        let mut assets: Vec<PortfolioAsset> = holdings
+            .into_iter()
+            .map(|(symbol, holdings)| PortfolioAsset {
+                btc_source: best_source(&graphs, &symbol, BTC_SYMBOL, &holdings),
+                usd_source: best_source(&graphs, &symbol, USD_SYMBOL, &holdings),
+                symbol,
+                holdings,
+            })
+            .collect();
+
+        assets.sort_by(|a, b| b.value_in_btc().cmp(&a.value_in_btc()));
+        PortfolioPresenter {
+            assets,
+            errors: Vec::new(),
+        }
+    }
+
+    /// fetches balances and prices from every exchange, eg. from `configured_exchanges`. An
+    /// exchange that fails is recorded in `errors` and the others are still shown.
+    pub fn fetch(exchanges: &[Box<dyn ExchangeAPI>]) -> Self {
+        let mut balances = Vec::new();
+        let mut errors = Vec::new();
+
+        for api in exchanges {
+            let fetched = api
+                .balances()
+                .and_then(|assets| api.all_prices().map(|prices| (assets, prices)));
+
+            match fetched {
+                Ok((assets, prices)) => balances.push((api.exchange(), assets, prices)),
+                Err(e) => {
+                    warn!("{}: cannot fetch balances: {}", api.display(), e);
+                    errors.push((api.exchange(), e.to_string()));
+                }
+            }
+        }
+
+        PortfolioPresenter {
+            errors,
+            ..Self::new(balances)
+        }
+    }
+
+    pub fn asset(&self, symbol: &str) -> Option<&PortfolioAsset> {
+        self.assets
+            .iter()
+            .find(|a| a.symbol == symbol.to_uppercase())
+    }
+
+    pub fn total_value_in_btc(&self) -> Decimal {
+        self.assets.iter().map(|a| a.value_in_btc()).sum()
+    }
+
+    pub fn total_value_in_usd(&self) -> Decimal {
+        self.assets.iter().map(|a| a.value_in_usd()).sum()
+    }
+
+    pub fn locked_value_in_btc(&self) -> Decimal {
+        self.assets
+            .iter()
+            .map(|a| a.price_in_btc().map_or(Decimal::ZERO, |p| p * a.locked()))
+            .sum()
+    }
+
+    pub fn free_value_in_btc(&self) -> Decimal {
+        self.total_value_in_btc() - self.locked_value_in_btc()
+    }
+
+    /// share of the portfolio's btc value held in an asset, as a percentage.
+    pub fn allocation(&self, symbol: &str) -> Decimal {
+        self.asset(symbol)
+            .map_or(Decimal::ZERO, |asset| self.allocation_of(asset))
+    }
+
+    /// every asset with its allocation, largest first.
+    pub fn allocations(&self) -> Vec<(String, Decimal)> {
+        self.assets
+            .iter()
+            .map(|asset| (asset.symbol.clone(), self.allocation_of(asset)))
+            .collect()
+    }
+
+    /// share of the portfolio's btc value held on each exchange, as a percentage.
+    pub fn exchange_allocations(&self) -> Vec<(Exchange, Decimal)> {
+        let mut values: Vec<(Exchange, Decimal)> = Vec::new();
+
+        for asset in &self.assets {
+            let price = asset.price_in_btc().unwrap_or(Decimal::ZERO);
+            for holding in &asset.holdings {
+                match values.iter_mut().find(|(e, _)| *e == holding.exchange) {
+                    Some((_, value)) => *value += holding.amount * price,
+                    None => values.push((holding.exchange, holding.amount * price)),
+                }
+            }
+        }
+
+        let total = self.total_value_in_btc();
+        values
+            .into_iter()
+            .map(|(exchange, value)| (exchange, percent_of(value, total)))
+            .collect()
+    }
+
+    /// assets that no exchange has a market for, left out of the totals.
+    pub fn unpriced_assets(&self) -> Vec<&PortfolioAsset> {
+        self.assets
+            .iter()
+            .filter(|a| a.btc_source.is_none())
+            .collect()
+    }
+
+    fn allocation_of(&self, asset: &PortfolioAsset) -> Decimal {
+        percent_of(asset.value_in_btc(), self.total_value_in_btc())
+    }
+}
+
+/// The most direct route on any exchange, preferring an exchange the asset is held on when
+/// routes are equally short.
+fn best_source(
+    graphs: &[(Exchange, ConversionGraph)],
+    symbol: &str,
+    to: &str,
+    holdings: &[ExchangeHolding],
+) -> Option<PriceSource> {
+    graphs
+        .iter()
+        .filter_map(|(exchange, graph)| {
+            graph.conversion(symbol, to).map(|conversion| PriceSource {
+                exchange: *exchange,
+                conversion,
+            })
+        })
+        .min_by_key(|source| {
+            let held_here = holdings.iter().any(|h| h.exchange == source.exchange);
+            (source.conversion.hops(), !held_here)
+        })
+}
+
+fn percent_of(value: Decimal, total: Decimal) -> Decimal {
+    value
+        .checked_div(total)
+        .map_or(Decimal::ZERO, |share| share * Decimal::new(100, 0))
+}
+
+static BTC_SYMBOL: &str = "BTC";
+static USD_SYMBOL: &str = "USDT";
@@ -1,137 +1,191 @@
-//use crate::models::*;
-//use crate::utils::*;
-//use chrono::{offset::Local, prelude::DateTime};
-//
-//#[derive(Debug, Clone)]
-//pub struct PositionPresenter {
-//    pub position: Position,
-//    pub prices: Vec<Price>,
-//    pub btc_price_in_usd: f64,
-//}
-//
-//impl PositionPresenter {
-//    //    pub fn price_in(&self, symbol: &str) -> Option<f64> {
-//    //        Price::
-//    //        find_pair_by_symbol_and_base(&self.symbol(), symbol, self.prices.clone()).map(|p| p.price)
-//    //    }
-//
-//    //    pub fn value_in(&self, symbol: &str) -> Option<f64> {
-//    //        if let Some(price) = self.price_in(symbol) {
-//    //            Some(price * self.qty())
-//    //        } else {
-//    //            None
-//    //        }
-//    //    }
-//
-//    pub fn time(&self) -> Option<DateTime<Local>> {
-//        self.position.trades.first().map(|t| t.time)
-//    }
-//
-//    pub fn qty(&self) -> f64 {
-//        self.position.qty()
-//    }
-//
-//    pub fn current_price_in_btc(&self) -> f64 {
-//        find_pair_by_symbol_and_base(&self.symbol(), "BTC", self.pairs.clone())
-//            .map(|p| p.price)
-//            .unwrap_or(0.0)
-//    }
-//
-//    // note: maybe discriminate based on trade types...
-//    pub fn current_price_in_usd(&self) -> f64 {
-//        find_pair_by_symbol_and_base(&self.symbol(), "USDT", self.pairs.clone())
-//            .map(|p| p.price)
-//            .unwrap_or(self.current_price_in_btc() * self.btc_price_in_usd)
-//    }
-//
-//    pub fn symbol(&self) -> String {
-//        self.position.symbol()
-//    }
-//
-//    pub fn is_valid(&self) -> bool {
-//        self.position.remaining_qty() == self.position.asset.amount
-//    }
-//
-//    // pub fn order_presenters(&self) -> Vec<OrderPresenter> {
-//    //     self.position
-//    //         .trades
-//    //         .clone()
-//    //         .into_iter()
-//    //         .map(|t| OrderPresenter {
-//    //             order: t,
-//    //             btc_value: 55.0,
-//    //         })
-//    //         .collect()
-//    // }
-//
-//    // pub fn pair_positions(&self) -> Vec<PairPosition> {}
-//
-//    // delete
-//    pub fn current_value_in_btc(&self) -> f64 {
-//        self.qty() * self.current_price_in_btc()
-//    }
-//
-//    // delete
-//    pub fn current_value_in_usd(&self) -> f64 {
-//        self.current_value_in_btc() * self.btc_price_in_usd
-//    }
-//
-//    pub fn entry_price_in_btc(&self) -> f64 {
-//        //fixme: tech debt
-//        match self.position.base_type().unwrap_or(AssetType::Bitcoin) {
-//            AssetType::Bitcoin => self.position.entry_price(),
-//            _ => self.position.entry_price() / self.btc_price_in_usd,
-//        }
-//    }
-//
-//    pub fn entry_price_in_usd(&self) -> f64 {
-//        //fixme: tech debt
-//        match self.position.base_type().unwrap_or(AssetType::Bitcoin) {
-//            AssetType::Fiat => self.position.entry_price(),
-//            _ => self.position.entry_price() * self.btc_price_in_usd,
-//        }
-//    }
-//
-//    pub fn percent_change(&self) -> f64 {
-//        price_percent(self.entry_price_in_btc(), self.current_price_in_btc())
-//    }
-//
-//    /// price of remaining units at the current price - those units at buy price
-//    pub fn unrealised_profit_btc(&self) -> f64 {
-//        (self.current_price_in_btc() - self.entry_price_in_btc()) * self.position.remaining_qty()
-//    }
-//
-//    pub fn unrealised_profit_usd(&self) -> f64 {
-//        (self.current_price_in_usd() * self.position.remaining_qty())
-//            - (self.entry_price_in_usd() * self.position.remaining_qty())
-//    }
-//
-//    pub fn realised_profit_btc(&self) -> f64 {
-//        if let Some(exit_price) = self.position.exit_price() {
-//            ((exit_price * self.position.sell_qty())
-//                - (self.position.entry_price() * self.position.sell_qty()))
-//        } else {
-//            0.0
-//        }
-//    }
-//
-//    pub fn realised_profit_usd(&self) -> f64 {
-//        self.realised_profit_btc() * self.current_price_in_usd() // WRONG
-//    }
-//
-//    pub fn realised_profit_percent(&self) -> f64 {
-//        if let Some(exit_price) = self.position.exit_price() {
-//            price_percent(self.position.entry_price(), exit_price)
-//        } else {
-//            0.0
-//        }
-//    }
-//}
-//
-//// delete
-//pub fn total_btc_staked(presenters: Vec<PositionPresenter>) -> f64 {
-//    presenters
-//        .into_iter()
-//        .map(|a| a.current_value_in_btc())
-//        .sum()
-//}
+use crate::error::*;
+use crate::exchanges::ExchangeAPI;
+use crate::models::*;
+use crate::utils::*;
+use chrono::{offset::Local, prelude::DateTime};
+use log::warn;
+use rust_decimal::Decimal;
+
+/// A position in one pair rebuilt from its trades, valued at current prices. Profits are worked
+/// out in the pair's quote currency, then converted to BTC and USD at today's rates. Fees paid in
+/// another asset (eg. BNB) count in the cost basis, valued at today's rates as well.
+#[derive(Debug, Clone)]
+pub struct PositionPresenter {
+    pub position: Position,
+    pub prices: Vec<Price>,
+    /// how much of the asset's balance the trades of all its pairs leave unexplained, eg. from
+    /// deposits, withdrawals or trades too old to fetch. `None` when they agree.
+    pub untracked: Option<Decimal>,
+    ledger: LotLedger,
+    graph: ConversionGraph,
+}
+
+impl PositionPresenter {
+    pub fn new(
+        position: Position,
+        prices: Vec<Price>,
+        method: CostBasisMethod,
+    ) -> CoreResult<Self> {
+        let graph = prices.conversions();
+        Self::with_conversions(position, prices, graph, method)
+    }
+
+    /// like `new`, with the prices' conversions built once for every position valued at them.
+    fn with_conversions(
+        position: Position,
+        prices: Vec<Price>,
+        graph: ConversionGraph,
+        method: CostBasisMethod,
+    ) -> CoreResult<Self> {
+        let ledger = position.net_ledger(method, &graph)?;
+
+        Ok(PositionPresenter {
+            position,
+            prices,
+            untracked: None,
+            ledger,
+            graph,
+        })
+    }
+
+    /// Rebuilds a position for every pair of every asset held on the exchange from its trade
+    /// history. Pairs whose trades cannot be fetched are skipped with a warning. The units held
+    /// according to the trades of an asset's pairs are checked against its balance, see
+    /// `untracked`.
+    pub fn for_exchange(
+        exchange: &dyn ExchangeAPI,
+        method: CostBasisMethod,
+    ) -> CoreResult<Vec<Self>> {
+        let prices = exchange.all_prices()?;
+        let graph = prices.conversions();
+        let pairs = exchange.all_pairs()?;
+        let mut positions = Vec::new();
+
+        for asset in exchange.balances()?.filter_zero_balances() {
+            let held = asset.symbol.to_uppercase();
+            let mut asset_positions: Vec<Self> = Vec::new();
+
+            for pair in pairs
+                .iter()
+                .filter(|pair| pair.symbol.to_uppercase() == held)
+            {
+                let trades = match exchange.trades_for_pair(pair.clone()) {
+                    Ok(trades) => trades,
+                    Err(e) => {
+                        warn!("{}: trades for {}: {}", exchange.display(), pair, e);
+                        continue;
+                    }
+                };
+                if trades.is_empty() {
+                    continue;
+                }
+
+                let position = Position::new(trades, asset.clone())?;
+                asset_positions.push(Self::with_conversions(
+                    position,
+                    prices.clone(),
+                    graph.clone(),
+                    method,
+                )?);
+            }
+
+            let tracked: Decimal = asset_positions.iter().map(|p| p.qty()).sum();
+            let untracked = asset.amount - tracked;
+            if !asset_positions.is_empty() && !untracked.is_zero() {
+                warn!(
+                    "{}: {} balance is {} but its trades account for {}",
+                    exchange.display(),
+                    held,
+                    asset.amount,
+                    tracked
+                );
+                for position in &mut asset_positions {
+                    position.untracked = Some(untracked);
+                }
+            }
+            positions.append(&mut asset_positions);
+        }
+        Ok(positions)
+    }
+
+    pub fn ledger(&self) -> &LotLedger {
+        &self.ledger
+    }
+
+    pub fn pair(&self) -> Pair {
+        self.position.trades[0].pair.clone()
+    }
+
+    pub fn symbol(&self) -> String {
+        self.position.symbol()
+    }
+
+    /// when the first trade was made.
+    pub fn time(&self) -> Option<DateTime<Local>> {
+        self.position.trades.iter().map(|t| t.time).min()
+    }
+
+    /// units still held according to the trades.
+    pub fn qty(&self) -> Decimal {
+        self.ledger.qty()
+    }
+
+    /// cost per unit still held, in the quote currency.
+    pub fn entry_price(&self) -> Decimal {
+        self.ledger.average_cost()
+    }
+
+    /// in the quote currency, `None` when the exchange has no price for the pair.
+    pub fn current_price(&self) -> Option<Decimal> {
+        let pair = self.pair();
+        self.prices
+            .iter()
+            .find(|price| price.pair == pair)
+            .map(|price| price.price)
+    }
+
+    pub fn percent_change(&self) -> Option<Decimal> {
+        self.current_price()
+            .map(|current| price_percent(self.entry_price(), current))
+    }
+
+    /// in the quote currency.
+    pub fn unrealized_pnl(&self) -> Option<Decimal> {
+        self.current_price()
+            .map(|current| self.ledger.unrealized_pnl(current))
+    }
+
+    /// in the quote currency.
+    pub fn realized_pnl(&self) -> Decimal {
+        self.ledger.realized_pnl()
+    }
+
+    /// what the units still held are worth, in `currency`.
+    pub fn value_in(&self, currency: &str) -> Option<Decimal> {
+        self.current_price()
+            .and_then(|current| self.quote_in(self.qty() * current, currency))
+    }
+
+    pub fn unrealized_pnl_in(&self, currency: &str) -> Option<Decimal> {
+        self.unrealized_pnl()
+            .and_then(|pnl| self.quote_in(pnl, currency))
+    }
+
+    pub fn realized_pnl_in(&self, currency: &str) -> Option<Decimal> {
+        self.quote_in(self.realized_pnl(), currency)
+    }
+
+    /// converts an amount of the quote currency, stablecoins count at par with USDT.
+    fn quote_in(&self, amount: Decimal, currency: &str) -> Option<Decimal> {
+        let quote = self.pair().base.to_uppercase();
+        let currency = currency.to_uppercase();
+
+        let at_par = AssetType::from_symbol(&quote) == AssetType::Stablecoin
+            && AssetType::from_symbol(&currency) == AssetType::Stablecoin;
+        if quote == currency || at_par {
+            return Some(amount);
+        }
+
+        self.graph.convert(amount, &quote, &currency)
+    }
+}
@@ -1,5 +1,7 @@
 use crate::models::*;
 use crate::utils::price_percent;
+use log::warn;
+use rust_decimal::Decimal;
 // use crate::utils::*;
 
 #[derive(Debug, Clone)]
@@ -7,11 +9,44 @@ pub struct TradePresenter {
     pub trade: Trade,
     // pub trades: Vec<Vec<Trade>>,
     pub fiat_pair: Pair,
+    /// the price of `fiat_pair`, one when the trade was made in fiat.
+    pub fiat_price: Decimal,
     //    prices: Vec<Price>,
-    pub price_in_trade_currency: f64,
+    pub price_in_trade_currency: Decimal,
+    /// the fee in the currency the trade was made in, see `Trade::fee_in_quote`.
+    pub fee_in_trade_currency: Decimal,
 }
 
 impl TradePresenter {
+    /// `price_in_trade_currency` and `fiat_price` are current prices, `prices` values a fee paid
+    /// in another asset at the time of the trade, eg. a `PriceHistory`. A fee that cannot be
+    /// valued counts as zero.
+    pub fn new(
+        trade: Trade,
+        fiat_pair: Pair,
+        fiat_price: Decimal,
+        price_in_trade_currency: Decimal,
+        prices: &dyn HistoricalPrices,
+    ) -> Self {
+        let fee_in_trade_currency = trade.fee_in_quote(prices).unwrap_or_else(|| {
+            warn!(
+                "no price for the {} {} fee of trade {}",
+                trade.fee,
+                trade.fee_symbol.clone().unwrap_or_default(),
+                trade.id
+            );
+            Decimal::ZERO
+        });
+
+        TradePresenter {
+            trade,
+            fiat_pair,
+            fiat_price,
+            price_in_trade_currency,
+            fee_in_trade_currency,
+        }
+    }
+
     // pub fn current_value_in_fiat(&self) -> Option<f64> {
 
     // }
@@ -48,15 +83,44 @@ impl TradePresenter {
 
     // the current price in the currency the trade was made in, eg if the trade was in btc,
     // this returns the current price in btc.
-    pub fn current_price(&self) -> f64 {
+    pub fn current_price(&self) -> Decimal {
         self.price_in_trade_currency
     }
 
-    pub fn profit_as_percent(&self) -> f64 {
+    pub fn profit_as_percent(&self) -> Decimal {
         price_percent(self.trade.sale_price, self.current_price())
     }
 
-    pub fn current_cost_in_fiat(&self) -> f64 {
-        0.0
+    /// what the trade cost (buy) or returned (sell) after fees.
+    pub fn net_cost(&self) -> Decimal {
+        match self.trade.trade_type {
+            TradeType::Buy => self.trade.sale_cost() + self.fee_in_trade_currency,
+            TradeType::Sell => self.trade.sale_cost() - self.fee_in_trade_currency,
+        }
+    }
+
+    /// profit at the current price once fees are paid, in the currency the trade was made in.
+    pub fn net_profit(&self) -> Decimal {
+        let current_cost = self.trade.qty * self.current_price();
+        match self.trade.trade_type {
+            TradeType::Buy => current_cost - self.net_cost(),
+            TradeType::Sell => self.net_cost() - current_cost,
+        }
+    }
+
+    pub fn net_profit_as_percent(&self) -> Decimal {
+        let net_price = self
+            .net_cost()
+            .checked_div(self.trade.qty)
+            .unwrap_or(Decimal::ZERO);
+        match self.trade.trade_type {
+            TradeType::Buy => price_percent(net_price, self.current_price()),
+            TradeType::Sell => price_percent(self.current_price(), net_price),
+        }
+    }
+
+    /// what the traded quantity is worth at the current price, in fiat.
+    pub fn current_cost_in_fiat(&self) -> Decimal {
+        self.trade.qty * self.current_price() * self.fiat_price
     }
 }
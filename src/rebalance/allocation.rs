@@ -0,0 +1,117 @@
+use crate::error::*;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::{fmt, fmt::Display};
+
+/// The share of a portfolio each asset should make up. Weights are relative, `50/30/20` and
+/// `0.5/0.3/0.2` are the same allocation.
+///
+/// ```rust,ignore
+/// let target: TargetAllocation = "BTC=50,ETH=30,USDT=20".parse()?;
+/// let target = TargetAllocation::new()
+///     .weight("BTC", Decimal::new(50, 0))
+///     .weight("ETH", Decimal::new(30, 0))
+///     .weight("USDT", Decimal::new(20, 0));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TargetAllocation {
+    weights: BTreeMap<String, Decimal>,
+}
+
+impl TargetAllocation {
+    pub fn new() -> Self {
+        TargetAllocation::default()
+    }
+
+    pub fn weight(mut self, asset: &str, weight: Decimal) -> Self {
+        self.weights.insert(asset.to_uppercase(), weight);
+        self
+    }
+
+    pub fn assets(&self) -> Vec<String> {
+        self.weights.keys().cloned().collect()
+    }
+
+    pub fn contains(&self, asset: &str) -> bool {
+        self.weights.contains_key(&asset.to_uppercase())
+    }
+
+    /// the asset's share of the portfolio, from 0 to 1.
+    pub fn fraction(&self, asset: &str) -> Decimal {
+        let total: Decimal = self.weights.values().sum();
+        self.weights
+            .get(&asset.to_uppercase())
+            .and_then(|weight| weight.checked_div(total))
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    pub fn validate(&self) -> CoreResult<()> {
+        if self.weights.is_empty() {
+            return Err(Box::new(TrailerError::MissingArgumentError(
+                "a target allocation needs at least one asset".to_string(),
+            )));
+        }
+
+        if let Some((asset, weight)) = self.weights.iter().find(|(_, w)| **w < Decimal::ZERO) {
+            return Err(Box::new(TrailerError::MissingArgumentError(format!(
+                "negative weight for {}: {}",
+                asset, weight
+            ))));
+        }
+
+        if self.weights.values().sum::<Decimal>() <= Decimal::ZERO {
+            return Err(Box::new(TrailerError::MissingArgumentError(
+                "target weights add up to zero".to_string(),
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TargetAllocation {
+    type Err = TrailerError;
+
+    /// `ASSET=WEIGHT` separated by commas, eg. `BTC=50,ETH=30,USDT=20`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut target = TargetAllocation::new();
+
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let mut parts = entry.splitn(2, '=');
+            let asset = parts.next().unwrap_or("").trim();
+            let weight = parts
+                .next()
+                .map(|w| w.trim().trim_end_matches('%'))
+                .and_then(|w| Decimal::from_str(w).ok());
+
+            match weight {
+                Some(weight) if !asset.is_empty() => target = target.weight(asset, weight),
+                _ => {
+                    return Err(TrailerError::MissingArgumentError(format!(
+                        "expected ASSET=WEIGHT, got {}",
+                        entry
+                    )))
+                }
+            }
+        }
+
+        Ok(target)
+    }
+}
+
+impl Display for TargetAllocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let entries: Vec<String> = self
+            .weights
+            .keys()
+            .map(|asset| {
+                format!(
+                    "{} {}%",
+                    asset,
+                    (self.fraction(asset) * Decimal::new(100, 0)).round_dp(2)
+                )
+            })
+            .collect();
+        write!(f, "{}", entries.join(", "))
+    }
+}
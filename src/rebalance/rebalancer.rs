@@ -0,0 +1,606 @@
+use crate::error::*;
+use crate::exchanges::{Exchange, ExchangeAPI};
+use crate::models::*;
+use crate::rebalance::*;
+use log::{info, warn};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::{fmt, fmt::Display};
+
+/// How one asset of the target stands.
+#[derive(Debug, Clone)]
+pub struct AssetAllocation {
+    pub asset: String,
+    pub amount: Decimal,
+    /// in the reference asset.
+    pub value: Decimal,
+    /// percent of the portfolio.
+    pub weight: Decimal,
+    /// percent of the portfolio.
+    pub target: Decimal,
+    /// `weight - target`, in percentage points.
+    pub drift: Decimal,
+}
+
+/// An order moving an asset towards its target.
+#[derive(Debug, Clone)]
+pub struct RebalanceTrade {
+    pub exchange: Exchange,
+    /// the exchange's native symbol.
+    pub symbol: String,
+    pub pair: Pair,
+    pub side: TradeType,
+    pub qty: Decimal,
+    /// the limit price, `slippage` past the market price.
+    pub price: Decimal,
+    /// in the quote asset.
+    pub value: Decimal,
+}
+
+impl Display for RebalanceTrade {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} @ {} ({} {})",
+            self.exchange.to_string(),
+            self.side,
+            self.qty,
+            self.symbol,
+            self.price,
+            self.value.round_dp(2),
+            self.pair.base
+        )
+    }
+}
+
+/// The trades that bring a portfolio back to its target allocation, sells first.
+#[derive(Debug, Clone)]
+pub struct RebalancePlan {
+    /// `None` for a plan across exchanges.
+    pub exchange: Option<Exchange>,
+    pub reference: String,
+    /// value of the assets in the target, in the reference asset.
+    pub total_value: Decimal,
+    pub allocations: Vec<AssetAllocation>,
+    pub trades: Vec<RebalanceTrade>,
+    /// assets off target that cannot be traded, and why.
+    pub skipped: Vec<String>,
+}
+
+impl RebalancePlan {
+    pub fn is_balanced(&self) -> bool {
+        self.trades.is_empty()
+    }
+}
+
+impl Display for RebalancePlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} {}",
+            self.exchange.map_or("all".to_string(), |e| e.to_string()),
+            self.total_value.round_dp(2),
+            self.reference
+        )?;
+
+        for allocation in &self.allocations {
+            writeln!(
+                f,
+                "  {:<6} {:>14} {:>8}% target {:>6}% drift {:>7}%",
+                allocation.asset,
+                allocation.value.round_dp(2),
+                allocation.weight.round_dp(2),
+                allocation.target.round_dp(2),
+                allocation.drift.round_dp(2)
+            )?;
+        }
+        for trade in &self.trades {
+            writeln!(f, "  {}", trade)?;
+        }
+        for skipped in &self.skipped {
+            writeln!(f, "  skipped: {}", skipped)?;
+        }
+        Ok(())
+    }
+}
+
+/// A trade sent by `Rebalancer::execute`, and how sending it went.
+#[derive(Debug, Clone)]
+pub struct RebalanceOrder {
+    pub trade: RebalanceTrade,
+    pub error: Option<String>,
+}
+
+struct Holdings {
+    exchange: Exchange,
+    instruments: Arc<InstrumentMap>,
+    graph: ConversionGraph,
+    /// total and free amounts by asset.
+    balances: BTreeMap<String, (Decimal, Decimal)>,
+}
+
+/// Works out and sends the trades that bring balances back to a `TargetAllocation`, one exchange
+/// at a time or across all of them. Every asset is traded against the reference asset, which
+/// takes up the difference. Assets outside the target are left alone and don't count towards the
+/// total, and assets within `drift_threshold` of their target aren't traded.
+///
+/// ```rust,ignore
+/// let rebalancer = Rebalancer::new("BTC=50,ETH=30,USDT=20".parse()?).drift_threshold(Decimal::new(5, 0));
+/// let plan = rebalancer.plan(&binance)?;
+/// println!("{}", plan);
+/// rebalancer.execute(&binance, &plan);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rebalancer {
+    target: TargetAllocation,
+    reference: String,
+    drift_threshold: Decimal,
+    slippage: Decimal,
+}
+
+impl Rebalancer {
+    pub fn new(target: TargetAllocation) -> Self {
+        Rebalancer {
+            target,
+            reference: "USDT".to_string(),
+            drift_threshold: Decimal::new(5, 0),
+            slippage: Decimal::new(5, 3),
+        }
+    }
+
+    /// the asset everything is valued in and traded against, USDT unless set.
+    pub fn reference(mut self, asset: &str) -> Self {
+        self.reference = asset.to_uppercase();
+        self
+    }
+
+    /// how far an asset may drift from its target before it is traded, in percentage points.
+    pub fn drift_threshold(mut self, drift_threshold: Decimal) -> Self {
+        self.drift_threshold = drift_threshold;
+        self
+    }
+
+    /// how far past the market price orders are priced, eg. `0.005` for 0.5%.
+    pub fn slippage(mut self, slippage: Decimal) -> Self {
+        self.slippage = slippage;
+        self
+    }
+
+    pub fn target(&self) -> &TargetAllocation {
+        &self.target
+    }
+
+    /// the trades that bring one exchange to the target on its own.
+    pub fn plan(&self, exchange: &dyn ExchangeAPI) -> CoreResult<RebalancePlan> {
+        self.target.validate()?;
+        let holdings = vec![self.holdings(exchange)?];
+        Ok(self.build(Some(exchange.exchange()), &holdings))
+    }
+
+    /// The trades that bring the exchanges together to the target. Sells are shared out by how
+    /// much of the asset each exchange holds and buys by how much of the reference asset is free.
+    /// Exchanges that cannot be read are left out and reported in `skipped`.
+    pub fn plan_global(&self, exchanges: &[Box<dyn ExchangeAPI>]) -> CoreResult<RebalancePlan> {
+        self.target.validate()?;
+        let mut holdings = Vec::new();
+        let mut failed = Vec::new();
+
+        for exchange in exchanges {
+            match self.holdings(exchange.as_ref()) {
+                Ok(book) => holdings.push(book),
+                Err(e) => {
+                    warn!("rebalance: {}: {}", exchange.display(), e);
+                    failed.push(format!("{}: {}", exchange.exchange().to_string(), e));
+                }
+            }
+        }
+
+        let mut plan = self.build(None, &holdings);
+        failed.append(&mut plan.skipped);
+        plan.skipped = failed;
+        Ok(plan)
+    }
+
+    /// Sends the plan's trades for `exchange` as limit orders, sells first. Buys may fail for lack
+    /// of funds while the sells are still filling; plan again once they have.
+    pub fn execute(&self, exchange: &dyn ExchangeAPI, plan: &RebalancePlan) -> Vec<RebalanceOrder> {
+        plan.trades
+            .iter()
+            .filter(|trade| trade.exchange == exchange.exchange())
+            .map(|trade| {
+                let result = match trade.side {
+                    TradeType::Buy => exchange.limit_buy(&trade.symbol, trade.qty, trade.price),
+                    TradeType::Sell => exchange.limit_sell(&trade.symbol, trade.qty, trade.price),
+                };
+
+                match &result {
+                    Ok(()) => info!("rebalance: {}", trade),
+                    Err(e) => warn!("rebalance: {} failed: {}", trade, e),
+                }
+
+                RebalanceOrder {
+                    trade: trade.clone(),
+                    error: result.err().map(|e| e.to_string()),
+                }
+            })
+            .collect()
+    }
+
+    fn holdings(&self, exchange: &dyn ExchangeAPI) -> CoreResult<Holdings> {
+        let mut balances = BTreeMap::new();
+        for asset in exchange.balances()? {
+            let entry = balances
+                .entry(asset.symbol.to_uppercase())
+                .or_insert((Decimal::ZERO, Decimal::ZERO));
+            entry.0 += asset.amount;
+            entry.1 += asset.amount - asset.locked;
+        }
+
+        Ok(Holdings {
+            exchange: exchange.exchange(),
+            instruments: exchange.instruments()?,
+            graph: ConversionGraph::from_prices(&exchange.all_prices()?),
+            balances,
+        })
+    }
+
+    fn price(&self, holdings: &Holdings, asset: &str) -> Option<Decimal> {
+        if asset == self.reference {
+            Some(Decimal::ONE)
+        } else {
+            holdings.graph.convert(Decimal::ONE, asset, &self.reference)
+        }
+    }
+
+    fn build(&self, exchange: Option<Exchange>, books: &[Holdings]) -> RebalancePlan {
+        let mut skipped = Vec::new();
+        // value of each target asset on each exchange.
+        let mut values: Vec<BTreeMap<String, Decimal>> = Vec::new();
+
+        for book in books {
+            let mut book_values = BTreeMap::new();
+            for asset in self.target.assets() {
+                let amount = book.balances.get(&asset).map_or(Decimal::ZERO, |b| b.0);
+                match self.price(book, &asset) {
+                    Some(price) => {
+                        book_values.insert(asset, amount * price);
+                    }
+                    None if amount.is_zero() => {
+                        book_values.insert(asset, Decimal::ZERO);
+                    }
+                    None => skipped.push(format!(
+                        "{}: no price for {} in {}",
+                        book.exchange.to_string(),
+                        asset,
+                        self.reference
+                    )),
+                }
+            }
+            values.push(book_values);
+        }
+
+        let value_of =
+            |asset: &str| -> Decimal { values.iter().filter_map(|v| v.get(asset)).sum() };
+        let total_value: Decimal = self.target.assets().iter().map(|a| value_of(a)).sum();
+        let hundred = Decimal::new(100, 0);
+
+        let allocations: Vec<AssetAllocation> = self
+            .target
+            .assets()
+            .into_iter()
+            .map(|asset| {
+                let value = value_of(&asset);
+                let weight = (value * hundred)
+                    .checked_div(total_value)
+                    .unwrap_or(Decimal::ZERO);
+                let target = self.target.fraction(&asset) * hundred;
+                AssetAllocation {
+                    amount: books
+                        .iter()
+                        .filter_map(|book| book.balances.get(&asset))
+                        .map(|b| b.0)
+                        .sum(),
+                    value,
+                    weight,
+                    target,
+                    drift: weight - target,
+                    asset,
+                }
+            })
+            .collect();
+
+        let mut trades = Vec::new();
+        for allocation in &allocations {
+            if allocation.asset == self.reference || allocation.drift.abs() < self.drift_threshold {
+                continue;
+            }
+
+            let delta = self.target.fraction(&allocation.asset) * total_value - allocation.value;
+            let selling = delta < Decimal::ZERO;
+
+            // what each exchange brings to the trade: the asset for sells, free funds for buys,
+            // which count even when the reference asset is not part of the target.
+            let shares: Vec<Decimal> = books
+                .iter()
+                .zip(&values)
+                .map(|(book, v)| {
+                    if selling {
+                        v.get(&allocation.asset).cloned().unwrap_or(Decimal::ZERO)
+                    } else {
+                        book.balances
+                            .get(&self.reference)
+                            .map_or(Decimal::ZERO, |b| b.1)
+                    }
+                })
+                .collect();
+            let shares_total: Decimal = shares.iter().sum();
+
+            for (book, share) in books.iter().zip(shares) {
+                let portion = share
+                    .checked_div(shares_total)
+                    .unwrap_or(Decimal::ONE / Decimal::from(books.len() as u64));
+                if portion.is_zero() {
+                    continue;
+                }
+
+                match self.trade(book, &allocation.asset, delta * portion) {
+                    Ok(trade) => trades.push(trade),
+                    Err(reason) => skipped.push(format!(
+                        "{}: {} {}",
+                        book.exchange.to_string(),
+                        allocation.asset,
+                        reason
+                    )),
+                }
+            }
+        }
+
+        trades.sort_by_key(|trade| trade.side == TradeType::Buy);
+
+        RebalancePlan {
+            exchange,
+            reference: self.reference.clone(),
+            total_value,
+            allocations,
+            trades,
+            skipped,
+        }
+    }
+
+    /// an order changing the holding of `asset` by `delta`, in the reference asset.
+    fn trade(
+        &self,
+        book: &Holdings,
+        asset: &str,
+        delta: Decimal,
+    ) -> Result<RebalanceTrade, String> {
+        let pair = Pair::new(asset, &self.reference);
+        let symbol = book
+            .instruments
+            .pair_to_native_symbol(&pair)
+            .ok_or_else(|| format!("has no {} market", pair))?
+            .to_string();
+        let market_price = self
+            .price(book, asset)
+            .filter(|price| *price > Decimal::ZERO)
+            .ok_or_else(|| "has no price".to_string())?;
+        let rules = book.instruments.rules(&symbol).cloned().unwrap_or_default();
+
+        let (side, price) = if delta < Decimal::ZERO {
+            (
+                TradeType::Sell,
+                market_price * (Decimal::ONE - self.slippage),
+            )
+        } else {
+            (
+                TradeType::Buy,
+                market_price * (Decimal::ONE + self.slippage),
+            )
+        };
+        let price = rules.round_price(price);
+
+        let mut qty = delta.abs() / market_price;
+        if side == TradeType::Sell {
+            let free = book.balances.get(asset).map_or(Decimal::ZERO, |b| b.1);
+            qty = qty.min(free);
+        }
+        let qty = rules.round_qty(qty);
+
+        if let Some(reason) = rules.check(qty, price) {
+            return Err(reason);
+        }
+
+        Ok(RebalanceTrade {
+            exchange: book.exchange,
+            symbol,
+            pair,
+            side,
+            qty,
+            price,
+            value: qty * price,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    /// holdings on one exchange, balances as `(asset, total, free)` and prices in USDT.
+    fn book(
+        exchange: Exchange,
+        balances: &[(&str, &str, &str)],
+        prices: &[(&str, &str)],
+    ) -> Holdings {
+        let mut instruments = InstrumentMap::new(exchange);
+        let mut graph = ConversionGraph::default();
+        for (asset, price) in prices {
+            instruments.insert(&format!("{}USDT", asset), asset, "USDT");
+            graph.add_market(&Pair::new(asset, "USDT"), dec(price));
+        }
+
+        Holdings {
+            exchange,
+            instruments: Arc::new(instruments),
+            graph,
+            balances: balances
+                .iter()
+                .map(|(asset, total, free)| (asset.to_string(), (dec(total), dec(free))))
+                .collect(),
+        }
+    }
+
+    fn rebalancer(target: &str) -> Rebalancer {
+        Rebalancer::new(target.parse().unwrap()).slippage(Decimal::ZERO)
+    }
+
+    #[test]
+    fn drift_within_the_threshold_is_left_alone() {
+        let rebalancer = rebalancer("BTC=50,USDT=50");
+
+        let close = book(
+            Exchange::Binance,
+            &[("BTC", "1", "1"), ("USDT", "1040", "1040")],
+            &[("BTC", "1000")],
+        );
+        let plan = rebalancer.build(Some(Exchange::Binance), &[close]);
+        assert!(plan.is_balanced());
+        assert_eq!(plan.total_value, dec("2040"));
+
+        let off = book(
+            Exchange::Binance,
+            &[("BTC", "1", "1"), ("USDT", "1400", "1400")],
+            &[("BTC", "1000")],
+        );
+        let plan = rebalancer.build(Some(Exchange::Binance), &[off]);
+        assert_eq!(plan.trades.len(), 1);
+        assert_eq!(plan.trades[0].side, TradeType::Buy);
+        assert_eq!(plan.trades[0].symbol, "BTCUSDT");
+        assert_eq!(plan.trades[0].qty, dec("0.2"));
+    }
+
+    #[test]
+    fn sells_come_before_buys() {
+        let rebalancer = rebalancer("BTC=25,ETH=25,USDT=50");
+        let holdings = book(
+            Exchange::Binance,
+            &[("ETH", "10", "10"), ("USDT", "1000", "1000")],
+            &[("BTC", "1000"), ("ETH", "100")],
+        );
+
+        let plan = rebalancer.build(Some(Exchange::Binance), &[holdings]);
+        let trades: Vec<(String, TradeType, Decimal)> = plan
+            .trades
+            .iter()
+            .map(|t| (t.pair.symbol.clone(), t.side, t.qty))
+            .collect();
+        assert_eq!(
+            trades,
+            vec![
+                ("ETH".to_string(), TradeType::Sell, dec("5")),
+                ("BTC".to_string(), TradeType::Buy, dec("0.5")),
+            ]
+        );
+    }
+
+    #[test]
+    fn sells_are_capped_at_the_free_balance() {
+        let rebalancer = rebalancer("ETH=50,USDT=50");
+        let holdings = book(
+            Exchange::Binance,
+            &[("ETH", "20", "2"), ("USDT", "1000", "1000")],
+            &[("ETH", "100")],
+        );
+
+        let plan = rebalancer.build(Some(Exchange::Binance), &[holdings]);
+        assert_eq!(plan.trades.len(), 1);
+        assert_eq!(plan.trades[0].side, TradeType::Sell);
+        assert_eq!(plan.trades[0].qty, dec("2"));
+    }
+
+    #[test]
+    fn trades_below_the_market_rules_are_skipped() {
+        let rebalancer = rebalancer("BTC=50,USDT=50");
+        let mut holdings = book(
+            Exchange::Binance,
+            &[("USDT", "1000", "1000")],
+            &[("BTC", "1000")],
+        );
+        Arc::make_mut(&mut holdings.instruments).set_rules(
+            "BTCUSDT",
+            MarketRules {
+                min_notional: dec("1000"),
+                ..MarketRules::default()
+            },
+        );
+
+        let plan = rebalancer.build(Some(Exchange::Binance), &[holdings]);
+        assert!(plan.is_balanced());
+        assert_eq!(plan.skipped.len(), 1);
+        assert!(plan.skipped[0].starts_with("binance: BTC value 500"));
+    }
+
+    #[test]
+    fn reference_outside_the_target_funds_buys_without_counting() {
+        let rebalancer = rebalancer("BTC=50,ETH=50");
+        let holdings = book(
+            Exchange::Binance,
+            &[
+                ("BTC", "3", "3"),
+                ("ETH", "10", "10"),
+                ("USDT", "1000", "1000"),
+            ],
+            &[("BTC", "1000"), ("ETH", "100")],
+        );
+
+        let plan = rebalancer.build(Some(Exchange::Binance), &[holdings]);
+        assert_eq!(plan.total_value, dec("4000"));
+        assert_eq!(plan.allocations.len(), 2);
+        let trades: Vec<(String, TradeType, Decimal)> = plan
+            .trades
+            .iter()
+            .map(|t| (t.pair.symbol.clone(), t.side, t.qty))
+            .collect();
+        assert_eq!(
+            trades,
+            vec![
+                ("BTC".to_string(), TradeType::Sell, dec("1")),
+                ("ETH".to_string(), TradeType::Buy, dec("10")),
+            ]
+        );
+    }
+
+    #[test]
+    fn global_buys_go_where_the_funds_are() {
+        let rebalancer = rebalancer("BTC=50,ETH=50");
+        let funded = book(
+            Exchange::Binance,
+            &[("USDT", "1000", "1000")],
+            &[("BTC", "1000"), ("ETH", "100")],
+        );
+        let holding = book(
+            Exchange::Okex,
+            &[("BTC", "3", "3"), ("ETH", "10", "10")],
+            &[("BTC", "1000"), ("ETH", "100")],
+        );
+
+        let plan = rebalancer.build(None, &[funded, holding]);
+        let trades: Vec<(Exchange, TradeType, Decimal)> = plan
+            .trades
+            .iter()
+            .map(|t| (t.exchange, t.side, t.qty))
+            .collect();
+        assert_eq!(
+            trades,
+            vec![
+                (Exchange::Okex, TradeType::Sell, dec("1")),
+                (Exchange::Binance, TradeType::Buy, dec("10")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use crate::error::*;
+use crate::presenters::PositionPresenter;
+use rust_decimal::Decimal;
+use serde_derive::Serialize;
+use std::io::Write;
+use std::{fmt, fmt::Display};
+
+/// One position of a `PositionReport`. Amounts in BTC and USD are `None` when no market prices
+/// them.
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionRow {
+    pub exchange: String,
+    pub asset: String,
+    pub pair: String,
+    pub qty: Decimal,
+    /// in the pair's quote currency, as is the current price.
+    pub entry_price: Decimal,
+    pub current_price: Option<Decimal>,
+    pub percent_change: Option<Decimal>,
+    pub value_btc: Option<Decimal>,
+    pub value_usd: Option<Decimal>,
+    pub unrealized_btc: Option<Decimal>,
+    pub unrealized_usd: Option<Decimal>,
+    pub realized_btc: Option<Decimal>,
+    pub realized_usd: Option<Decimal>,
+    /// units of the asset's balance its trades don't account for, see
+    /// `PositionPresenter::untracked`.
+    pub untracked: Option<Decimal>,
+}
+
+/// sums of the rows, leaving out amounts that could not be priced.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PositionTotals {
+    pub value_btc: Decimal,
+    pub value_usd: Decimal,
+    pub unrealized_btc: Decimal,
+    pub unrealized_usd: Decimal,
+    pub realized_btc: Decimal,
+    pub realized_usd: Decimal,
+}
+
+/// Entry and current prices, and profit and loss of every position, with totals. Printed as a
+/// table, or written as json or csv.
+///
+/// ```rust,ignore
+/// let positions = PositionPresenter::for_exchange(&binance, CostBasisMethod::Fifo)?;
+/// let report = PositionReport::new(&positions);
+/// println!("{}", report);
+/// report.write_csv(std::io::stdout())?;
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionReport {
+    pub rows: Vec<PositionRow>,
+    pub totals: PositionTotals,
+}
+
+impl PositionReport {
+    pub fn new(positions: &[PositionPresenter]) -> Self {
+        let rows: Vec<PositionRow> = positions.iter().map(position_row).collect();
+
+        let sum = |amount: fn(&PositionRow) -> Option<Decimal>| -> Decimal {
+            rows.iter().filter_map(amount).sum()
+        };
+        let totals = PositionTotals {
+            value_btc: sum(|row| row.value_btc),
+            value_usd: sum(|row| row.value_usd),
+            unrealized_btc: sum(|row| row.unrealized_btc),
+            unrealized_usd: sum(|row| row.unrealized_usd),
+            realized_btc: sum(|row| row.realized_btc),
+            realized_usd: sum(|row| row.realized_usd),
+        };
+
+        PositionReport { rows, totals }
+    }
+
+    pub fn to_json(&self) -> CoreResult<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// one line per position, the totals are left to the reader.
+    pub fn write_csv<W: Write>(&self, writer: W) -> CoreResult<()> {
+        let mut csv = csv::Writer::from_writer(writer);
+
+        for row in &self.rows {
+            csv.serialize(row)?;
+        }
+
+        csv.flush()?;
+        Ok(())
+    }
+}
+
+fn position_row(position: &PositionPresenter) -> PositionRow {
+    let btc = |amount: Option<Decimal>| amount.map(|a| a.round_dp(8));
+    let usd = |amount: Option<Decimal>| amount.map(|a| a.round_dp(2));
+
+    PositionRow {
+        exchange: position.position.asset.exchange.to_string(),
+        asset: position.symbol(),
+        pair: position.pair().to_string(),
+        qty: position.qty(),
+        entry_price: position.entry_price().round_dp(8),
+        current_price: position.current_price(),
+        percent_change: position.percent_change().map(|p| p.round_dp(2)),
+        value_btc: btc(position.value_in("BTC")),
+        value_usd: usd(position.value_in("USDT")),
+        unrealized_btc: btc(position.unrealized_pnl_in("BTC")),
+        unrealized_usd: usd(position.unrealized_pnl_in("USDT")),
+        realized_btc: btc(position.realized_pnl_in("BTC")),
+        realized_usd: usd(position.realized_pnl_in("USDT")),
+        untracked: position.untracked,
+    }
+}
+
+impl Display for PositionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show = |amount: Option<Decimal>| amount.map_or("-".to_string(), |a| a.to_string());
+
+        writeln!(
+            f,
+            "{:<8} {:<12} {:>14} {:>14} {:>14} {:>8} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>14}",
+            "exchange",
+            "pair",
+            "qty",
+            "entry",
+            "current",
+            "change%",
+            "value BTC",
+            "value USD",
+            "unreal BTC",
+            "unreal USD",
+            "real BTC",
+            "real USD",
+            "untracked"
+        )?;
+
+        for row in &self.rows {
+            writeln!(
+                f,
+                "{:<8} {:<12} {:>14} {:>14} {:>14} {:>8} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>14}",
+                row.exchange,
+                row.pair,
+                row.qty,
+                row.entry_price,
+                show(row.current_price),
+                show(row.percent_change),
+                show(row.value_btc),
+                show(row.value_usd),
+                show(row.unrealized_btc),
+                show(row.unrealized_usd),
+                show(row.realized_btc),
+                show(row.realized_usd),
+                show(row.untracked)
+            )?;
+        }
+
+        let totals = &self.totals;
+        write!(
+            f,
+            "{:<8} {:<12} {:>14} {:>14} {:>14} {:>8} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>14}",
+            "total",
+            "",
+            "",
+            "",
+            "",
+            "",
+            totals.value_btc.round_dp(8),
+            totals.value_usd.round_dp(2),
+            totals.unrealized_btc.round_dp(8),
+            totals.unrealized_usd.round_dp(2),
+            totals.realized_btc.round_dp(8),
+            totals.realized_usd.round_dp(2),
+            ""
+        )
+    }
+}
@@ -0,0 +1,527 @@
+use crate::error::*;
+use crate::models::*;
+use chrono::{offset::Local, prelude::DateTime, Datelike, Duration};
+use rust_decimal::Decimal;
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use std::{fmt, fmt::Display, io::Write};
+
+/// Holdings kept for longer than this count as long term.
+pub fn long_term_holding_period() -> Duration {
+    Duration::days(365)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HoldingTerm {
+    Short,
+    Long,
+    /// units sold that were never bought in the trade history, so their holding period is not
+    /// known.
+    Unknown,
+}
+
+impl Display for HoldingTerm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HoldingTerm::Short => write!(f, "short"),
+            HoldingTerm::Long => write!(f, "long"),
+            HoldingTerm::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// One disposal matched against one acquisition lot, valued in the report's fiat currency.
+#[derive(Debug, Clone)]
+pub struct TaxReportRow {
+    pub symbol: String,
+    pub qty: Decimal,
+    /// `None` when the units sold were never bought in the trade history.
+    pub acquired: Option<DateTime<Local>>,
+    pub disposed: DateTime<Local>,
+    pub proceeds: Decimal,
+    pub cost_basis: Decimal,
+    pub term: HoldingTerm,
+    pub trade_id: String,
+}
+
+impl TaxReportRow {
+    pub fn gain(&self) -> Decimal {
+        self.proceeds - self.cost_basis
+    }
+}
+
+/// A fee paid on one trade, valued in fiat when it was paid.
+#[derive(Debug, Clone)]
+pub struct FeePaid {
+    pub time: DateTime<Local>,
+    pub amount: Decimal,
+    pub trade_id: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TermTotals {
+    pub disposals: usize,
+    pub proceeds: Decimal,
+    pub cost_basis: Decimal,
+    pub gain: Decimal,
+}
+
+#[derive(Debug, Clone)]
+pub struct TaxSummary {
+    pub fiat: String,
+    pub short_term: TermTotals,
+    pub long_term: TermTotals,
+    /// disposals of units with no known acquisition, see `HoldingTerm::Unknown`.
+    pub unknown_term: TermTotals,
+    pub fees: Decimal,
+}
+
+impl TaxSummary {
+    pub fn gain(&self) -> Decimal {
+        self.short_term.gain + self.long_term.gain + self.unknown_term.gain
+    }
+}
+
+impl Display for TaxSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, totals) in &[
+            ("short term", &self.short_term),
+            ("long term", &self.long_term),
+            ("unknown", &self.unknown_term),
+        ] {
+            writeln!(
+                f,
+                "{:<10} {:>5} disposals  proceeds {} {}  cost {} {}  gain {} {}",
+                name,
+                totals.disposals,
+                totals.proceeds.round_dp(2),
+                self.fiat,
+                totals.cost_basis.round_dp(2),
+                self.fiat,
+                totals.gain.round_dp(2),
+                self.fiat
+            )?;
+        }
+        writeln!(f, "fees paid  {} {}", self.fees.round_dp(2), self.fiat)?;
+        write!(f, "total gain {} {}", self.gain().round_dp(2), self.fiat)
+    }
+}
+
+#[derive(Serialize)]
+struct CsvRow {
+    asset: String,
+    qty: String,
+    acquired: String,
+    disposed: String,
+    proceeds: String,
+    cost_basis: String,
+    gain: String,
+    term: String,
+    trade_id: String,
+}
+
+/// Capital gains for every disposal in a trade history, see `TaxReport::generate`.
+#[derive(Debug, Clone)]
+pub struct TaxReport {
+    pub fiat: String,
+    pub method: CostBasisMethod,
+    pub rows: Vec<TaxReportRow>,
+    pub fees: Vec<FeePaid>,
+}
+
+impl TaxReport {
+    /// Every trade is split into legs valued in `fiat` at the time of the trade: buying XYZ with
+    /// BTC acquires XYZ and disposes of BTC. Fees paid in a third asset (eg. BNB) add to the cost
+    /// or reduce the proceeds of the trade, and count as a disposal of that asset.
+    pub fn generate(
+        trades: &[Trade],
+        method: CostBasisMethod,
+        fiat: &str,
+        prices: &dyn HistoricalPrices,
+    ) -> CoreResult<Self> {
+        let fiat = fiat.to_uppercase();
+        let mut trades = trades.to_vec();
+        trades.sort_by(|a, b| a.time.cmp(&b.time));
+
+        let mut legs: Vec<Trade> = Vec::new();
+        let mut fees: Vec<FeePaid> = Vec::new();
+
+        for trade in &trades {
+            let (mut trade_legs, fee) = fiat_legs(trade, &fiat, prices)?;
+            legs.append(&mut trade_legs);
+            if fee > Decimal::ZERO {
+                fees.push(FeePaid {
+                    time: trade.time,
+                    amount: fee,
+                    trade_id: trade.id.clone(),
+                });
+            }
+        }
+
+        // one ledger per asset, every leg is priced in fiat.
+        let mut ledgers: BTreeMap<String, LotLedger> = BTreeMap::new();
+        for leg in &legs {
+            ledgers
+                .entry(leg.pair.symbol.clone())
+                .or_insert_with(|| LotLedger::new(method))
+                .add_trade(leg)?;
+        }
+
+        let mut rows: Vec<TaxReportRow> = Vec::new();
+        for (symbol, ledger) in &ledgers {
+            for disposal in ledger.disposals() {
+                rows.append(&mut disposal_rows(symbol, disposal));
+            }
+        }
+        rows.sort_by(|a, b| a.disposed.cmp(&b.disposed));
+
+        Ok(TaxReport {
+            fiat,
+            method,
+            rows,
+            fees,
+        })
+    }
+
+    /// only the disposals made and fees paid in the given calendar year.
+    pub fn for_year(&self, year: i32) -> TaxReport {
+        TaxReport {
+            fiat: self.fiat.clone(),
+            method: self.method,
+            rows: self
+                .rows
+                .iter()
+                .filter(|row| row.disposed.year() == year)
+                .cloned()
+                .collect(),
+            fees: self
+                .fees
+                .iter()
+                .filter(|fee| fee.time.year() == year)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// fees paid, valued in fiat when they were paid.
+    pub fn total_fees(&self) -> Decimal {
+        self.fees.iter().map(|fee| fee.amount).sum()
+    }
+
+    pub fn summary(&self) -> TaxSummary {
+        let mut short_term = TermTotals::default();
+        let mut long_term = TermTotals::default();
+        let mut unknown_term = TermTotals::default();
+
+        for row in &self.rows {
+            let totals = match row.term {
+                HoldingTerm::Short => &mut short_term,
+                HoldingTerm::Long => &mut long_term,
+                HoldingTerm::Unknown => &mut unknown_term,
+            };
+            totals.disposals += 1;
+            totals.proceeds += row.proceeds;
+            totals.cost_basis += row.cost_basis;
+            totals.gain += row.gain();
+        }
+
+        TaxSummary {
+            fiat: self.fiat.clone(),
+            short_term,
+            long_term,
+            unknown_term,
+            fees: self.total_fees(),
+        }
+    }
+
+    pub fn write_csv<W: Write>(&self, writer: W) -> CoreResult<()> {
+        let mut csv = csv::Writer::from_writer(writer);
+
+        for row in &self.rows {
+            csv.serialize(CsvRow {
+                asset: row.symbol.clone(),
+                qty: row.qty.to_string(),
+                acquired: row
+                    .acquired
+                    .map(|time| time.format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                disposed: row.disposed.format("%Y-%m-%d").to_string(),
+                proceeds: row.proceeds.round_dp(2).to_string(),
+                cost_basis: row.cost_basis.round_dp(2).to_string(),
+                gain: row.gain().round_dp(2).to_string(),
+                term: row.term.to_string(),
+                trade_id: row.trade_id.clone(),
+            })?;
+        }
+
+        csv.flush()?;
+        Ok(())
+    }
+}
+
+fn disposal_rows(symbol: &str, disposal: &Disposal) -> Vec<TaxReportRow> {
+    let mut rows: Vec<TaxReportRow> = disposal
+        .lots
+        .iter()
+        .map(|lot| TaxReportRow {
+            symbol: symbol.to_string(),
+            qty: lot.qty,
+            acquired: Some(lot.acquired),
+            disposed: disposal.time,
+            proceeds: lot.qty * disposal.sale_price,
+            cost_basis: lot.cost_basis(),
+            term: if disposal.time - lot.acquired > long_term_holding_period() {
+                HoldingTerm::Long
+            } else {
+                HoldingTerm::Short
+            },
+            trade_id: disposal.trade_id.clone(),
+        })
+        .collect();
+
+    if disposal.unmatched_qty > Decimal::ZERO {
+        rows.push(TaxReportRow {
+            symbol: symbol.to_string(),
+            qty: disposal.unmatched_qty,
+            acquired: None,
+            disposed: disposal.time,
+            proceeds: disposal.unmatched_qty * disposal.sale_price,
+            cost_basis: Decimal::ZERO,
+            term: HoldingTerm::Unknown,
+            trade_id: disposal.trade_id.clone(),
+        });
+    }
+
+    rows
+}
+
+/// Splits a trade into fiat priced legs, returning them with the fiat value of the fee.
+fn fiat_legs(
+    trade: &Trade,
+    fiat: &str,
+    prices: &dyn HistoricalPrices,
+) -> CoreResult<(Vec<Trade>, Decimal)> {
+    let symbol = trade.pair.symbol.to_uppercase();
+    let quote = trade.pair.base.to_uppercase();
+    let rate = |asset: &str| -> CoreResult<Decimal> {
+        prices
+            .price_at(asset, fiat, trade.time)
+            .or_else(|| {
+                if is_fiat_equivalent(asset, fiat) {
+                    Some(Decimal::ONE)
+                } else {
+                    None
+                }
+            })
+            .ok_or_else(|| {
+                Box::new(TrailerError::Generic(format!(
+                    "no {} price for {} at {}, needed for trade {}",
+                    fiat, asset, trade.time, trade.id
+                ))) as Box<dyn std::error::Error>
+            })
+    };
+
+    let quote_rate = rate(&quote)?;
+    let price = trade.sale_price * quote_rate;
+    let fee_symbol = trade.fee_symbol.as_ref().map(|s| s.to_uppercase());
+
+    let mut legs = Vec::new();
+    let mut quote_qty = trade.sale_cost();
+
+    // fees paid in the bought asset only shrink the lot, everything else is valued in fiat.
+    let (fee, fee_value) = match fee_symbol {
+        _ if trade.fee <= Decimal::ZERO => (None, Decimal::ZERO),
+        Some(ref s) if *s == symbol && trade.trade_type == TradeType::Buy => {
+            (Some(symbol.clone()), trade.fee * price)
+        }
+        Some(ref s) if *s == symbol => (Some(fiat.to_string()), trade.fee * price),
+        Some(ref s) if *s == quote => {
+            match trade.trade_type {
+                TradeType::Buy => quote_qty += trade.fee,
+                TradeType::Sell => quote_qty -= trade.fee,
+            };
+            (Some(fiat.to_string()), trade.fee * quote_rate)
+        }
+        Some(ref s) => {
+            let fee_rate = rate(s)?;
+            if !is_fiat_equivalent(s, fiat) {
+                legs.push(leg(trade, s, fiat, TradeType::Sell, trade.fee, fee_rate));
+            }
+            (Some(fiat.to_string()), trade.fee * fee_rate)
+        }
+        None => (None, Decimal::ZERO),
+    };
+
+    let mut base_leg = leg(trade, &symbol, fiat, trade.trade_type, trade.qty, price);
+    if let Some(fee_symbol) = fee {
+        base_leg.fee = if fee_symbol == symbol {
+            trade.fee
+        } else {
+            fee_value
+        };
+        base_leg.fee_symbol = Some(fee_symbol);
+    }
+    if !is_fiat_equivalent(&symbol, fiat) {
+        legs.push(base_leg);
+    }
+
+    if !is_fiat_equivalent(&quote, fiat) && quote_qty > Decimal::ZERO {
+        let quote_side = match trade.trade_type {
+            TradeType::Buy => TradeType::Sell,
+            TradeType::Sell => TradeType::Buy,
+        };
+        legs.push(leg(trade, &quote, fiat, quote_side, quote_qty, quote_rate));
+    }
+
+    Ok((legs, fee_value))
+}
+
+/// Stablecoins are treated as cash when reporting in USD, spending them is not a disposal.
+fn is_fiat_equivalent(asset: &str, fiat: &str) -> bool {
+    asset == fiat || (fiat == "USD" && crate::KNOWN_STABLECOIN_SYMBOLS.contains(&asset))
+}
+
+fn leg(
+    trade: &Trade,
+    symbol: &str,
+    fiat: &str,
+    trade_type: TradeType,
+    qty: Decimal,
+    price: Decimal,
+) -> Trade {
+    Trade {
+        fee: Decimal::ZERO,
+        fee_symbol: None,
+        id: trade.id.clone(),
+        pair: Pair::new(symbol, fiat),
+        sale_price: price,
+        qty,
+        time: trade.time,
+        trade_type,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn trade(id: &str, side: TradeType, qty: &str, price: &str, date: (i32, u32, u32)) -> Trade {
+        Trade {
+            fee: Decimal::ZERO,
+            fee_symbol: None,
+            id: id.to_string(),
+            pair: Pair::new("BTC", "USDT"),
+            sale_price: dec(price),
+            qty: dec(qty),
+            time: Local
+                .from_local_datetime(
+                    &NaiveDate::from_ymd_opt(date.0, date.1, date.2)
+                        .and_then(|day| day.and_hms_opt(12, 0, 0))
+                        .unwrap(),
+                )
+                .unwrap(),
+            trade_type: side,
+        }
+    }
+
+    fn with_fee(mut trade: Trade, fee: &str) -> Trade {
+        trade.fee = dec(fee);
+        trade.fee_symbol = Some("USDT".to_string());
+        trade
+    }
+
+    /// USDT counts as USD, so no prices are needed.
+    fn report() -> TaxReport {
+        let trades = vec![
+            with_fee(trade("1", TradeType::Buy, "1", "100", (2018, 1, 1)), "1"),
+            trade("2", TradeType::Buy, "1", "200", (2019, 6, 1)),
+            with_fee(trade("3", TradeType::Sell, "1.5", "300", (2019, 7, 1)), "3"),
+            trade("4", TradeType::Sell, "1", "400", (2020, 2, 1)),
+        ];
+        TaxReport::generate(
+            &trades,
+            CostBasisMethod::Fifo,
+            "usd",
+            &ConversionGraph::default(),
+        )
+        .unwrap()
+    }
+
+    fn rows(report: &TaxReport) -> Vec<(String, Decimal, Decimal, HoldingTerm)> {
+        report
+            .rows
+            .iter()
+            .map(|row| (row.trade_id.clone(), row.proceeds, row.cost_basis, row.term))
+            .collect()
+    }
+
+    #[test]
+    fn disposals_are_split_per_lot_and_term() {
+        let report = report();
+
+        assert_eq!(
+            rows(&report),
+            vec![
+                ("3".to_string(), dec("298"), dec("101"), HoldingTerm::Long),
+                ("3".to_string(), dec("149"), dec("100"), HoldingTerm::Short),
+                ("4".to_string(), dec("200"), dec("100"), HoldingTerm::Short),
+                ("4".to_string(), dec("200"), dec("0"), HoldingTerm::Unknown),
+            ]
+        );
+        assert_eq!(report.rows[3].acquired, None);
+        assert_eq!(report.total_fees(), dec("4"));
+    }
+
+    #[test]
+    fn summary_totals_each_term() {
+        let summary = report().summary();
+
+        assert_eq!(summary.long_term.disposals, 1);
+        assert_eq!(summary.long_term.gain, dec("197"));
+        assert_eq!(summary.short_term.disposals, 2);
+        assert_eq!(summary.short_term.gain, dec("149"));
+        assert_eq!(summary.unknown_term.gain, dec("200"));
+        assert_eq!(summary.gain(), dec("546"));
+        assert_eq!(summary.fees, dec("4"));
+    }
+
+    #[test]
+    fn for_year_keeps_only_that_years_disposals_and_fees() {
+        let report = report();
+
+        let year = report.for_year(2019);
+        assert_eq!(year.rows.len(), 2);
+        assert_eq!(year.total_fees(), dec("3"));
+        assert_eq!(year.summary().gain(), dec("246"));
+
+        let year = report.for_year(2020);
+        assert_eq!(year.rows.len(), 2);
+        assert_eq!(year.total_fees(), Decimal::ZERO);
+
+        let year = report.for_year(2018);
+        assert!(year.rows.is_empty());
+        assert_eq!(year.total_fees(), dec("1"));
+    }
+
+    #[test]
+    fn fees_in_a_third_asset_are_disposals_of_it() {
+        let mut buy = trade("1", TradeType::Buy, "1", "100", (2019, 1, 1));
+        buy.fee = dec("0.5");
+        buy.fee_symbol = Some("BNB".to_string());
+
+        let mut graph = ConversionGraph::default();
+        graph.add_market(&Pair::new("BNB", "USD"), dec("20"));
+        let report = TaxReport::generate(&[buy], CostBasisMethod::Fifo, "USD", &graph).unwrap();
+
+        assert_eq!(report.total_fees(), dec("10"));
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.rows[0].symbol, "BNB");
+        assert_eq!(report.rows[0].term, HoldingTerm::Unknown);
+        assert_eq!(report.rows[0].proceeds, dec("10"));
+    }
+}
@@ -0,0 +1,585 @@
+use crate::error::*;
+use crate::exchanges::{paper_api::PaperAPI, Exchange, ExchangeAPI};
+use crate::models::*;
+use crate::risk::*;
+use chrono::prelude::*;
+use log::{error, info, warn};
+use rust_decimal::Decimal;
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RiskState {
+    /// why trading was stopped, while the kill switch is on.
+    killed: Option<String>,
+    /// the local date the starting equity was taken, as `%Y-%m-%d`.
+    day: Option<String>,
+    day_start_equity: Option<Decimal>,
+}
+
+/// An `ExchangeAPI` that checks every order against `RiskLimits` before passing it on, and
+/// rejects it with `TrailerError::RiskRejected` otherwise. Anything else goes straight through.
+///
+/// The kill switch cancels every open order and rejects all orders until `reset`. It is shared by
+/// clones of the guard, so another thread can pull it, and it trips by itself once the day's
+/// loss reaches `max_daily_loss`. The day's loss is measured from the equity seen by the first
+/// order checked that day.
+///
+/// Without a `state_file` the kill switch and the day's starting equity only last as long as
+/// the process, so a command run once per order never sees a daily loss. `exchange_api` keeps
+/// them in `~/.crypto/risk_<exchange>.json`.
+///
+/// `max_open_orders` and `max_position` need the exchange's open orders, `check_supported` fails
+/// for exchanges that cannot list them, eg. Huobi and OKEx. There the kill switch stops trading
+/// without cancelling anything.
+///
+/// ```rust,ignore
+/// let limits = RiskLimits::new()
+///     .max_order_notional(Decimal::new(1000, 0))
+///     .price_band(Decimal::new(5, 0));
+/// let guarded = RiskGuard::wrap(Box::new(binance), limits);
+/// guarded.limit_buy("BTCUSDT", qty, price)?;
+/// guarded.kill("manual stop")?;
+/// ```
+#[derive(Clone)]
+pub struct RiskGuard {
+    inner: Arc<dyn ExchangeAPI>,
+    limits: RiskLimits,
+    state: Arc<Mutex<RiskState>>,
+    path: Option<PathBuf>,
+}
+
+impl RiskGuard {
+    pub fn wrap(inner: Box<dyn ExchangeAPI>, limits: RiskLimits) -> Self {
+        RiskGuard {
+            inner: Arc::from(inner),
+            limits,
+            state: Arc::new(Mutex::new(RiskState::default())),
+            path: None,
+        }
+    }
+
+    /// Keeps the kill switch and the day's starting equity at `path`, loading them from there
+    /// if the file exists.
+    pub fn state_file<P: AsRef<Path>>(mut self, path: P) -> CoreResult<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        if path.exists() {
+            let state: RiskState = serde_json::from_str(&fs::read_to_string(&path)?)?;
+            *self.state.lock().expect("risk state") = state;
+        }
+
+        self.path = Some(path);
+        Ok(self)
+    }
+
+    /// `~/.crypto/risk_<exchange>.json`, next to the config file.
+    pub fn default_state_file(exchange: Exchange) -> CoreResult<PathBuf> {
+        let home_path =
+            dirs::home_dir().ok_or_else(|| TrailerError::Generic(format!("cannot get homedir")))?;
+
+        Ok(home_path
+            .join(".crypto")
+            .join(format!("risk_{}.json", exchange.to_string())))
+    }
+
+    /// Fails with a `ConfigError` if a limit needs open orders the exchange cannot list, instead
+    /// of rejecting every order later.
+    pub fn check_supported(&self) -> CoreResult<()> {
+        if !self.limits.needs_open_orders() {
+            return Ok(());
+        }
+
+        self.inner.open_orders().map(|_| ()).map_err(|e| {
+            Box::new(TrailerError::ConfigError(format!(
+                "{} cannot list open orders, needed by max_open_orders and max_position: {}",
+                self.inner.display(),
+                e
+            ))) as Box<dyn std::error::Error>
+        })
+    }
+
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    pub fn inner(&self) -> &dyn ExchangeAPI {
+        self.inner.as_ref()
+    }
+
+    pub fn is_killed(&self) -> bool {
+        self.kill_reason().is_some()
+    }
+
+    pub fn kill_reason(&self) -> Option<String> {
+        self.state.lock().expect("risk state").killed.clone()
+    }
+
+    /// Stops all trading and cancels every open order, returning how many were cancelled. Orders
+    /// that fail to cancel, or cannot be listed, are logged, trading stays stopped either way.
+    pub fn kill(&self, reason: &str) -> CoreResult<usize> {
+        {
+            let mut state = self.state.lock().expect("risk state");
+            state.killed = Some(reason.to_string());
+            self.save(&state)?;
+        }
+        error!("{}: kill switch: {}", self.inner.display(), reason);
+
+        let open = match self.inner.open_orders() {
+            Ok(open) => open,
+            Err(e) => {
+                warn!("kill switch: cannot list open orders: {}", e);
+                return Ok(0);
+            }
+        };
+
+        let mut cancelled = 0;
+        for order in open {
+            let symbol = self.inner.native_symbol(&order.pair)?;
+            match self.inner.cancel_order(&symbol, &order.id) {
+                Ok(()) => cancelled += 1,
+                Err(e) => warn!("kill switch: cancelling {} {}: {}", symbol, order.id, e),
+            }
+        }
+        Ok(cancelled)
+    }
+
+    /// lifts the kill switch.
+    pub fn reset(&self) -> CoreResult<()> {
+        let mut state = self.state.lock().expect("risk state");
+        state.killed = None;
+        self.save(&state)?;
+        info!("{}: kill switch reset", self.inner.display());
+        Ok(())
+    }
+
+    /// Checks an order against every limit without sending it.
+    pub fn check(
+        &self,
+        symbol: &str,
+        side: TradeType,
+        qty: Decimal,
+        price: Decimal,
+    ) -> CoreResult<()> {
+        if let Some(reason) = self.kill_reason() {
+            return reject(format!("trading stopped: {}", reason));
+        }
+
+        if let Some(allowed) = &self.limits.allowed_symbols {
+            if !allowed
+                .iter()
+                .any(|s| s.to_uppercase() == symbol.to_uppercase())
+            {
+                return reject(format!("{} is not an allowed symbol", symbol));
+            }
+        }
+
+        let pair = self.inner.pair(symbol)?;
+        let reference = self.limits.reference_asset();
+
+        let needs_prices = self.limits.price_band.is_some()
+            || self.limits.max_order_notional.is_some()
+            || self.limits.max_daily_loss.is_some();
+        let prices = if needs_prices {
+            self.inner.all_prices()?
+        } else {
+            Vec::new()
+        };
+        let graph = ConversionGraph::from_prices(&prices);
+
+        if let Some(band) = self.limits.price_band {
+            let last = prices
+                .iter()
+                .find(|p| p.pair == pair)
+                .map(|p| p.price)
+                .ok_or(Box::new(TrailerError::PairNotFound(symbol.to_string())))?;
+            let distance = crate::utils::price_percent(last, price).abs();
+            if distance > band {
+                return reject(format!(
+                    "{} {} at {} is {}% from the last price {}, the band is {}%",
+                    side,
+                    symbol,
+                    price,
+                    distance.round_dp(2),
+                    last,
+                    band
+                ));
+            }
+        }
+
+        if let Some(max) = self.limits.max_order_notional {
+            let notional =
+                value_in(&graph, qty * price, &pair.base, &reference).ok_or_else(|| {
+                    risk_error(format!("cannot value {} in {}", pair.base, reference))
+                })?;
+            if notional > max {
+                return reject(format!(
+                    "{} {} {} is worth {} {}, the limit is {}",
+                    side,
+                    qty,
+                    symbol,
+                    notional.round_dp(2),
+                    reference,
+                    max
+                ));
+            }
+        }
+
+        let position_limit = match side {
+            TradeType::Buy => self.limits.position_limit(&pair.symbol),
+            TradeType::Sell => None,
+        };
+
+        if self.limits.max_open_orders.is_some() || position_limit.is_some() {
+            // `check_supported` rules out exchanges that cannot list them.
+            let open = self.inner.open_orders()?;
+
+            if let Some(max) = self.limits.max_open_orders {
+                if open.len() >= max {
+                    return reject(format!(
+                        "{} orders already open, the limit is {}",
+                        open.len(),
+                        max
+                    ));
+                }
+            }
+
+            if let Some(max) = position_limit {
+                let held: Decimal = self
+                    .inner
+                    .balances()?
+                    .iter()
+                    .filter(|asset| asset.symbol.to_uppercase() == pair.symbol.to_uppercase())
+                    .map(|asset| asset.amount)
+                    .sum();
+                let buying: Decimal = open
+                    .iter()
+                    .filter(|o| o.trade_type == TradeType::Buy && o.pair.symbol == pair.symbol)
+                    .map(|o| o.qty - o.executed_qty)
+                    .sum();
+
+                if held + buying + qty > max {
+                    return reject(format!(
+                        "buying {} {} would hold {}, the limit is {}",
+                        qty,
+                        pair.symbol,
+                        held + buying + qty,
+                        max
+                    ));
+                }
+            }
+        }
+
+        if let Some(max) = self.limits.max_daily_loss {
+            let loss = self.daily_loss(&graph, &reference)?;
+            if loss >= max {
+                let reason = format!(
+                    "daily loss of {} {} reached the limit of {}",
+                    loss.round_dp(2),
+                    reference,
+                    max
+                );
+                // the order is rejected for the loss even if open orders cannot be cancelled.
+                if let Err(e) = self.kill(&reason) {
+                    warn!("kill switch: {}", e);
+                }
+                return reject(reason);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// what the account has lost since the first check of the day, negative for a gain.
+    fn daily_loss(&self, graph: &ConversionGraph, reference: &str) -> CoreResult<Decimal> {
+        let equity: Decimal = self
+            .inner
+            .balances()?
+            .iter()
+            .filter_map(|asset| value_in(graph, asset.amount, &asset.symbol, reference))
+            .sum();
+
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        let mut state = self.state.lock().expect("risk state");
+        if state.day.as_ref() != Some(&today) {
+            state.day = Some(today);
+            state.day_start_equity = Some(equity);
+            self.save(&state)?;
+        }
+
+        Ok(state.day_start_equity.unwrap_or(equity) - equity)
+    }
+
+    fn save(&self, state: &RiskState) -> CoreResult<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_string_pretty(state)?)?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+fn value_in(
+    graph: &ConversionGraph,
+    amount: Decimal,
+    asset: &str,
+    reference: &str,
+) -> Option<Decimal> {
+    if asset.to_uppercase() == reference.to_uppercase() {
+        Some(amount)
+    } else {
+        graph.convert(amount, asset, reference)
+    }
+}
+
+fn risk_error(reason: String) -> Box<dyn std::error::Error> {
+    Box::new(TrailerError::RiskRejected(reason))
+}
+
+fn reject(reason: String) -> CoreResult<()> {
+    warn!("order rejected: {}", reason);
+    Err(risk_error(reason))
+}
+
+impl ExchangeAPI for RiskGuard {
+    /// a guard around an empty paper exchange, see `wrap`.
+    fn new() -> Self {
+        RiskGuard::wrap(Box::new(PaperAPI::new()), RiskLimits::default())
+    }
+
+    /// the wrapped api keeps its own keys, this is the same guard.
+    fn authenticate(&self, _key: &str, _secret: &str) -> Self {
+        self.clone()
+    }
+
+    fn display(&self) -> String {
+        self.inner.display()
+    }
+
+    fn btc_symbol(&self) -> String {
+        self.inner.btc_symbol()
+    }
+
+    fn usd_symbol(&self) -> String {
+        self.inner.usd_symbol()
+    }
+
+    fn base_pairs(&self) -> Vec<String> {
+        self.inner.base_pairs()
+    }
+
+    fn balances(&self) -> CoreResult<Vec<Asset>> {
+        self.inner.balances()
+    }
+
+    fn instruments(&self) -> CoreResult<Arc<InstrumentMap>> {
+        self.inner.instruments()
+    }
+
+    fn all_prices(&self) -> CoreResult<Vec<Price>> {
+        self.inner.all_prices()
+    }
+
+    fn limit_buy(&self, symbol: &str, amount: Decimal, price: Decimal) -> CoreResult<()> {
+        self.check(symbol, TradeType::Buy, amount, price)?;
+        self.inner.limit_buy(symbol, amount, price)
+    }
+
+    fn limit_sell(&self, symbol: &str, amount: Decimal, price: Decimal) -> CoreResult<()> {
+        self.check(symbol, TradeType::Sell, amount, price)?;
+        self.inner.limit_sell(symbol, amount, price)
+    }
+
+    fn cancel_order(&self, symbol: &str, order_id: &str) -> CoreResult<()> {
+        self.inner.cancel_order(symbol, order_id)
+    }
+
+    fn open_orders(&self) -> CoreResult<Vec<Order>> {
+        self.inner.open_orders()
+    }
+
+    fn past_orders(&self) -> CoreResult<Vec<Order>> {
+        self.inner.past_orders()
+    }
+
+    fn order(&self, symbol: &str, order_id: &str) -> CoreResult<Order> {
+        self.inner.order(symbol, order_id)
+    }
+
+    fn book_tickers(&self) -> CoreResult<Vec<BookTicker>> {
+        self.inner.book_tickers()
+    }
+
+    fn trades_for_pair(&self, pair: Pair) -> CoreResult<Vec<Trade>> {
+        self.inner.trades_for_pair(pair)
+    }
+
+    fn intervals(&self) -> Vec<Interval> {
+        self.inner.intervals()
+    }
+
+    fn chart_data(&self, pair: &str, interval: Interval) -> CoreResult<Vec<Candlestick>> {
+        self.inner.chart_data(pair, interval)
+    }
+
+    fn market_depth(&self, pair: &str) -> CoreResult<Depth> {
+        self.inner.market_depth(pair)
+    }
+
+    /// a stop sells at `limit_price` once triggered, it is checked like a limit sell there.
+    fn stop_loss(
+        &self,
+        symbol: &str,
+        amount: Decimal,
+        stop_price: Decimal,
+        limit_price: Decimal,
+    ) -> CoreResult<()> {
+        self.check(symbol, TradeType::Sell, amount, limit_price)?;
+        self.inner
+            .stop_loss(symbol, amount, stop_price, limit_price)
+    }
+
+    fn exchange(&self) -> Exchange {
+        self.inner.exchange()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::huobi_api::HuobiAPI;
+    use crate::exchanges::paper_api::Quote;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    /// 1000 USDT and 10 BTC, with BTC last traded at 100 USDT.
+    fn paper() -> PaperAPI {
+        let paper = PaperAPI::new().market("BTCUSDT", &Pair::new("BTC", "USDT"));
+        paper.deposit("USDT", dec("1000"));
+        paper.deposit("BTC", dec("10"));
+        paper.update("BTCUSDT", &Quote::from_price(1, dec("100")));
+        paper
+    }
+
+    fn guard(paper: &PaperAPI, limits: RiskLimits) -> RiskGuard {
+        RiskGuard::wrap(Box::new(paper.clone()), limits)
+    }
+
+    fn rejected(result: CoreResult<()>) -> bool {
+        match result {
+            Err(e) => match e.downcast_ref::<TrailerError>() {
+                Some(TrailerError::RiskRejected(_)) => true,
+                _ => false,
+            },
+            Ok(()) => false,
+        }
+    }
+
+    #[test]
+    fn price_band_rejects_orders_far_from_the_last_price() {
+        let guard = guard(&paper(), RiskLimits::new().price_band(dec("5")));
+
+        assert!(rejected(guard.limit_buy("BTCUSDT", dec("1"), dec("106"))));
+        assert!(rejected(guard.limit_sell("BTCUSDT", dec("1"), dec("94"))));
+        assert!(guard.limit_buy("BTCUSDT", dec("1"), dec("104")).is_ok());
+        assert!(guard.limit_sell("BTCUSDT", dec("1"), dec("96")).is_ok());
+    }
+
+    #[test]
+    fn notional_is_limited_in_the_reference_asset() {
+        let guard = guard(&paper(), RiskLimits::new().max_order_notional(dec("500")));
+
+        assert!(rejected(guard.limit_buy("BTCUSDT", dec("6"), dec("100"))));
+        assert!(guard.limit_buy("BTCUSDT", dec("5"), dec("100")).is_ok());
+    }
+
+    #[test]
+    fn position_counts_held_and_open_buys() {
+        let paper = paper();
+        let guard = guard(&paper, RiskLimits::new().max_position("BTC", dec("12")));
+
+        assert!(guard.limit_buy("BTCUSDT", dec("1"), dec("90")).is_ok());
+        assert!(rejected(guard.limit_buy("BTCUSDT", dec("1.5"), dec("90"))));
+        assert!(guard.limit_buy("BTCUSDT", dec("1"), dec("90")).is_ok());
+        // sells never add to a position.
+        assert!(guard.limit_sell("BTCUSDT", dec("1"), dec("110")).is_ok());
+        assert_eq!(paper.open_orders().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn open_orders_are_limited() {
+        let guard = guard(&paper(), RiskLimits::new().max_open_orders(1));
+
+        assert!(guard.limit_sell("BTCUSDT", dec("1"), dec("110")).is_ok());
+        assert!(rejected(guard.limit_sell("BTCUSDT", dec("1"), dec("120"))));
+    }
+
+    #[test]
+    fn daily_loss_trips_the_kill_switch() {
+        let paper = paper();
+        let guard = guard(&paper, RiskLimits::new().max_daily_loss(dec("100")));
+
+        // 2000 USDT of equity at the first check of the day.
+        assert!(guard.limit_sell("BTCUSDT", dec("1"), dec("120")).is_ok());
+
+        paper.update("BTCUSDT", &Quote::from_price(2, dec("95")));
+        assert!(guard.limit_sell("BTCUSDT", dec("1"), dec("120")).is_ok());
+        assert!(!guard.is_killed());
+
+        paper.update("BTCUSDT", &Quote::from_price(3, dec("90")));
+        assert!(rejected(guard.limit_sell("BTCUSDT", dec("1"), dec("120"))));
+        assert!(guard.is_killed());
+        assert!(paper.open_orders().unwrap().is_empty());
+    }
+
+    #[test]
+    fn kill_cancels_and_rejects_until_reset() {
+        let paper = paper();
+        let guard = guard(&paper, RiskLimits::new());
+        guard.limit_sell("BTCUSDT", dec("1"), dec("110")).unwrap();
+        guard.limit_buy("BTCUSDT", dec("1"), dec("90")).unwrap();
+
+        assert_eq!(guard.kill("manual stop").unwrap(), 2);
+        assert_eq!(guard.kill_reason(), Some("manual stop".to_string()));
+        assert!(paper.open_orders().unwrap().is_empty());
+        assert_eq!(paper.balance("USDT"), dec("1000"));
+
+        // clones share the switch.
+        let clone = guard.clone();
+        assert!(rejected(clone.limit_buy("BTCUSDT", dec("1"), dec("90"))));
+
+        guard.reset().unwrap();
+        assert!(clone.limit_buy("BTCUSDT", dec("1"), dec("90")).is_ok());
+    }
+
+    #[test]
+    fn open_order_limits_need_an_exchange_that_lists_them() {
+        let limits = RiskLimits::new().max_position("BTC", dec("1"));
+        let huobi = RiskGuard::wrap(Box::new(HuobiAPI::new()), limits.clone());
+        assert!(huobi.check_supported().is_err());
+        assert!(guard(&paper(), limits).check_supported().is_ok());
+
+        let huobi = RiskGuard::wrap(
+            Box::new(HuobiAPI::new()),
+            RiskLimits::new().price_band(dec("5")),
+        );
+        assert!(huobi.check_supported().is_ok());
+        // the kill switch still stops trading where nothing can be cancelled.
+        assert_eq!(huobi.kill("manual stop").unwrap(), 0);
+        assert!(huobi.is_killed());
+    }
+}
@@ -0,0 +1,94 @@
+use rust_decimal::Decimal;
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+
+/// Limits checked before an order reaches the exchange, unset limits are not checked. They can
+/// be set per exchange in the config file:
+///
+/// ```toml
+/// [exchange.binance.risk]
+/// max_order_notional = "1000"
+/// max_open_orders = 10
+/// allowed_symbols = ["BTCUSDT", "ETHUSDT"]
+/// price_band = "5"
+/// max_daily_loss = "250"
+///
+/// [exchange.binance.risk.max_position]
+/// BTC = "0.5"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RiskLimits {
+    /// the largest order, in the reference asset.
+    pub max_order_notional: Option<Decimal>,
+    /// the most of an asset that may be held, counting open buy orders, in that asset.
+    #[serde(default)]
+    pub max_position: BTreeMap<String, Decimal>,
+    pub max_open_orders: Option<usize>,
+    /// the exchange's native symbols that may be traded, any when unset.
+    pub allowed_symbols: Option<Vec<String>>,
+    /// how far an order may be priced from the last price, in percent.
+    pub price_band: Option<Decimal>,
+    /// the most the account may lose in a day before trading stops, in the reference asset.
+    pub max_daily_loss: Option<Decimal>,
+    /// the asset notionals and losses are measured in, USDT when unset.
+    pub reference: Option<String>,
+}
+
+impl RiskLimits {
+    pub fn new() -> Self {
+        RiskLimits::default()
+    }
+
+    pub fn max_order_notional(mut self, notional: Decimal) -> Self {
+        self.max_order_notional = Some(notional);
+        self
+    }
+
+    pub fn max_position(mut self, asset: &str, qty: Decimal) -> Self {
+        self.max_position.insert(asset.to_uppercase(), qty);
+        self
+    }
+
+    pub fn max_open_orders(mut self, orders: usize) -> Self {
+        self.max_open_orders = Some(orders);
+        self
+    }
+
+    pub fn allowed_symbols(mut self, symbols: Vec<String>) -> Self {
+        self.allowed_symbols = Some(symbols);
+        self
+    }
+
+    pub fn price_band(mut self, percent: Decimal) -> Self {
+        self.price_band = Some(percent);
+        self
+    }
+
+    pub fn max_daily_loss(mut self, loss: Decimal) -> Self {
+        self.max_daily_loss = Some(loss);
+        self
+    }
+
+    pub fn reference(mut self, asset: &str) -> Self {
+        self.reference = Some(asset.to_uppercase());
+        self
+    }
+
+    pub(crate) fn reference_asset(&self) -> String {
+        self.reference
+            .as_ref()
+            .map_or("USDT".to_string(), |asset| asset.to_uppercase())
+    }
+
+    /// `max_open_orders` and `max_position` count the open orders on the exchange.
+    pub(crate) fn needs_open_orders(&self) -> bool {
+        self.max_open_orders.is_some() || !self.max_position.is_empty()
+    }
+
+    pub(crate) fn position_limit(&self, asset: &str) -> Option<Decimal> {
+        self.max_position
+            .iter()
+            .find(|(limited, _)| limited.to_uppercase() == asset.to_uppercase())
+            .map(|(_, qty)| *qty)
+    }
+}
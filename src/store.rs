@@ -0,0 +1,419 @@
+use crate::error::*;
+use crate::models::*;
+use chrono::{offset::Local, prelude::DateTime, NaiveDate};
+use log::warn;
+use rust_decimal::Decimal;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Total value of the book at the time of a snapshot.
+#[derive(Debug, Clone)]
+pub struct EquityPoint {
+    pub time: DateTime<Local>,
+    pub btc: Decimal,
+    pub usd: Decimal,
+}
+
+/// Holdings of one asset, summed across exchanges, at the time of a snapshot.
+#[derive(Debug, Clone)]
+pub struct AssetPoint {
+    pub time: DateTime<Local>,
+    pub amount: Decimal,
+    pub btc: Decimal,
+    pub usd: Decimal,
+}
+
+/// The last snapshot of a day compared to the last snapshot of the day before.
+#[derive(Debug, Clone)]
+pub struct DailyChange {
+    pub date: NaiveDate,
+    pub usd: Decimal,
+    pub btc: Decimal,
+    pub change_usd: Decimal,
+    pub change_btc: Decimal,
+    /// change in usd as a percentage, zero for the first day.
+    pub change_percent: Decimal,
+}
+
+/// Snapshots appended to a local file, one json document per line, oldest first.
+#[derive(Debug, Clone)]
+pub struct SnapshotStore {
+    path: PathBuf,
+}
+
+impl SnapshotStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> CoreResult<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        Ok(SnapshotStore { path })
+    }
+
+    /// `~/.crypto/snapshots.jsonl`, next to the config file.
+    pub fn open_default() -> CoreResult<Self> {
+        let home_path =
+            dirs::home_dir().ok_or_else(|| TrailerError::Generic(format!("cannot get homedir")))?;
+
+        Self::open(home_path.join(".crypto").join("snapshots.jsonl"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, snapshot: &Snapshot) -> CoreResult<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        writeln!(file, "{}", serde_json::to_string(snapshot)?)?;
+        Ok(())
+    }
+
+    /// every stored snapshot, oldest first. Lines that cannot be read (eg. a write cut short) are
+    /// skipped with a warning.
+    pub fn snapshots(&self) -> CoreResult<Vec<Snapshot>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut snapshots = Vec::new();
+        for (number, line) in BufReader::new(File::open(&self.path)?).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<Snapshot>(&line) {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(e) => warn!(
+                    "skipping snapshot on line {} of {}: {}",
+                    number + 1,
+                    self.path.display(),
+                    e
+                ),
+            }
+        }
+
+        snapshots.sort_by_key(|s| s.time);
+        Ok(snapshots)
+    }
+
+    pub fn latest(&self) -> CoreResult<Option<Snapshot>> {
+        Ok(self.snapshots()?.pop())
+    }
+
+    /// total value of the book over time, optionally for a single exchange.
+    pub fn equity_curve(&self, exchange: Option<&str>) -> CoreResult<Vec<EquityPoint>> {
+        Ok(self
+            .snapshots()?
+            .into_iter()
+            .map(|snapshot| match exchange {
+                Some(exchange) => snapshot.exchange(exchange),
+                None => snapshot,
+            })
+            .map(|snapshot| EquityPoint {
+                time: snapshot.datetime(),
+                btc: snapshot.total_btc(),
+                usd: snapshot.total_usd(),
+            })
+            .collect())
+    }
+
+    pub fn asset_history(&self, symbol: &str) -> CoreResult<Vec<AssetPoint>> {
+        let symbol = symbol.to_uppercase();
+
+        Ok(self
+            .snapshots()?
+            .into_iter()
+            .map(|snapshot| {
+                let held: Vec<&AssetSnapshot> = snapshot
+                    .assets
+                    .iter()
+                    .filter(|a| a.symbol.to_uppercase() == symbol)
+                    .collect();
+
+                AssetPoint {
+                    time: snapshot.datetime(),
+                    amount: held.iter().map(|a| a.amount).sum(),
+                    btc: held.iter().filter_map(|a| a.value_in_btc).sum(),
+                    usd: held.iter().filter_map(|a| a.value_in_usd).sum(),
+                }
+            })
+            .collect())
+    }
+
+    pub fn daily_changes(&self) -> CoreResult<Vec<DailyChange>> {
+        let mut closes: Vec<EquityPoint> = Vec::new();
+
+        for point in self.equity_curve(None)? {
+            match closes.last_mut() {
+                Some(last) if last.time.naive_local().date() == point.time.naive_local().date() => {
+                    *last = point
+                }
+                _ => closes.push(point),
+            }
+        }
+
+        let mut changes: Vec<DailyChange> = Vec::new();
+        let mut previous: Option<&EquityPoint> = None;
+
+        for close in &closes {
+            let (change_usd, change_btc) = previous.map_or((Decimal::ZERO, Decimal::ZERO), |p| {
+                (close.usd - p.usd, close.btc - p.btc)
+            });
+
+            changes.push(DailyChange {
+                date: close.time.naive_local().date(),
+                usd: close.usd,
+                btc: close.btc,
+                change_usd,
+                change_btc,
+                change_percent: previous.map_or(Decimal::ZERO, |p| {
+                    crate::utils::price_percent(p.usd, close.usd)
+                }),
+            });
+            previous = Some(close);
+        }
+
+        Ok(changes)
+    }
+
+    /// Takes a snapshot with `capture` and stores it every `interval` until `running` is cleared.
+    /// A failed capture is logged and retried on the next tick.
+    pub fn record_every<F>(
+        &self,
+        interval: Duration,
+        running: &AtomicBool,
+        mut capture: F,
+    ) -> CoreResult<()>
+    where
+        F: FnMut() -> CoreResult<Snapshot>,
+    {
+        while running.load(Ordering::Relaxed) {
+            let started = Instant::now();
+
+            match capture() {
+                Ok(snapshot) => self.append(&snapshot)?,
+                Err(e) => warn!("could not take a snapshot: {}", e),
+            }
+
+            // sleep in short steps so clearing `running` stops the loop promptly.
+            while running.load(Ordering::Relaxed) && started.elapsed() < interval {
+                thread::sleep(std::cmp::min(
+                    Duration::from_millis(500),
+                    interval - started.elapsed().min(interval),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    /// a store in the temp dir, removed when dropped.
+    struct TempStore(SnapshotStore);
+
+    impl TempStore {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "cryptotrader_{}_{}.jsonl",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_file(&path);
+            TempStore(SnapshotStore::open(path).unwrap())
+        }
+    }
+
+    impl Drop for TempStore {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(self.0.path());
+        }
+    }
+
+    fn at(day: u32, hour: u32) -> DateTime<Local> {
+        Local
+            .from_local_datetime(
+                &NaiveDate::from_ymd_opt(2019, 3, day)
+                    .and_then(|date| date.and_hms_opt(hour, 0, 0))
+                    .unwrap(),
+            )
+            .unwrap()
+    }
+
+    /// assets as `(exchange, symbol, amount, btc, usd)`.
+    fn snapshot(time: DateTime<Local>, assets: &[(&str, &str, &str, &str, &str)]) -> Snapshot {
+        let mut snapshot = Snapshot::new(time);
+        for (exchange, symbol, amount, btc, usd) in assets {
+            snapshot.assets.push(AssetSnapshot {
+                exchange: exchange.to_string(),
+                symbol: symbol.to_string(),
+                amount: dec(amount),
+                value_in_btc: Some(dec(btc)),
+                value_in_usd: Some(dec(usd)),
+            });
+        }
+        snapshot
+    }
+
+    #[test]
+    fn equity_curve_is_read_back_oldest_first() {
+        let store = TempStore::new("equity");
+        let store = &store.0;
+        store
+            .append(&snapshot(
+                at(2, 12),
+                &[
+                    ("binance", "BTC", "1", "1", "4000"),
+                    ("okex", "USDT", "2000", "0.5", "2000"),
+                ],
+            ))
+            .unwrap();
+        store
+            .append(&snapshot(
+                at(1, 12),
+                &[("binance", "BTC", "1", "1", "3800")],
+            ))
+            .unwrap();
+
+        let curve = store.equity_curve(None).unwrap();
+        assert_eq!(curve.len(), 2);
+        assert_eq!(curve[0].time, at(1, 12));
+        assert_eq!(curve[0].usd, dec("3800"));
+        assert_eq!(curve[1].btc, dec("1.5"));
+        assert_eq!(curve[1].usd, dec("6000"));
+
+        let okex = store.equity_curve(Some("okex")).unwrap();
+        assert_eq!(okex[0].usd, Decimal::ZERO);
+        assert_eq!(okex[1].usd, dec("2000"));
+        assert_eq!(
+            store.latest().unwrap().unwrap().time,
+            at(2, 12).timestamp_millis() as u64
+        );
+    }
+
+    #[test]
+    fn asset_history_sums_exchanges() {
+        let store = TempStore::new("asset");
+        let store = &store.0;
+        store
+            .append(&snapshot(
+                at(1, 12),
+                &[
+                    ("binance", "BTC", "1", "1", "4000"),
+                    ("okex", "btc", "0.5", "0.5", "2000"),
+                    ("okex", "ETH", "10", "0.3", "1200"),
+                ],
+            ))
+            .unwrap();
+        store
+            .append(&snapshot(
+                at(2, 12),
+                &[("okex", "ETH", "10", "0.3", "1200")],
+            ))
+            .unwrap();
+
+        let history = store.asset_history("Btc").unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].amount, dec("1.5"));
+        assert_eq!(history[0].btc, dec("1.5"));
+        assert_eq!(history[0].usd, dec("6000"));
+        assert_eq!(history[1].amount, Decimal::ZERO);
+    }
+
+    #[test]
+    fn daily_changes_compare_the_last_snapshot_of_each_day() {
+        let store = TempStore::new("daily");
+        let store = &store.0;
+        for (time, usd) in &[(at(1, 9), "100"), (at(1, 18), "110"), (at(2, 12), "121")] {
+            store
+                .append(&snapshot(*time, &[("binance", "USDT", usd, "0.01", usd)]))
+                .unwrap();
+        }
+
+        let changes = store.daily_changes().unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].date, at(1, 0).naive_local().date());
+        assert_eq!(changes[0].usd, dec("110"));
+        assert_eq!(changes[0].change_usd, Decimal::ZERO);
+        assert_eq!(changes[0].change_percent, Decimal::ZERO);
+        assert_eq!(changes[1].usd, dec("121"));
+        assert_eq!(changes[1].change_usd, dec("11"));
+        assert_eq!(changes[1].change_btc, Decimal::ZERO);
+        assert_eq!(changes[1].change_percent, dec("10"));
+    }
+
+    #[test]
+    fn unreadable_lines_are_skipped() {
+        let store = TempStore::new("corrupt");
+        let store = &store.0;
+        store
+            .append(&snapshot(
+                at(1, 12),
+                &[("binance", "BTC", "1", "1", "4000")],
+            ))
+            .unwrap();
+        OpenOptions::new()
+            .append(true)
+            .open(store.path())
+            .and_then(|mut file| writeln!(file, "{{\"time\": 12"))
+            .unwrap();
+        store
+            .append(&snapshot(
+                at(2, 12),
+                &[("binance", "BTC", "1", "1", "4100")],
+            ))
+            .unwrap();
+
+        assert_eq!(store.snapshots().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn record_every_stores_until_stopped() {
+        let store = TempStore::new("record");
+        let store = &store.0;
+        let running = AtomicBool::new(true);
+        let mut calls = 0;
+
+        store
+            .record_every(Duration::from_millis(1), &running, || {
+                calls += 1;
+                if calls == 3 {
+                    running.store(false, Ordering::Relaxed);
+                }
+                match calls {
+                    2 => Err(Box::new(TrailerError::Generic("offline".to_string()))),
+                    _ => Ok(snapshot(
+                        at(calls, 12),
+                        &[("binance", "BTC", "1", "1", "4000")],
+                    )),
+                }
+            })
+            .unwrap();
+
+        assert_eq!(calls, 3);
+        let snapshots = store.snapshots().unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[1].datetime(), at(3, 12));
+    }
+}
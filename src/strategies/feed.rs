@@ -0,0 +1,362 @@
+use crate::error::*;
+use crate::exchanges::{paper_api::Quote, ExchangeAPI};
+use crate::models::*;
+use chrono::DateTime;
+use log::warn;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A piece of market data, replayed from history or received from a feed.
+#[derive(Debug, Clone)]
+pub enum MarketEvent {
+    Candle(Candlestick),
+    Trade(Trade),
+    /// book tickers carry no time of their own, unix millis.
+    BookTicker(u64, BookTicker),
+    Depth(u64, Depth),
+}
+
+impl MarketEvent {
+    /// when the event is known, unix millis. Candles are known once they close.
+    pub fn time(&self) -> u64 {
+        match self {
+            MarketEvent::Candle(candle) => candle.close_time,
+            MarketEvent::Trade(trade) => trade.time.timestamp_millis().max(0) as u64,
+            MarketEvent::BookTicker(time, _) => *time,
+            MarketEvent::Depth(time, _) => *time,
+        }
+    }
+
+    /// the prices orders can be matched against, `None` for a book missing a side.
+    pub fn quote(&self) -> Option<Quote> {
+        match self {
+            MarketEvent::Candle(candle) => Some(Quote::from_candle(candle)),
+            MarketEvent::Trade(trade) => Some(Quote::from_trade(trade)),
+            MarketEvent::BookTicker(time, ticker) => Some(Quote::from_book_ticker(*time, ticker)),
+            MarketEvent::Depth(time, depth) => match (depth.bids.first(), depth.asks.first()) {
+                (Some(bid), Some(ask)) => {
+                    let mut quote =
+                        Quote::from_price(*time, (bid.price + ask.price) / Decimal::new(2, 0));
+                    quote.ask_open = ask.price;
+                    quote.ask_low = ask.price;
+                    quote.bid_open = bid.price;
+                    quote.bid_high = bid.price;
+                    quote.bid_low = bid.price;
+                    Some(quote)
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+/// A market event for the market `symbol`, in the exchange's native spelling.
+#[derive(Debug, Clone)]
+pub struct FeedEvent {
+    pub symbol: String,
+    pub event: MarketEvent,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn send(sender: &Sender<FeedEvent>, symbol: &str, event: MarketEvent) -> bool {
+    sender
+        .send(FeedEvent {
+            symbol: symbol.to_string(),
+            event,
+        })
+        .is_ok()
+}
+
+/// Polls any exchange every `every`, sending book tickers and each candle of `interval` once it
+/// has closed. Stops once `running` is cleared or the receiver is dropped.
+pub fn poll_feed(
+    exchange: Box<dyn ExchangeAPI + Send>,
+    symbols: Vec<String>,
+    interval: Interval,
+    every: Duration,
+    sender: Sender<FeedEvent>,
+    running: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut last_candle: HashMap<String, u64> = HashMap::new();
+
+        while running.load(Ordering::Relaxed) {
+            let now = now_millis();
+
+            match exchange.book_tickers() {
+                Ok(tickers) => {
+                    for ticker in tickers {
+                        let symbol = match exchange.native_symbol(&ticker.pair) {
+                            Ok(symbol) if symbols.contains(&symbol) => symbol,
+                            _ => continue,
+                        };
+                        if !send(&sender, &symbol, MarketEvent::BookTicker(now, ticker)) {
+                            return;
+                        }
+                    }
+                }
+                Err(e) => warn!("{}: book tickers failed: {}", exchange.display(), e),
+            }
+
+            for symbol in &symbols {
+                let candles = match exchange.candles(symbol, interval) {
+                    Ok(candles) => candles,
+                    Err(e) => {
+                        warn!(
+                            "{}: candles for {} failed: {}",
+                            exchange.display(),
+                            symbol,
+                            e
+                        );
+                        continue;
+                    }
+                };
+
+                let closed: Vec<Candlestick> = candles
+                    .into_iter()
+                    .filter(|candle| candle.close_time < now)
+                    .collect();
+                // the first poll only sends the latest closed candle, not the whole history.
+                let since = match last_candle.get(symbol) {
+                    Some(open_time) => *open_time,
+                    None => closed
+                        .len()
+                        .checked_sub(2)
+                        .map_or(0, |previous| closed[previous].open_time),
+                };
+
+                for candle in closed.into_iter().filter(|c| c.open_time > since) {
+                    last_candle.insert(symbol.clone(), candle.open_time);
+                    if !send(&sender, symbol, MarketEvent::Candle(candle)) {
+                        return;
+                    }
+                }
+            }
+
+            thread::sleep(every);
+        }
+    })
+}
+
+struct BinanceForwarder {
+    symbol: String,
+    pair: Pair,
+    sender: Sender<FeedEvent>,
+}
+
+impl binance::websockets::KlineEventHandler for BinanceForwarder {
+    fn kline_handler(&self, event: &binance::model::KlineEvent) {
+        let kline = &event.kline;
+        if !kline.is_final_bar {
+            return;
+        }
+
+        let candle = Candlestick {
+            open_time: kline.start_time.max(0) as u64,
+            close_time: kline.end_time.max(0) as u64,
+            open_price: kline.open.parse().unwrap_or(Decimal::ZERO),
+            close_price: kline.close.parse().unwrap_or(Decimal::ZERO),
+            high_price: kline.high.parse().unwrap_or(Decimal::ZERO),
+            low_price: kline.low.parse().unwrap_or(Decimal::ZERO),
+            volume: kline.volume.parse().unwrap_or(Decimal::ZERO),
+            quote_volume: kline.quote_volume.parse().ok(),
+            number_of_trades: kline.number_of_trades.max(0) as u64,
+        };
+        send(&self.sender, &self.symbol, MarketEvent::Candle(candle));
+    }
+}
+
+impl binance::websockets::MarketEventHandler for BinanceForwarder {
+    fn aggregated_trades_handler(&self, event: &binance::model::TradesEvent) {
+        let trade = Trade {
+            fee: Decimal::ZERO,
+            fee_symbol: None,
+            id: event.aggregated_trade_id.to_string(),
+            pair: self.pair.clone(),
+            sale_price: event.price.parse().unwrap_or(Decimal::ZERO),
+            qty: event.qty.parse().unwrap_or(Decimal::ZERO),
+            time: crate::utils::local_datetime_from_unix(event.trade_order_time),
+            // the maker was the buyer, so the trade was taken by a seller.
+            trade_type: TradeType::is_buy(!event.is_buyer_maker),
+        };
+        send(&self.sender, &self.symbol, MarketEvent::Trade(trade));
+    }
+
+    fn depth_orderbook_handler(&self, _event: &binance::model::DepthOrderBookEvent) {}
+
+    fn partial_orderbook_handler(&self, _order_book: &binance::model::OrderBook) {}
+}
+
+/// Streams closed candles of `interval` and aggregated trades for a binance market, one
+/// websocket each. The binance client reads until the connection fails.
+pub fn binance_feed(
+    exchange: &dyn ExchangeAPI,
+    symbol: &str,
+    interval: Interval,
+    sender: Sender<FeedEvent>,
+) -> CoreResult<Vec<JoinHandle<()>>> {
+    let pair = exchange.pair(symbol)?;
+    let streams = vec![
+        format!("{}@kline_{}", symbol.to_lowercase(), interval),
+        format!("{}@aggTrade", symbol.to_lowercase()),
+    ];
+
+    Ok(streams
+        .into_iter()
+        .map(|stream| {
+            let forwarder = BinanceForwarder {
+                symbol: symbol.to_string(),
+                pair: pair.clone(),
+                sender: sender.clone(),
+            };
+
+            thread::spawn(move || {
+                let mut socket = binance::websockets::WebSockets::new();
+                if stream.ends_with("aggTrade") {
+                    socket.add_market_handler(forwarder);
+                } else {
+                    socket.add_kline_handler(forwarder);
+                }
+
+                match socket.connect(&stream) {
+                    Ok(()) => socket.event_loop(),
+                    Err(e) => warn!("binance: {} failed to connect: {}", stream, e),
+                }
+            })
+        })
+        .collect())
+}
+
+fn okex_millis(timestamp: &str) -> u64 {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|time| time.timestamp_millis().max(0) as u64)
+        .unwrap_or(0)
+}
+
+/// Streams book tickers, full depth and closed candles of `interval` for okex markets, over one
+/// reconnecting websocket, until `running` is cleared or the receiver is dropped.
+pub fn okex_feed(
+    exchange: &dyn ExchangeAPI,
+    symbols: &[String],
+    interval: Interval,
+    sender: Sender<FeedEvent>,
+    running: Arc<AtomicBool>,
+) -> CoreResult<JoinHandle<()>> {
+    use okex::websockets::{Channel, WebSockets, WebsocketEvent};
+
+    let granularity = interval.okex_granularity().ok_or_else(|| {
+        Box::new(TrailerError::MissingArgumentError(format!(
+            "unsupported interval: {}",
+            interval
+        )))
+    })?;
+    let width = u64::from(granularity) * 1000;
+    let instruments = exchange.instruments()?;
+
+    let channels: Vec<Channel> = symbols
+        .iter()
+        .flat_map(|symbol| {
+            vec![
+                Channel::Ticker(symbol.clone()),
+                Channel::Candle(granularity, symbol.clone()),
+                Channel::Depth(symbol.clone()),
+            ]
+        })
+        .collect();
+
+    Ok(thread::spawn(move || {
+        let stop = running.clone();
+        let mut building: HashMap<String, Candlestick> = HashMap::new();
+
+        let mut socket = WebSockets::new(|event: WebsocketEvent| {
+            let (symbol, event) = match event {
+                WebsocketEvent::Ticker(ticker) => {
+                    let pair = match instruments.pair(&ticker.instrument_id) {
+                        Some(pair) => pair,
+                        None => return Ok(()),
+                    };
+                    let time = okex_millis(&ticker.timestamp);
+                    let book_ticker = BookTicker {
+                        pair,
+                        bid_price: ticker.best_bid,
+                        bid_qty: ticker.best_bid_size,
+                        ask_price: ticker.best_ask,
+                        ask_qty: ticker.best_ask_size,
+                    };
+                    (
+                        ticker.instrument_id,
+                        MarketEvent::BookTicker(time, book_ticker),
+                    )
+                }
+                WebsocketEvent::Depth(book) => {
+                    let offers = |levels: &[okex::models::OkexBookLevel]| {
+                        levels
+                            .iter()
+                            .map(|level| Offer {
+                                price: level.price(),
+                                qty: level.size(),
+                            })
+                            .collect()
+                    };
+                    let depth = Depth {
+                        bids: offers(&book.bids),
+                        asks: offers(&book.asks),
+                    };
+                    let time = okex_millis(&book.timestamp);
+                    (book.instrument_id, MarketEvent::Depth(time, depth))
+                }
+                WebsocketEvent::Candle(_, update) => {
+                    let open_time = okex_millis(&update.candle.time);
+                    let candle = Candlestick {
+                        open_time,
+                        close_time: open_time + width - 1,
+                        open_price: update.candle.open,
+                        close_price: update.candle.close,
+                        high_price: update.candle.high,
+                        low_price: update.candle.low,
+                        volume: update.candle.volume,
+                        quote_volume: None,
+                        number_of_trades: 0,
+                    };
+
+                    // okex resends the open candle as it changes, the previous one closed once
+                    // a newer candle starts.
+                    match building.insert(update.instrument_id.clone(), candle) {
+                        Some(previous) if previous.open_time < open_time => {
+                            (update.instrument_id, MarketEvent::Candle(previous))
+                        }
+                        _ => return Ok(()),
+                    }
+                }
+                WebsocketEvent::Error(e) => {
+                    warn!("okex: websocket error: {}", e);
+                    return Ok(());
+                }
+                _ => return Ok(()),
+            };
+
+            if !send(&sender, &symbol, event) {
+                stop.store(false, Ordering::Relaxed);
+            }
+            Ok(())
+        });
+
+        if let Err(e) = socket
+            .subscribe(&channels)
+            .and_then(|_| socket.event_loop(&running))
+        {
+            warn!("okex: feed stopped: {}", e);
+        }
+    }))
+}
@@ -0,0 +1,294 @@
+use crate::error::*;
+use crate::exchanges::{paper_api::PaperAPI, ExchangeAPI};
+use crate::models::*;
+use crate::strategies::*;
+use chrono::prelude::*;
+use log::warn;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// What a running strategy is doing, readable from other threads through
+/// `StrategyRunner::monitor`.
+#[derive(Debug, Clone, Default)]
+pub struct RunnerStatus {
+    pub running: bool,
+    pub events: u64,
+    pub last_event: Option<DateTime<Local>>,
+    pub open_orders: Vec<Order>,
+    /// the latest fills, oldest first, at most `MAX_STATUS_FILLS`.
+    pub fills: Vec<Trade>,
+    pub errors: u64,
+    pub last_error: Option<String>,
+    /// the strategy's own `state`.
+    pub strategy: BTreeMap<String, String>,
+}
+
+/// fills kept in `RunnerStatus`, older ones are dropped.
+pub static MAX_STATUS_FILLS: usize = 1000;
+
+/// Feeds market events to a strategy and keeps track of its orders, on a live exchange or a
+/// `PaperAPI`.
+///
+/// ```rust,ignore
+/// let (sender, receiver) = std::sync::mpsc::channel();
+/// let running = Arc::new(AtomicBool::new(true));
+/// binance_feed(&binance, &symbols, Interval::OneMinute, sender, running.clone())?;
+///
+/// let mut runner = StrategyRunner::live(strategy, Box::new(binance))?;
+/// let status = runner.monitor();
+/// runner.run(&receiver, &running)?;
+/// ```
+pub struct StrategyRunner<S: Strategy> {
+    strategy: S,
+    exchange: Box<dyn ExchangeAPI>,
+    paper: Option<PaperAPI>,
+    orders: Vec<Order>,
+    sync_every: Duration,
+    last_sync: Option<Instant>,
+    status: Arc<Mutex<RunnerStatus>>,
+}
+
+impl<S: Strategy> StrategyRunner<S> {
+    /// Orders are followed through the exchange's open orders, fails with the exchange's
+    /// `Unsupported` where they cannot be listed, eg. Huobi and OKEx.
+    pub fn live(strategy: S, exchange: Box<dyn ExchangeAPI>) -> CoreResult<Self> {
+        exchange.open_orders()?;
+        Ok(Self::new(strategy, exchange))
+    }
+
+    /// Trades on a paper exchange, filling orders against the incoming market events.
+    pub fn paper(strategy: S, paper: PaperAPI) -> Self {
+        StrategyRunner {
+            paper: Some(paper.clone()),
+            ..Self::new(strategy, Box::new(paper))
+        }
+    }
+
+    fn new(strategy: S, exchange: Box<dyn ExchangeAPI>) -> Self {
+        StrategyRunner {
+            strategy,
+            exchange,
+            paper: None,
+            orders: Vec::new(),
+            sync_every: Duration::from_secs(10),
+            last_sync: None,
+            status: Arc::new(Mutex::new(RunnerStatus::default())),
+        }
+    }
+
+    /// how often open orders are checked on a live exchange, paper orders are checked after
+    /// every event.
+    pub fn sync_every(mut self, every: Duration) -> Self {
+        self.sync_every = every;
+        self
+    }
+
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    pub fn exchange(&self) -> &dyn ExchangeAPI {
+        self.exchange.as_ref()
+    }
+
+    /// shared status, updated as the runner goes.
+    pub fn monitor(&self) -> Arc<Mutex<RunnerStatus>> {
+        self.status.clone()
+    }
+
+    pub fn status(&self) -> RunnerStatus {
+        self.status.lock().expect("runner status").clone()
+    }
+
+    /// Passes one event to the strategy, first filling paper orders against it.
+    pub fn handle(&mut self, feed_event: &FeedEvent) -> CoreResult<()> {
+        let exchange = self.exchange.as_ref();
+        let symbol = feed_event.symbol.as_str();
+
+        {
+            let mut status = self.status.lock().expect("runner status");
+            status.events += 1;
+            status.last_event = Some(Local::now());
+        }
+
+        if let (Some(paper), Some(quote)) = (&self.paper, feed_event.event.quote()) {
+            for fill in paper.update(symbol, &quote) {
+                self.record_fill(&fill);
+                self.strategy.on_fill(exchange, &fill)?;
+            }
+        }
+
+        match &feed_event.event {
+            MarketEvent::Candle(candle) => self.strategy.on_candle(exchange, symbol, candle)?,
+            MarketEvent::Trade(trade) => self.strategy.on_trade(exchange, symbol, trade)?,
+            MarketEvent::BookTicker(_, ticker) => {
+                if let Some(paper) = &self.paper {
+                    paper.update_book_ticker(symbol, ticker);
+                }
+                self.strategy.on_book_ticker(exchange, symbol, ticker)?
+            }
+            MarketEvent::Depth(_, depth) => self.strategy.on_depth(exchange, symbol, depth)?,
+        }
+
+        if self.paper.is_some() {
+            self.sync_orders()?;
+        }
+
+        self.status.lock().expect("runner status").strategy = self.strategy.state();
+        Ok(())
+    }
+
+    /// Compares the exchange's open orders with the last known ones and tells the strategy what
+    /// changed. On a live exchange fills are only seen here, from the growth of each order's
+    /// executed quantity and without their fees. An order that left the book is looked up in
+    /// `past_orders`, or by id where those are not listed, and reported as `Closed` if neither
+    /// knows it.
+    pub fn sync_orders(&mut self) -> CoreResult<()> {
+        let exchange = self.exchange.as_ref();
+        let open = exchange.open_orders()?;
+
+        let gone: Vec<Order> = self
+            .orders
+            .iter()
+            .filter(|known| !open.iter().any(|order| order.id == known.id))
+            .cloned()
+            .collect();
+        let placed: Vec<Order> = open
+            .iter()
+            .filter(|order| !self.orders.iter().any(|known| known.id == order.id))
+            .cloned()
+            .collect();
+        let partial: Vec<(Order, Decimal)> = open
+            .iter()
+            .map(|order| {
+                let before = self
+                    .orders
+                    .iter()
+                    .find(|known| known.id == order.id)
+                    .map_or(Decimal::ZERO, |known| known.executed_qty);
+                (order.clone(), order.executed_qty - before)
+            })
+            .filter(|(_, filled)| *filled > Decimal::ZERO)
+            .collect();
+
+        self.orders = open;
+        self.last_sync = Some(Instant::now());
+        self.status.lock().expect("runner status").open_orders = self.orders.clone();
+
+        let past = if gone.is_empty() {
+            Vec::new()
+        } else {
+            exchange.past_orders().unwrap_or_else(|e| {
+                warn!("{}: past orders: {}", exchange.display(), e);
+                Vec::new()
+            })
+        };
+
+        for order in placed {
+            self.strategy
+                .on_order(exchange, &OrderEvent::Placed(order))?;
+        }
+
+        for (order, filled) in partial {
+            if let Some(fill) = self.live_fill(&order, filled) {
+                self.record_fill(&fill);
+                self.strategy.on_fill(exchange, &fill)?;
+            }
+        }
+
+        for order in gone {
+            let last = match past.iter().find(|past| past.id == order.id) {
+                Some(past) => Some(past.clone()),
+                None => exchange
+                    .native_symbol(&order.pair)
+                    .and_then(|symbol| exchange.order(&symbol, &order.id))
+                    .ok(),
+            };
+
+            match last {
+                Some(last) if last.executed_qty > Decimal::ZERO => {
+                    if let Some(fill) =
+                        self.live_fill(&last, last.executed_qty - order.executed_qty)
+                    {
+                        self.record_fill(&fill);
+                        self.strategy.on_fill(exchange, &fill)?;
+                    }
+                    self.strategy
+                        .on_order(exchange, &OrderEvent::Filled(last))?;
+                }
+                _ => self
+                    .strategy
+                    .on_order(exchange, &OrderEvent::Closed(order))?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The fill for `qty` newly executed on a live order, paper fills come from the paper
+    /// exchange instead.
+    fn live_fill(&self, order: &Order, qty: Decimal) -> Option<Trade> {
+        if self.paper.is_some() || qty <= Decimal::ZERO {
+            return None;
+        }
+
+        Some(Trade {
+            fee: Decimal::ZERO,
+            fee_symbol: None,
+            id: order.id.clone(),
+            pair: order.pair.clone(),
+            sale_price: order.purchase_price,
+            qty,
+            time: Local::now(),
+            trade_type: order.trade_type,
+        })
+    }
+
+    fn record_fill(&self, fill: &Trade) {
+        let mut status = self.status.lock().expect("runner status");
+        status.fills.push(fill.clone());
+        if status.fills.len() > MAX_STATUS_FILLS {
+            let excess = status.fills.len() - MAX_STATUS_FILLS;
+            status.fills.drain(..excess);
+        }
+    }
+
+    /// Handles events until `running` is cleared or every feed has hung up. Errors from the
+    /// strategy or the exchange are logged and kept in the status, they don't stop the runner.
+    pub fn run(&mut self, events: &Receiver<FeedEvent>, running: &AtomicBool) -> CoreResult<()> {
+        self.status.lock().expect("runner status").running = true;
+
+        while running.load(Ordering::Relaxed) {
+            let result = match events.recv_timeout(Duration::from_millis(250)) {
+                Ok(event) => self.handle(&event),
+                Err(RecvTimeoutError::Timeout) => Ok(()),
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            self.record(result);
+
+            let due = self
+                .last_sync
+                .map_or(true, |last| last.elapsed() >= self.sync_every);
+            if due {
+                let result = self.sync_orders();
+                self.record(result);
+            }
+        }
+
+        self.status.lock().expect("runner status").running = false;
+        Ok(())
+    }
+
+    fn record(&mut self, result: CoreResult<()>) {
+        if let Err(e) = result {
+            warn!("{}: strategy error: {}", self.exchange.display(), e);
+            let mut status = self.status.lock().expect("runner status");
+            status.errors += 1;
+            status.last_error = Some(e.to_string());
+        }
+    }
+}
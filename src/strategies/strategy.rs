@@ -0,0 +1,78 @@
+#![allow(unused_variables)]
+
+use crate::error::*;
+use crate::exchanges::ExchangeAPI;
+use crate::models::*;
+use std::collections::BTreeMap;
+
+/// A change to one of the strategy's orders, seen by comparing the exchange's open orders.
+#[derive(Debug, Clone)]
+pub enum OrderEvent {
+    Placed(Order),
+    Filled(Order),
+    /// gone from the book without filling, eg. cancelled or expired.
+    Closed(Order),
+}
+
+impl OrderEvent {
+    pub fn order(&self) -> &Order {
+        match self {
+            OrderEvent::Placed(order) | OrderEvent::Filled(order) | OrderEvent::Closed(order) => {
+                order
+            }
+        }
+    }
+}
+
+/// A trading strategy. Callbacks receive the exchange to trade on, live or simulated, and place
+/// orders through the same `ExchangeAPI` calls either way. `symbol` is the exchange's native
+/// symbol of the market the event belongs to.
+pub trait Strategy {
+    fn on_candle(
+        &mut self,
+        exchange: &dyn ExchangeAPI,
+        symbol: &str,
+        candle: &Candlestick,
+    ) -> CoreResult<()>;
+
+    fn on_trade(
+        &mut self,
+        exchange: &dyn ExchangeAPI,
+        symbol: &str,
+        trade: &Trade,
+    ) -> CoreResult<()> {
+        Ok(())
+    }
+
+    fn on_book_ticker(
+        &mut self,
+        exchange: &dyn ExchangeAPI,
+        symbol: &str,
+        ticker: &BookTicker,
+    ) -> CoreResult<()> {
+        Ok(())
+    }
+
+    fn on_depth(
+        &mut self,
+        exchange: &dyn ExchangeAPI,
+        symbol: &str,
+        depth: &Depth,
+    ) -> CoreResult<()> {
+        Ok(())
+    }
+
+    fn on_order(&mut self, exchange: &dyn ExchangeAPI, event: &OrderEvent) -> CoreResult<()> {
+        Ok(())
+    }
+
+    /// one of the strategy's own orders was filled.
+    fn on_fill(&mut self, exchange: &dyn ExchangeAPI, fill: &Trade) -> CoreResult<()> {
+        Ok(())
+    }
+
+    /// named values describing the strategy's state, shown when monitoring it.
+    fn state(&self) -> BTreeMap<String, String> {
+        BTreeMap::new()
+    }
+}
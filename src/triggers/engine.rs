@@ -0,0 +1,463 @@
+use crate::error::*;
+use crate::exchanges::{Exchange, ExchangeAPI};
+use crate::models::*;
+use crate::strategies::FeedEvent;
+use crate::triggers::*;
+use log::{info, warn};
+use rust_decimal::Decimal;
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A trigger whose condition was met, and how placing its order went.
+#[derive(Debug, Clone)]
+pub struct FiredTrigger {
+    pub trigger: Trigger,
+    /// the price that set it off.
+    pub price: Decimal,
+    /// the limit price of the order sent.
+    pub order_price: Decimal,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TriggerFile {
+    next_id: u64,
+    triggers: Vec<Trigger>,
+}
+
+/// Holds stops, take profits and trailing stops client side, for exchanges without them, and
+/// sends the orders through `ExchangeAPI` once they fire. Orders are limit orders priced
+/// `slippage` past the trigger price so they fill straight away, like a market order would.
+/// Pending triggers are kept in a json file and survive restarts.
+///
+/// ```rust,ignore
+/// let mut engine = TriggerEngine::open_default(binance.exchange())?;
+/// engine.add_oco("BTCUSDT", TradeType::Sell, qty, stop_price, take_profit)?;
+/// engine.add("ETHUSDT", TradeType::Sell, qty, TriggerKind::TrailingStop {
+///     offset: TrailingOffset::Percent(Decimal::new(5, 0)),
+///     best: None,
+/// })?;
+/// engine.watch(&binance, Duration::from_secs(5), &running)?;
+/// ```
+#[derive(Debug)]
+pub struct TriggerEngine {
+    path: Option<PathBuf>,
+    next_id: u64,
+    triggers: Vec<Trigger>,
+    slippage: Decimal,
+}
+
+impl TriggerEngine {
+    /// an engine which forgets its triggers when dropped.
+    pub fn new() -> Self {
+        TriggerEngine {
+            path: None,
+            next_id: 0,
+            triggers: Vec::new(),
+            slippage: Decimal::new(1, 2),
+        }
+    }
+
+    /// Loads the triggers kept at `path`, starting empty if there is no file yet.
+    pub fn open<P: AsRef<Path>>(path: P) -> CoreResult<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let file: TriggerFile = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            TriggerFile::default()
+        };
+
+        Ok(TriggerEngine {
+            path: Some(path),
+            next_id: file.next_id,
+            triggers: file.triggers,
+            ..TriggerEngine::new()
+        })
+    }
+
+    /// `~/.crypto/triggers_<exchange>.json`, next to the config file. Each exchange keeps its
+    /// own triggers, as symbols are the exchange's native ones.
+    pub fn open_default(exchange: Exchange) -> CoreResult<Self> {
+        let home_path =
+            dirs::home_dir().ok_or_else(|| TrailerError::Generic(format!("cannot get homedir")))?;
+
+        Self::open(
+            home_path
+                .join(".crypto")
+                .join(format!("triggers_{}.json", exchange.to_string())),
+        )
+    }
+
+    /// how far past the trigger price orders are priced, eg. `0.01` for 1%.
+    pub fn slippage(mut self, slippage: Decimal) -> Self {
+        self.slippage = slippage;
+        self
+    }
+
+    pub fn triggers(&self) -> &[Trigger] {
+        &self.triggers
+    }
+
+    pub fn add(
+        &mut self,
+        symbol: &str,
+        side: TradeType,
+        qty: Decimal,
+        kind: TriggerKind,
+    ) -> CoreResult<u64> {
+        let id = self.push(symbol, side, qty, kind, None)?;
+        self.save()?;
+        Ok(id)
+    }
+
+    /// Sells `qty` at whichever comes first: the price falling to `stop_price` or rising to
+    /// `take_profit`. Buys mirror it, covering a short once the price rises to `stop_price` or
+    /// falls to `take_profit`. Returns the ids of both triggers.
+    pub fn add_oco(
+        &mut self,
+        symbol: &str,
+        side: TradeType,
+        qty: Decimal,
+        stop_price: Decimal,
+        take_profit: Decimal,
+    ) -> CoreResult<(u64, u64)> {
+        let (valid, position) = match side {
+            TradeType::Sell => (stop_price < take_profit, "below"),
+            TradeType::Buy => (stop_price > take_profit, "above"),
+        };
+        if !valid {
+            return Err(Box::new(TrailerError::MissingArgumentError(format!(
+                "{} stop {} must be {} take profit {}",
+                side, stop_price, position, take_profit
+            ))));
+        }
+
+        let group = self.next_id + 1;
+        let stop = self.push(
+            symbol,
+            side,
+            qty,
+            TriggerKind::StopMarket { stop_price },
+            Some(group),
+        )?;
+        let profit = self.push(
+            symbol,
+            side,
+            qty,
+            TriggerKind::TakeProfit { price: take_profit },
+            Some(group),
+        )?;
+
+        self.save()?;
+        Ok((stop, profit))
+    }
+
+    /// removes a trigger, returning whether it existed.
+    pub fn cancel(&mut self, id: u64) -> CoreResult<bool> {
+        let before = self.triggers.len();
+        self.triggers.retain(|trigger| trigger.id != id);
+
+        if self.triggers.len() == before {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Checks the triggers for `symbol` against its latest price, sending the orders of those that
+    /// fire, rounded to the market's step and tick sizes. Triggers whose order fails stay pending
+    /// and are tried again on the next price.
+    pub fn on_price(
+        &mut self,
+        exchange: &dyn ExchangeAPI,
+        symbol: &str,
+        price: Decimal,
+    ) -> CoreResult<Vec<FiredTrigger>> {
+        let mut fired: Vec<FiredTrigger> = Vec::new();
+        let mut changed = false;
+
+        if !self.triggers.iter().any(|t| t.symbol == symbol) {
+            return Ok(fired);
+        }
+        let instruments = exchange.instruments()?;
+        let rules = instruments.rules(symbol);
+
+        for trigger in self.triggers.iter_mut().filter(|t| t.symbol == symbol) {
+            let before = trigger.clone();
+            let fires = trigger.update(price);
+            changed |= *trigger != before;
+
+            if fires {
+                let order_price = match trigger.side {
+                    TradeType::Sell => price * (Decimal::ONE - self.slippage),
+                    TradeType::Buy => price * (Decimal::ONE + self.slippage),
+                };
+                let (qty, order_price) = match rules {
+                    Some(rules) => (rules.round_qty(trigger.qty), rules.round_price(order_price)),
+                    None => (trigger.qty, order_price.round_dp(8)),
+                };
+
+                let result = match trigger.side {
+                    TradeType::Sell => exchange.limit_sell(symbol, qty, order_price),
+                    TradeType::Buy => exchange.limit_buy(symbol, qty, order_price),
+                };
+
+                match &result {
+                    Ok(()) => info!("{} fired at {}", trigger, price),
+                    Err(e) => warn!("{} fired at {} but the order failed: {}", trigger, price, e),
+                }
+
+                fired.push(FiredTrigger {
+                    trigger: trigger.clone(),
+                    price,
+                    order_price,
+                    error: result.err().map(|e| e.to_string()),
+                });
+            }
+        }
+
+        for done in fired.iter().filter(|fired| fired.error.is_none()) {
+            let trigger = &done.trigger;
+            self.triggers.retain(|pending| {
+                pending.id != trigger.id
+                    && (trigger.oco_group.is_none() || pending.oco_group != trigger.oco_group)
+            });
+            changed = true;
+        }
+
+        if changed {
+            self.save()?;
+        }
+        Ok(fired)
+    }
+
+    /// Checks every pending trigger against the exchange's current prices.
+    pub fn poll(&mut self, exchange: &dyn ExchangeAPI) -> CoreResult<Vec<FiredTrigger>> {
+        if self.triggers.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut fired = Vec::new();
+        for price in exchange.all_prices()? {
+            let symbol = match exchange.native_symbol(&price.pair) {
+                Ok(symbol) => symbol,
+                Err(_) => continue,
+            };
+            if self.triggers.iter().any(|trigger| trigger.symbol == symbol) {
+                fired.append(&mut self.on_price(exchange, &symbol, price.price)?);
+            }
+        }
+        Ok(fired)
+    }
+
+    /// Checks the triggers against a websocket or replayed market event.
+    pub fn on_event(
+        &mut self,
+        exchange: &dyn ExchangeAPI,
+        event: &FeedEvent,
+    ) -> CoreResult<Vec<FiredTrigger>> {
+        match event.event.quote() {
+            Some(quote) => self.on_price(exchange, &event.symbol, quote.last),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Polls the exchange every `every` until `running` is cleared. Failed polls are logged and
+    /// retried.
+    pub fn watch(
+        &mut self,
+        exchange: &dyn ExchangeAPI,
+        every: Duration,
+        running: &AtomicBool,
+    ) -> CoreResult<()> {
+        while running.load(Ordering::Relaxed) {
+            if let Err(e) = self.poll(exchange) {
+                warn!("{}: polling triggers failed: {}", exchange.display(), e);
+            }
+            thread::sleep(every);
+        }
+        Ok(())
+    }
+
+    fn push(
+        &mut self,
+        symbol: &str,
+        side: TradeType,
+        qty: Decimal,
+        kind: TriggerKind,
+        oco_group: Option<u64>,
+    ) -> CoreResult<u64> {
+        if qty <= Decimal::ZERO {
+            return Err(Box::new(TrailerError::MissingArgumentError(format!(
+                "trigger quantity must be positive: {}",
+                qty
+            ))));
+        }
+
+        self.next_id += 1;
+        self.triggers.push(Trigger {
+            id: self.next_id,
+            symbol: symbol.to_string(),
+            side,
+            qty,
+            kind,
+            oco_group,
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_millis() as u64)
+                .unwrap_or(0),
+        });
+        Ok(self.next_id)
+    }
+
+    /// Writes the triggers to a temporary file first, a crash mid write leaves the old ones.
+    fn save(&self) -> CoreResult<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let file = TriggerFile {
+            next_id: self.next_id,
+            triggers: self.triggers.clone(),
+        };
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_string_pretty(&file)?)?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+impl Default for TriggerEngine {
+    fn default() -> Self {
+        TriggerEngine::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::paper_api::PaperAPI;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    /// 1000 USDT and 10 BTC.
+    fn paper() -> PaperAPI {
+        let paper = PaperAPI::new().market("BTCUSDT", &Pair::new("BTC", "USDT"));
+        paper.deposit("USDT", dec("1000"));
+        paper.deposit("BTC", dec("10"));
+        paper
+    }
+
+    #[test]
+    fn oco_removes_the_other_leg_once_one_fires() {
+        let paper = paper();
+        let mut engine = TriggerEngine::new();
+        let (stop, profit) = engine
+            .add_oco("BTCUSDT", TradeType::Sell, dec("1"), dec("90"), dec("110"))
+            .unwrap();
+        assert_ne!(stop, profit);
+
+        assert!(engine
+            .on_price(&paper, "BTCUSDT", dec("100"))
+            .unwrap()
+            .is_empty());
+        assert_eq!(engine.triggers().len(), 2);
+
+        let fired = engine.on_price(&paper, "BTCUSDT", dec("110")).unwrap();
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].trigger.id, profit);
+        assert_eq!(fired[0].order_price, dec("108.9"));
+        assert!(fired[0].error.is_none());
+        assert!(engine.triggers().is_empty());
+
+        let orders = paper.open_orders().unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].trade_type, TradeType::Sell);
+        assert_eq!(orders[0].purchase_price, dec("108.9"));
+    }
+
+    #[test]
+    fn buy_oco_mirrors_the_sell_side() {
+        let paper = paper();
+        let mut engine = TriggerEngine::new();
+        assert!(engine
+            .add_oco("BTCUSDT", TradeType::Buy, dec("1"), dec("90"), dec("110"))
+            .is_err());
+        let (stop, _) = engine
+            .add_oco("BTCUSDT", TradeType::Buy, dec("1"), dec("110"), dec("90"))
+            .unwrap();
+
+        assert!(engine
+            .on_price(&paper, "BTCUSDT", dec("100"))
+            .unwrap()
+            .is_empty());
+        let fired = engine.on_price(&paper, "BTCUSDT", dec("111")).unwrap();
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].trigger.id, stop);
+        assert_eq!(fired[0].order_price, dec("112.11"));
+        assert!(engine.triggers().is_empty());
+        assert_eq!(paper.open_orders().unwrap()[0].trade_type, TradeType::Buy);
+    }
+
+    #[test]
+    fn failed_orders_leave_the_trigger_pending() {
+        let paper = paper();
+        let mut engine = TriggerEngine::new();
+        engine
+            .add(
+                "BTCUSDT",
+                TradeType::Sell,
+                dec("20"),
+                TriggerKind::StopMarket {
+                    stop_price: dec("90"),
+                },
+            )
+            .unwrap();
+
+        let fired = engine.on_price(&paper, "BTCUSDT", dec("89")).unwrap();
+        assert_eq!(fired.len(), 1);
+        assert!(fired[0].error.is_some());
+        assert_eq!(engine.triggers().len(), 1);
+
+        paper.deposit("BTC", dec("10"));
+        let fired = engine.on_price(&paper, "BTCUSDT", dec("88")).unwrap();
+        assert!(fired[0].error.is_none());
+        assert!(engine.triggers().is_empty());
+    }
+
+    #[test]
+    fn other_symbols_are_left_alone() {
+        let paper = paper();
+        let mut engine = TriggerEngine::new();
+        engine
+            .add(
+                "BTCUSDT",
+                TradeType::Sell,
+                dec("1"),
+                TriggerKind::StopMarket {
+                    stop_price: dec("90"),
+                },
+            )
+            .unwrap();
+
+        assert!(engine
+            .on_price(&paper, "ETHUSDT", dec("1"))
+            .unwrap()
+            .is_empty());
+        assert_eq!(engine.triggers().len(), 1);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::models::*;
+use rust_decimal::Decimal;
+use serde_derive::{Deserialize, Serialize};
+use std::{fmt, fmt::Display};
+
+/// How far the price may move back from its best before a trailing stop fires.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TrailingOffset {
+    /// eg. `5` for 5%.
+    Percent(Decimal),
+    /// in the quote asset.
+    Absolute(Decimal),
+}
+
+impl TrailingOffset {
+    fn distance(&self, price: Decimal) -> Decimal {
+        match self {
+            TrailingOffset::Percent(percent) => price * *percent / Decimal::new(100, 0),
+            TrailingOffset::Absolute(amount) => *amount,
+        }
+    }
+}
+
+/// The condition a trigger waits for. Conditions are written for sells, exiting a holding; buy
+/// triggers mirror them, eg. a buy stop fires when the price rises to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TriggerKind {
+    /// fires when the price falls to `stop_price`.
+    StopMarket { stop_price: Decimal },
+    /// fires when the price rises to `price`.
+    TakeProfit { price: Decimal },
+    /// fires when the price falls `offset` below the best price seen since the trigger was set.
+    TrailingStop {
+        offset: TrailingOffset,
+        best: Option<Decimal>,
+    },
+}
+
+/// An order held client side until its condition is met.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trigger {
+    pub id: u64,
+    /// the exchange's native symbol.
+    pub symbol: String,
+    pub side: TradeType,
+    pub qty: Decimal,
+    pub kind: TriggerKind,
+    /// triggers in the same group cancel each other once one fires (one cancels the other).
+    pub oco_group: Option<u64>,
+    /// unix millis.
+    pub created: u64,
+}
+
+impl Trigger {
+    /// the price the trigger fires at, as things stand.
+    pub fn trigger_price(&self) -> Option<Decimal> {
+        match &self.kind {
+            TriggerKind::StopMarket { stop_price } => Some(*stop_price),
+            TriggerKind::TakeProfit { price } => Some(*price),
+            TriggerKind::TrailingStop { offset, best } => best.map(|best| match self.side {
+                TradeType::Sell => best - offset.distance(best),
+                TradeType::Buy => best + offset.distance(best),
+            }),
+        }
+    }
+
+    /// Feeds the latest price, returning whether the trigger fires. Trailing stops follow the
+    /// price here.
+    pub fn update(&mut self, price: Decimal) -> bool {
+        let sell = self.side == TradeType::Sell;
+
+        if let TriggerKind::TrailingStop { best, .. } = &mut self.kind {
+            let improved = match best {
+                Some(best) if sell => price > *best,
+                Some(best) => price < *best,
+                None => true,
+            };
+            if improved {
+                *best = Some(price);
+            }
+        }
+
+        match (&self.kind, self.trigger_price()) {
+            (TriggerKind::TakeProfit { .. }, Some(target)) if sell => price >= target,
+            (TriggerKind::TakeProfit { .. }, Some(target)) => price <= target,
+            (_, Some(stop)) if sell => price <= stop,
+            (_, Some(stop)) => price >= stop,
+            (_, None) => false,
+        }
+    }
+}
+
+impl Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match &self.kind {
+            TriggerKind::StopMarket { .. } => "stop".to_string(),
+            TriggerKind::TakeProfit { .. } => "take profit".to_string(),
+            TriggerKind::TrailingStop {
+                offset: TrailingOffset::Percent(percent),
+                ..
+            } => format!("trailing {}%", percent),
+            TriggerKind::TrailingStop {
+                offset: TrailingOffset::Absolute(amount),
+                ..
+            } => format!("trailing {}", amount),
+        };
+
+        write!(
+            f,
+            "#{} {} {} {} {} at {}",
+            self.id,
+            kind,
+            self.side,
+            self.qty,
+            self.symbol,
+            self.trigger_price()
+                .map_or("-".to_string(), |price| price.to_string())
+        )?;
+
+        if let Some(group) = self.oco_group {
+            write!(f, " (oco {})", group)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn trigger(side: TradeType, kind: TriggerKind) -> Trigger {
+        Trigger {
+            id: 1,
+            symbol: "BTCUSDT".to_string(),
+            side,
+            qty: Decimal::ONE,
+            kind,
+            oco_group: None,
+            created: 0,
+        }
+    }
+
+    fn trailing(side: TradeType, offset: TrailingOffset) -> Trigger {
+        trigger(side, TriggerKind::TrailingStop { offset, best: None })
+    }
+
+    #[test]
+    fn stop_fires_at_its_level() {
+        let mut sell = trigger(
+            TradeType::Sell,
+            TriggerKind::StopMarket {
+                stop_price: dec("90"),
+            },
+        );
+        assert!(!sell.update(dec("90.01")));
+        assert!(sell.update(dec("90")));
+        assert!(sell.update(dec("85")));
+
+        let mut buy = trigger(
+            TradeType::Buy,
+            TriggerKind::StopMarket {
+                stop_price: dec("110"),
+            },
+        );
+        assert!(!buy.update(dec("109.99")));
+        assert!(buy.update(dec("110")));
+    }
+
+    #[test]
+    fn take_profit_fires_at_its_level() {
+        let mut sell = trigger(
+            TradeType::Sell,
+            TriggerKind::TakeProfit { price: dec("110") },
+        );
+        assert!(!sell.update(dec("109.99")));
+        assert!(sell.update(dec("110")));
+
+        let mut buy = trigger(TradeType::Buy, TriggerKind::TakeProfit { price: dec("90") });
+        assert!(!buy.update(dec("90.01")));
+        assert!(buy.update(dec("90")));
+    }
+
+    #[test]
+    fn trailing_stop_follows_the_best_price_by_percent() {
+        let mut stop = trailing(TradeType::Sell, TrailingOffset::Percent(dec("10")));
+        assert_eq!(stop.trigger_price(), None);
+
+        assert!(!stop.update(dec("100")));
+        assert_eq!(stop.trigger_price(), Some(dec("90")));
+        assert!(!stop.update(dec("120")));
+        assert_eq!(stop.trigger_price(), Some(dec("108")));
+        // falling back does not lower the stop.
+        assert!(!stop.update(dec("110")));
+        assert_eq!(stop.trigger_price(), Some(dec("108")));
+        assert!(stop.update(dec("108")));
+    }
+
+    #[test]
+    fn trailing_stop_follows_the_best_price_by_amount() {
+        let mut stop = trailing(TradeType::Sell, TrailingOffset::Absolute(dec("5")));
+
+        assert!(!stop.update(dec("100")));
+        assert!(!stop.update(dec("110")));
+        assert!(!stop.update(dec("105.01")));
+        assert_eq!(stop.trigger_price(), Some(dec("105")));
+        assert!(stop.update(dec("105")));
+    }
+
+    #[test]
+    fn buy_trailing_stop_follows_the_lowest_price() {
+        let mut percent = trailing(TradeType::Buy, TrailingOffset::Percent(dec("10")));
+        assert!(!percent.update(dec("100")));
+        assert_eq!(percent.trigger_price(), Some(dec("110")));
+        assert!(!percent.update(dec("80")));
+        assert!(!percent.update(dec("87")));
+        assert_eq!(percent.trigger_price(), Some(dec("88")));
+        assert!(percent.update(dec("88")));
+
+        let mut amount = trailing(TradeType::Buy, TrailingOffset::Absolute(dec("5")));
+        assert!(!amount.update(dec("100")));
+        assert!(!amount.update(dec("90")));
+        assert!(!amount.update(dec("94.99")));
+        assert!(amount.update(dec("95")));
+    }
+}
@@ -3,6 +3,7 @@
 
 use chrono::offset::Local;
 use chrono::prelude::DateTime;
+use rust_decimal::Decimal;
 use std::time::{Duration, UNIX_EPOCH};
 
 /// Converts a unix timestamp to a rust DateTime.
@@ -10,28 +11,29 @@ pub fn local_datetime_from_unix(time: u64) -> DateTime<Local> {
     DateTime::<Local>::from(UNIX_EPOCH + Duration::from_millis(time))
 }
 
-/// Expresses the difference as a percentage between two floats.
+/// Expresses the difference as a percentage between two prices, zero without an entry price.
 ///
 /// ```rust
-/// use cryptotrader::presenters::price_percent;
-/// assert_eq!(price_percent(5.0, 10.0), 100.0);
-/// assert_eq!(price_percent(100.0, 50.0), -50.0);
+/// # use cryptotrader::utils::price_percent;
+/// # use rust_decimal::Decimal;
+/// assert_eq!(price_percent(Decimal::new(5, 0), Decimal::new(10, 0)), Decimal::new(100, 0));
+/// assert_eq!(price_percent(Decimal::new(100, 0), Decimal::new(50, 0)), Decimal::new(-50, 0));
 /// ```
-pub fn price_percent(entry_price: f64, exit_price: f64) -> f64 {
-    if entry_price < exit_price {
-        (100. / entry_price * exit_price) - 100.
-    } else {
-        -(100. + -100. / entry_price * exit_price)
-    }
+pub fn price_percent(entry_price: Decimal, exit_price: Decimal) -> Decimal {
+    (exit_price - entry_price)
+        .checked_div(entry_price)
+        .map_or(Decimal::ZERO, |change| change * Decimal::new(100, 0))
 }
 
-/// Finds the average (mean) of a series of f64 floats.
+/// Finds the average (mean) of a series of decimals, zero for an empty series.
 ///
 /// ```rust
-/// assert_eq!(average(5.0, 10.0), 7.5);
-/// assert_eq!(average(50.0, 50.0, -100.0), 0.0);
+/// # use cryptotrader::utils::average;
+/// # use rust_decimal::Decimal;
+/// assert_eq!(average(&vec![Decimal::new(5, 0), Decimal::new(10, 0)]), Decimal::new(75, 1));
 /// ```
-pub fn average(numbers: &Vec<f64>) -> f64 {
-    let sum: f64 = numbers.iter().sum();
-    sum / numbers.len() as f64
+pub fn average(numbers: &Vec<Decimal>) -> Decimal {
+    let sum: Decimal = numbers.iter().sum();
+    sum.checked_div(Decimal::from(numbers.len()))
+        .unwrap_or(Decimal::ZERO)
 }